@@ -0,0 +1,149 @@
+/*
+    multi-party-ed25519
+
+    Copyright 2018 by Kzen Networks
+
+    This file is part of multi-party-ed25519 library
+    (https://github.com/KZen-networks/multisig-schnorr)
+
+    multi-party-ed25519 is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-ed25519/blob/master/LICENSE>
+*/
+
+//! Runs the protocols one round at a time, with every message in a JSON file, so that the
+//! parties don't need a network between them: each command reads the messages of the other
+//! parties, writes the ones of this party and keeps what it needs for the next round in a state
+//! file.
+
+extern crate clap;
+extern crate curv;
+extern crate hex;
+extern crate multi_party_eddsa;
+extern crate serde;
+extern crate serde_json;
+
+mod files;
+mod musig2;
+mod threshold;
+
+use clap::{value_parser, Arg, ArgMatches, Command};
+use std::convert::TryFrom;
+use std::path::{Path, PathBuf};
+use std::process;
+
+use files::{read, read_json, write_json, write_secret_json, Result};
+use multi_party_eddsa::protocols::{ExpandedKeyPair, PublicKey, Signature};
+
+fn file(id: &'static str, help: &'static str) -> Arg {
+    Arg::new(id)
+        .long(id)
+        .value_name("FILE")
+        .value_parser(value_parser!(PathBuf))
+        .required(true)
+        .help(help)
+}
+
+fn files(id: &'static str, help: &'static str) -> Arg {
+    file(id, help).num_args(1..)
+}
+
+fn index(id: &'static str, help: &'static str) -> Arg {
+    Arg::new(id)
+        .long(id)
+        .value_name("N")
+        .value_parser(value_parser!(u16))
+        .required(true)
+        .help(help)
+}
+
+fn cli() -> Command {
+    Command::new("multi-party-eddsa")
+        .about("Multi-party Ed25519 signing, one round at a time over JSON files")
+        .subcommand_required(true)
+        .subcommand(
+            Command::new("keygen")
+                .about("Creates a key pair for MuSig2")
+                .arg(file("key", "Where to write the key pair, keep it secret"))
+                .arg(file("public-key", "Where to write the public key")),
+        )
+        .subcommand(musig2::cli())
+        .subcommand(
+            Command::new("aggregate")
+                .about("Adds up MuSig2 partial signatures and checks the signature")
+                .arg(files("public-keys", "The public keys of all the signers"))
+                .arg(file("message", "The signed message"))
+                .arg(files(
+                    "partials",
+                    "The partial signatures of all the signers",
+                ))
+                .arg(file("out", "Where to write the signature"))
+                .arg(
+                    file("aggregate-key", "Where to write the aggregated public key")
+                        .required(false),
+                ),
+        )
+        .subcommand(
+            Command::new("verify")
+                .about("Verifies a signature")
+                .arg(file("public-key", "The public key"))
+                .arg(file("message", "The signed message"))
+                .arg(file("signature", "The signature")),
+        )
+        .subcommand(threshold::cli())
+}
+
+fn path<'a>(matches: &'a ArgMatches, id: &str) -> &'a Path {
+    matches
+        .get_one::<PathBuf>(id)
+        .expect("required argument")
+        .as_path()
+}
+
+fn paths<'a>(matches: &'a ArgMatches, id: &str) -> Vec<&'a Path> {
+    matches
+        .get_many::<PathBuf>(id)
+        .map(|paths| paths.map(PathBuf::as_path).collect())
+        .unwrap_or_default()
+}
+
+fn keygen(matches: &ArgMatches) -> Result<()> {
+    let keys = ExpandedKeyPair::create();
+    let public_key = PublicKey::try_from(keys.public_key.clone())?;
+    write_secret_json(path(matches, "key"), &keys)?;
+    write_json(path(matches, "public-key"), &public_key)?;
+    println!("{}", hex::encode(public_key.to_bytes()));
+    Ok(())
+}
+
+fn verify(matches: &ArgMatches) -> Result<()> {
+    let public_key: PublicKey = read_json(path(matches, "public-key"))?;
+    let message = read(path(matches, "message"))?;
+    let signature: Signature = read_json(path(matches, "signature"))?;
+    signature.verify(&message, public_key.as_point())?;
+    println!("valid signature");
+    Ok(())
+}
+
+fn run(matches: &ArgMatches) -> Result<()> {
+    match matches.subcommand() {
+        Some(("keygen", matches)) => keygen(matches),
+        Some(("musig2", matches)) => musig2::run(matches),
+        Some(("aggregate", matches)) => musig2::aggregate(matches),
+        Some(("verify", matches)) => verify(matches),
+        Some(("threshold", matches)) => threshold::run(matches),
+        _ => unreachable!("subcommand required"),
+    }
+}
+
+fn main() {
+    if let Err(error) = run(&cli().get_matches()) {
+        eprintln!("error: {}", error);
+        process::exit(1);
+    }
+}
+
+mod test;
@@ -0,0 +1,165 @@
+/*
+    multi-party-ed25519
+
+    Copyright 2018 by Kzen Networks
+
+    This file is part of multi-party-ed25519 library
+    (https://github.com/KZen-networks/multisig-schnorr)
+
+    multi-party-ed25519 is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-ed25519/blob/master/LICENSE>
+*/
+
+//! MuSig2 signing takes two commands for each signer: `nonce` before the message is signed, and
+//! `sign` once the nonces of all the signers are there. The partial signatures are then added up
+//! with `aggregate`.
+
+use clap::{ArgMatches, Command};
+use curv::elliptic::curves::{Ed25519, Point, Scalar};
+use std::convert::TryFrom;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use files::{read, read_json, write_json, write_secret_json, Result};
+use multi_party_eddsa::protocols::musig2::{self, ExportedPartialNonces, PartialSignature};
+use multi_party_eddsa::protocols::musig2::{PublicKeyAgg, PublicPartialNonces};
+use multi_party_eddsa::protocols::{ExpandedKeyPair, PublicKey, Signature, SignatureMode};
+use {file, files, path, paths};
+
+pub fn cli() -> Command {
+    Command::new("musig2")
+        .about("MuSig2 signing")
+        .subcommand_required(true)
+        .subcommand(
+            Command::new("nonce")
+                .about("Creates the nonces to sign a message with")
+                .arg(file("key", "The key pair from `keygen`"))
+                .arg(file("message", "The message to sign"))
+                .arg(file(
+                    "secret-nonces",
+                    "Where to write the secret nonces, keep them secret until `sign`",
+                ))
+                .arg(file("out", "Where to write the public nonces, for the other signers")),
+        )
+        .subcommand(
+            Command::new("sign")
+                .about("Signs with the nonces from `nonce`, which are deleted so they are never used twice")
+                .arg(file("key", "The key pair from `keygen`"))
+                .arg(file("message", "The message to sign"))
+                .arg(file("secret-nonces", "The secret nonces from `nonce`"))
+                .arg(files("public-keys", "The public keys of all the signers"))
+                .arg(files(
+                    "nonces",
+                    "The public nonces of the signers, the ones of this signer are skipped",
+                ))
+                .arg(file("out", "Where to write the partial signature")),
+        )
+}
+
+pub fn run(matches: &ArgMatches) -> Result<()> {
+    match matches.subcommand() {
+        Some(("nonce", matches)) => nonce(matches),
+        Some(("sign", matches)) => sign(matches),
+        _ => unreachable!("subcommand required"),
+    }
+}
+
+fn read_public_keys(paths: &[&Path]) -> Result<Vec<Point<Ed25519>>> {
+    paths
+        .iter()
+        .map(|path| Ok(read_json::<PublicKey>(path)?.into()))
+        .collect()
+}
+
+fn nonce(matches: &ArgMatches) -> Result<()> {
+    let keys: ExpandedKeyPair = read_json(path(matches, "key"))?;
+    let message = read(path(matches, "message"))?;
+    let (private_nonces, public_nonces) =
+        musig2::generate_partial_nonces(&keys, Some(&message), &SignatureMode::Pure);
+    write_secret_json(
+        path(matches, "secret-nonces"),
+        &private_nonces.dangerous_export(),
+    )?;
+    write_json(path(matches, "out"), &public_nonces)
+}
+
+fn sign(matches: &ArgMatches) -> Result<()> {
+    let keys: ExpandedKeyPair = read_json(path(matches, "key"))?;
+    let message = read(path(matches, "message"))?;
+    let secret_nonces = path(matches, "secret-nonces");
+    let private_nonces = read_json::<ExportedPartialNonces>(secret_nonces)?.dangerous_import();
+    // signing twice with the same nonces leaks the key, so they go before anything else can fail
+    fs::remove_file(secret_nonces)?;
+
+    let my_nonces = private_nonces.public_nonces();
+    let mut other_nonces = Vec::new();
+    for path in paths(matches, "nonces") {
+        let nonces: PublicPartialNonces = read_json(path)?;
+        if nonces != my_nonces {
+            other_nonces.push(nonces.R);
+        }
+    }
+    let public_keys = read_public_keys(&paths(matches, "public-keys"))?;
+    if other_nonces.len() + 1 != public_keys.len() {
+        return Err(format!(
+            "expected the nonces of {} other signers, got {}",
+            public_keys.len() - 1,
+            other_nonces.len()
+        )
+        .into());
+    }
+    let key_agg = PublicKeyAgg::key_aggregation_n(public_keys, &keys.public_key)
+        .ok_or("the public key of the key pair is not one of the public keys")?;
+    let partial_sig = musig2::partial_sign(
+        &other_nonces,
+        private_nonces,
+        &key_agg,
+        &keys,
+        &message,
+        &SignatureMode::Pure,
+    )?;
+    write_json(path(matches, "out"), &partial_sig)
+}
+
+pub fn aggregate(matches: &ArgMatches) -> Result<()> {
+    let public_keys = read_public_keys(&paths(matches, "public-keys"))?;
+    let message = read(path(matches, "message"))?;
+    let partial_sigs = paths(matches, "partials")
+        .into_iter()
+        .map(read_json)
+        .collect::<Result<Vec<PartialSignature>>>()?;
+    if partial_sigs.len() != public_keys.len() {
+        return Err(format!(
+            "expected {} partial signatures, got {}",
+            public_keys.len(),
+            partial_sigs.len()
+        )
+        .into());
+    }
+    if partial_sigs.iter().any(|sig| sig.R != partial_sigs[0].R) {
+        return Err("the partial signatures are for different nonces".into());
+    }
+
+    let others: Vec<Scalar<Ed25519>> = partial_sigs[1..]
+        .iter()
+        .map(|sig| sig.my_partial_s.clone())
+        .collect();
+    let signature: Signature = musig2::aggregate_partial_signatures(&partial_sigs[0], &others);
+    let agg_public_key = PublicKeyAgg::key_aggregation_n(public_keys.clone(), &public_keys[0])
+        .expect("the key is one of the keys")
+        .agg_public_key;
+    signature
+        .verify(&message, &agg_public_key)
+        .map_err(|_| "the signature doesn't verify, one of the partial signatures is wrong")?;
+
+    write_json(path(matches, "out"), &signature)?;
+    if let Some(aggregate_key) = matches.get_one::<PathBuf>("aggregate-key") {
+        write_json(aggregate_key, &PublicKey::try_from(agg_public_key)?)?;
+    }
+    println!("{}", hex::encode(&signature.to_bytes()[..]));
+    Ok(())
+}
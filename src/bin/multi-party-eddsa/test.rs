@@ -0,0 +1,222 @@
+/*
+    multi-party-ed25519
+
+    Copyright 2018 by Kzen Networks
+
+    This file is part of multi-party-ed25519 library
+    (https://github.com/KZen-networks/multisig-schnorr)
+
+    multi-party-ed25519 is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-ed25519/blob/master/LICENSE>
+*/
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+    use std::iter;
+    use std::path::{Path, PathBuf};
+    use std::process;
+
+    use files::Result;
+    use {cli, run};
+
+    // an empty directory for the files of a test
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("multi-party-eddsa-{}-{}", name, process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("messages")).unwrap();
+        fs::write(dir.join("message"), [79, 77, 69, 82]).unwrap();
+        fs::write(dir.join("other-message"), [79, 77, 69, 83]).unwrap();
+        dir
+    }
+
+    // runs the command line `args`, with the arguments starting with @ taken as paths in `dir`
+    fn cli_run(dir: &Path, args: &str) -> Result<()> {
+        let args = args
+            .split_whitespace()
+            .map(|arg| match arg.strip_prefix('@') {
+                Some(file) => dir.join(file),
+                None => PathBuf::from(arg),
+            });
+        let matches = cli()
+            .try_get_matches_from(iter::once(PathBuf::from("multi-party-eddsa")).chain(args))
+            .unwrap();
+        run(&matches)
+    }
+
+    // the messages of `round` that were written so far, as arguments
+    fn messages(dir: &Path, round: u16) -> String {
+        let prefix = format!("round{}-", round);
+        let mut files: Vec<_> = fs::read_dir(dir.join("messages"))
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .filter(|name| name.starts_with(&prefix))
+            .map(|name| format!("@messages/{}", name))
+            .collect();
+        files.sort();
+        files.join(" ")
+    }
+
+    #[test]
+    fn test_cli() {
+        cli().debug_assert();
+    }
+
+    #[test]
+    fn test_musig2_commands() {
+        let dir = &temp_dir("musig2");
+        for i in 1..=3 {
+            cli_run(dir, &format!("keygen --key @key{0} --public-key @pk{0}", i)).unwrap();
+            cli_run(
+                dir,
+                &format!(
+                    "musig2 nonce --key @key{0} --message @message --secret-nonces @secret{0} --out @nonces{0}",
+                    i
+                ),
+            )
+            .unwrap();
+        }
+        for i in 1..=3 {
+            let sign = format!(
+                "musig2 sign --key @key{0} --message @message --secret-nonces @secret{0} --public-keys @pk1 @pk2 @pk3 --nonces @nonces1 @nonces2 @nonces3 --out @partial{0}",
+                i
+            );
+            cli_run(dir, &sign).unwrap();
+            // the nonces are gone once they were used
+            assert!(cli_run(dir, &sign).is_err());
+        }
+        cli_run(
+            dir,
+            "aggregate --public-keys @pk1 @pk2 @pk3 --message @message --partials @partial1 @partial2 @partial3 --out @signature --aggregate-key @apk",
+        )
+        .unwrap();
+        assert!(cli_run(
+            dir,
+            "aggregate --public-keys @pk1 @pk2 @pk3 --message @other-message --partials @partial1 @partial2 @partial3 --out @signature2",
+        )
+        .is_err());
+
+        cli_run(
+            dir,
+            "verify --public-key @apk --message @message --signature @signature",
+        )
+        .unwrap();
+        assert!(cli_run(
+            dir,
+            "verify --public-key @apk --message @other-message --signature @signature"
+        )
+        .is_err());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_threshold_commands() {
+        let dir = &temp_dir("threshold");
+        for i in 1..=3 {
+            cli_run(
+                dir,
+                &format!(
+                    "threshold keygen round1 --index {0} --threshold 1 --parties 3 --state @state{0} --out-dir @messages",
+                    i
+                ),
+            )
+            .unwrap();
+        }
+        // the state is still waiting for round 1
+        assert!(cli_run(
+            dir,
+            &format!(
+                "threshold keygen round3 --state @state1 --messages {} --out-dir @messages",
+                messages(dir, 1)
+            ),
+        )
+        .is_err());
+        for round in 2..=3 {
+            let round_messages = messages(dir, round - 1);
+            for i in 1..=3 {
+                cli_run(
+                    dir,
+                    &format!(
+                        "threshold keygen round{} --state @state{} --messages {} --out-dir @messages",
+                        round, i, round_messages
+                    ),
+                )
+                .unwrap();
+            }
+        }
+        let shares = messages(dir, 3);
+        // the secret shares sent to a single party are only readable by their owner
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            for share in shares.split_whitespace() {
+                let path = dir.join(&share[1..]);
+                let mode = fs::metadata(path).unwrap().permissions().mode();
+                assert_eq!(mode & 0o777, 0o600);
+            }
+        }
+        for i in 1..=3 {
+            cli_run(
+                dir,
+                &format!(
+                    "threshold keygen finish --state @state{0} --messages {1} --key @key{0} --public-key @pk",
+                    i, shares
+                ),
+            )
+            .unwrap();
+        }
+
+        fs::remove_dir_all(dir.join("messages")).unwrap();
+        for i in [1, 3] {
+            cli_run(
+                dir,
+                &format!(
+                    "threshold sign round1 --key @key{0} --signers 1 3 --message @message --state @state{0} --out-dir @messages",
+                    i
+                ),
+            )
+            .unwrap();
+        }
+        for round in 2..=4 {
+            let round_messages = messages(dir, round - 1);
+            for i in [1, 3] {
+                cli_run(
+                    dir,
+                    &format!(
+                        "threshold sign round{} --state @state{} --messages {} --out-dir @messages",
+                        round, i, round_messages
+                    ),
+                )
+                .unwrap();
+            }
+        }
+        // party 3's local signature is missing
+        assert!(cli_run(
+            dir,
+            "threshold sign finish --state @state1 --messages @messages/round4-from1-toall.json --out @signature",
+        )
+        .is_err());
+        let local_sigs = messages(dir, 4);
+        for i in [1, 3] {
+            cli_run(
+                dir,
+                &format!(
+                    "threshold sign finish --state @state{} --messages {} --out @signature",
+                    i, local_sigs
+                ),
+            )
+            .unwrap();
+        }
+        cli_run(
+            dir,
+            "verify --public-key @pk --message @message --signature @signature",
+        )
+        .unwrap();
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
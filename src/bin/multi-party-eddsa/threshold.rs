@@ -0,0 +1,271 @@
+/*
+    multi-party-ed25519
+
+    Copyright 2018 by Kzen Networks
+
+    This file is part of multi-party-ed25519 library
+    (https://github.com/KZen-networks/multisig-schnorr)
+
+    multi-party-ed25519 is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-ed25519/blob/master/LICENSE>
+*/
+
+//! Threshold key generation and signing, one command per round. Each command runs the state
+//! machine of the protocol from the state file left by the previous one, and writes the messages
+//! of the round to a directory, one file per message. Messages are picked by the party from the
+//! files it's given, so a whole directory of messages can be passed to every party.
+
+use clap::{value_parser, Arg, ArgMatches, Command};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::convert::TryFrom;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use files::{read, read_json, write_json, write_secret_json, Result};
+use multi_party_eddsa::protocols::state_machine::{Msg, StateMachine};
+use multi_party_eddsa::protocols::thresholdsig::state_machine::{KeyGen, LocalKey, Signing};
+use multi_party_eddsa::protocols::thresholdsig::{Keys, Parameters};
+use multi_party_eddsa::protocols::{PublicKey, SignatureMode};
+use {file, index, path, paths};
+
+fn out_dir() -> Arg {
+    Arg::new("out-dir")
+        .long("out-dir")
+        .value_name("DIR")
+        .value_parser(value_parser!(PathBuf))
+        .required(true)
+        .help("Where to write the messages of this party")
+}
+
+fn state() -> Arg {
+    file(
+        "state",
+        "The state of this party between rounds, keep it secret",
+    )
+}
+
+fn messages(round: u16) -> Arg {
+    Arg::new("messages")
+        .long("messages")
+        .value_name("FILE")
+        .value_parser(value_parser!(PathBuf))
+        .required(true)
+        .num_args(1..)
+        .help(format!(
+            "The round {} messages, the ones of this party or for other parties are skipped",
+            round
+        ))
+}
+
+// `roundN` for the rounds after the first one, they all take the messages of the round before
+fn next_round(round: u16) -> Command {
+    let (name, about) = match round {
+        2 => ("round2", "Opens the commitments"),
+        3 => (
+            "round3",
+            "Sends the shares, each file must only reach the party it is for",
+        ),
+        _ => ("round4", "Sends the local signature"),
+    };
+    Command::new(name)
+        .about(about)
+        .arg(state())
+        .arg(messages(round - 1))
+        .arg(out_dir())
+}
+
+fn finish(last_round: u16) -> Command {
+    Command::new("finish")
+        .arg(state())
+        .arg(messages(last_round))
+}
+
+pub fn cli() -> Command {
+    Command::new("threshold")
+        .about("Threshold key generation and signing, parties are numbered from 1")
+        .subcommand_required(true)
+        .subcommand(
+            Command::new("keygen")
+                .about("Key generation, in 3 rounds")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("round1")
+                        .about("Starts key generation")
+                        .arg(index("index", "The index of this party"))
+                        .arg(index(
+                            "threshold",
+                            "Signing takes more than this many parties",
+                        ))
+                        .arg(index("parties", "The number of parties"))
+                        .arg(state())
+                        .arg(out_dir()),
+                )
+                .subcommand(next_round(2))
+                .subcommand(next_round(3))
+                .subcommand(
+                    finish(3)
+                        .about("Checks the shares and writes the key")
+                        .arg(file("key", "Where to write the key share, keep it secret"))
+                        .arg(file("public-key", "Where to write the shared public key")),
+                ),
+        )
+        .subcommand(
+            Command::new("sign")
+                .about("Signing, in 4 rounds")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("round1")
+                        .about("Starts signing")
+                        .arg(file("key", "The key share from key generation"))
+                        .arg(
+                            index("signers", "The indices of the signers, including this one")
+                                .num_args(1..),
+                        )
+                        .arg(file("message", "The message to sign"))
+                        .arg(state())
+                        .arg(out_dir()),
+                )
+                .subcommand(next_round(2))
+                .subcommand(next_round(3))
+                .subcommand(next_round(4))
+                .subcommand(
+                    finish(4)
+                        .about("Checks the local signatures and writes the signature")
+                        .arg(file("out", "Where to write the signature")),
+                ),
+        )
+}
+
+pub fn run(matches: &ArgMatches) -> Result<()> {
+    match matches.subcommand() {
+        Some(("keygen", matches)) => match matches.subcommand() {
+            Some(("round1", matches)) => keygen(matches),
+            Some(("finish", matches)) => {
+                let mut keygen: KeyGen = finish_round(matches, 3)?;
+                let local_key = keygen.pick_output()?;
+                let public_key = PublicKey::try_from(local_key.shared_keys.y.clone())?;
+                write_secret_json(path(matches, "key"), &local_key)?;
+                write_json(path(matches, "public-key"), &public_key)?;
+                fs::remove_file(path(matches, "state"))?;
+                println!("{}", hex::encode(public_key.to_bytes()));
+                Ok(())
+            }
+            Some((round, matches)) => run_round::<KeyGen>(matches, round),
+            None => unreachable!("subcommand required"),
+        },
+        Some(("sign", matches)) => match matches.subcommand() {
+            Some(("round1", matches)) => sign(matches),
+            Some(("finish", matches)) => {
+                let mut signing: Signing = finish_round(matches, 4)?;
+                let signature = signing.pick_output()?;
+                write_json(path(matches, "out"), &signature)?;
+                fs::remove_file(path(matches, "state"))?;
+                println!("{}", hex::encode(&signature.to_bytes()[..]));
+                Ok(())
+            }
+            Some((round, matches)) => run_round::<Signing>(matches, round),
+            None => unreachable!("subcommand required"),
+        },
+        _ => unreachable!("subcommand required"),
+    }
+}
+
+fn keygen(matches: &ArgMatches) -> Result<()> {
+    let party_index = *matches.get_one::<u16>("index").expect("required argument");
+    let params = Parameters {
+        threshold: *matches.get_one("threshold").expect("required argument"),
+        share_count: *matches.get_one("parties").expect("required argument"),
+    };
+    let parties = (1..=params.share_count).collect();
+    let keygen = KeyGen::new(Keys::phase1_create(party_index), params, parties)?;
+    save(keygen, matches)
+}
+
+fn sign(matches: &ArgMatches) -> Result<()> {
+    let local_key: LocalKey = read_json(path(matches, "key"))?;
+    let signers = matches
+        .get_many::<u16>("signers")
+        .expect("required argument")
+        .cloned()
+        .collect();
+    let message = read(path(matches, "message"))?;
+    let signing = Signing::new(&local_key, signers, &message, SignatureMode::Pure)?;
+    save(signing, matches)
+}
+
+fn run_round<M>(matches: &ArgMatches, round: &str) -> Result<()>
+where
+    M: StateMachine + Serialize + DeserializeOwned,
+    M::MessageBody: Serialize + DeserializeOwned,
+{
+    let round: u16 = round["round".len()..].parse().expect("roundN subcommand");
+    let machine: M = finish_round(matches, round - 1)?;
+    save(machine, matches)
+}
+
+// Loads the state, which must be waiting for the messages of `round`, and hands it the messages.
+fn finish_round<M>(matches: &ArgMatches, round: u16) -> Result<M>
+where
+    M: StateMachine + DeserializeOwned,
+    M::MessageBody: DeserializeOwned,
+{
+    let mut machine: M = read_json(path(matches, "state"))?;
+    if machine.current_round() != round {
+        return Err(format!(
+            "the state is waiting for the messages of round {}, not {}",
+            machine.current_round(),
+            round
+        )
+        .into());
+    }
+    let me = machine.party_index();
+    for path in paths(matches, "messages") {
+        let msg: Msg<M::MessageBody> = read_json(path)?;
+        if msg.sender == me || matches!(msg.receiver, Some(receiver) if receiver != me) {
+            continue;
+        }
+        machine
+            .handle_incoming(msg)
+            .map_err(|error| format!("{}: {}", path.display(), error))?;
+    }
+    if machine.current_round() == round {
+        return Err(machine.pick_output().err().expect("not finished").into());
+    }
+    Ok(machine)
+}
+
+// Writes the messages of the current round and the state to continue from. Point-to-point
+// messages hold secret shares for their receiver, so only their owner can read them.
+fn save<M>(mut machine: M, matches: &ArgMatches) -> Result<()>
+where
+    M: StateMachine + Serialize,
+    M::MessageBody: Serialize,
+{
+    let out_dir: &Path = matches
+        .get_one::<PathBuf>("out-dir")
+        .expect("required argument");
+    fs::create_dir_all(out_dir)?;
+    let round = machine.current_round();
+    for msg in machine.outgoing() {
+        let receiver = match msg.receiver {
+            Some(receiver) => receiver.to_string(),
+            None => "all".to_string(),
+        };
+        let file = out_dir.join(format!(
+            "round{}-from{}-to{}.json",
+            round, msg.sender, receiver
+        ));
+        if msg.receiver.is_some() {
+            write_secret_json(&file, &msg)?;
+        } else {
+            write_json(&file, &msg)?;
+        }
+        println!("{}", file.display());
+    }
+    write_secret_json(path(matches, "state"), &machine)
+}
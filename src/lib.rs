@@ -15,13 +15,21 @@
 */
 
 extern crate curv;
+extern crate curve25519_dalek;
 
 extern crate hex;
 #[macro_use]
 extern crate serde_derive;
 extern crate rand;
+extern crate serde;
 extern crate serde_json;
 extern crate sha2;
+extern crate zeroize;
+
+#[cfg(feature = "async")]
+extern crate futures;
+#[cfg(feature = "async")]
+extern crate tokio;
 
 #[cfg(test)]
 extern crate ed25519_dalek;
@@ -29,22 +37,134 @@ extern crate ed25519_dalek;
 extern crate itertools;
 #[cfg(test)]
 extern crate rand_xoshiro;
+#[cfg(test)]
+extern crate serde_cbor;
 
 pub mod protocols;
 
-#[derive(Copy, PartialEq, Eq, Clone, Debug)]
+/// The errors of all the protocols of this crate.
+///
+/// The variants that hold parties identify the ones at fault by their party index, as it was
+/// passed to the function that found them. The state machines wrap them in a `RoundError`, which
+/// holds the round they were found in.
+#[derive(PartialEq, Eq, Clone, Debug)]
 pub enum Error {
+    /// A key doesn't belong to the party using it, or to the group it's used with.
     InvalidKey,
-    InvalidSS,
+    /// Secret shares failed verification, holds the parties that sent them.
+    InvalidSS(Vec<u16>),
+    /// A share from the trusted dealer failed verification, holds the party that received it.
+    InvalidDealtShare(u16),
+    /// A commitment doesn't match the values or the party it's checked against.
     InvalidCom,
+    /// Commitments were opened to other values, holds the parties that opened them.
+    InvalidDecom(Vec<u16>),
     InvalidSig,
+    /// Partial signatures failed verification, holds the parties that sent them.
+    InvalidPartialSig(Vec<u16>),
+    /// Proofs of knowledge of secret keys failed verification, holds the parties that sent them.
+    InvalidProof(Vec<u16>),
+    /// Nonces were used with a different key or message than the ones they were generated for.
+    InvalidNonce,
+    /// Signatures to combine are for different nonces, holds the parties whose nonce differs from
+    /// the first one's.
+    InvalidR(Vec<u16>),
+    /// An input doesn't have the number of entries it should, e.g. one per party.
+    WrongLength {
+        expected: usize,
+        received: usize,
+    },
+    /// More than `threshold` and at most `share_count` parties are needed.
+    WrongPartyCount {
+        threshold: u16,
+        share_count: u16,
+        received: usize,
+    },
+    /// There was nothing to combine.
+    EmptyInput,
+    /// A party index that can't be used, e.g. 0 for a party whose share is dealt at its index.
+    InvalidPartyIndex(u16),
+    /// A party appears more than once, or in two roles that exclude each other.
+    DuplicateParty(u16),
+    /// A party is not one of the parties of the key or of the protocol.
+    UnknownParty(u16),
+    /// A party that has to take part is missing, e.g. a member of an n-of-n key.
+    MissingParty(u16),
+    /// A context string is longer than the 255 bytes RFC 8032 allows, holds its length.
+    ContextTooLong(usize),
 }
 
 use std::fmt;
 
+// a list of parties, as `1, 3`
+pub(crate) struct Parties<'a>(pub(crate) &'a [u16]);
+
+impl<'a> fmt::Display for Parties<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, party) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+            write!(f, "{}", party)?;
+        }
+        Ok(())
+    }
+}
+
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}", &self)
+        match self {
+            Error::InvalidKey => f.write_str("invalid key"),
+            Error::InvalidSS(parties) => {
+                write!(f, "invalid secret shares from parties {}", Parties(parties))
+            }
+            Error::InvalidDealtShare(party) => {
+                write!(f, "invalid share dealt to party {}", party)
+            }
+            Error::InvalidCom => f.write_str("invalid commitment"),
+            Error::InvalidDecom(parties) => write!(
+                f,
+                "commitments of parties {} were opened to other values",
+                Parties(parties)
+            ),
+            Error::InvalidSig => f.write_str("invalid signature"),
+            Error::InvalidPartialSig(parties) => write!(
+                f,
+                "invalid partial signatures from parties {}",
+                Parties(parties)
+            ),
+            Error::InvalidProof(parties) => write!(
+                f,
+                "invalid proofs of knowledge of the secret keys of parties {}",
+                Parties(parties)
+            ),
+            Error::InvalidNonce => f.write_str("nonces used with another key or message"),
+            Error::InvalidR(parties) => write!(
+                f,
+                "signatures of parties {} are for another nonce than the first one",
+                Parties(parties)
+            ),
+            Error::WrongLength { expected, received } => {
+                write!(f, "expected {} entries, received {}", expected, received)
+            }
+            Error::WrongPartyCount {
+                threshold,
+                share_count,
+                received,
+            } => write!(
+                f,
+                "expected more than {} and at most {} parties, received {}",
+                threshold, share_count, received
+            ),
+            Error::EmptyInput => f.write_str("nothing to combine"),
+            Error::InvalidPartyIndex(party) => write!(f, "invalid party index {}", party),
+            Error::DuplicateParty(party) => write!(f, "party {} appears more than once", party),
+            Error::UnknownParty(party) => write!(f, "unknown party {}", party),
+            Error::MissingParty(party) => write!(f, "party {} is missing", party),
+            Error::ContextTooLong(len) => {
+                write!(f, "context of {} bytes is longer than 255 bytes", len)
+            }
+        }
     }
 }
 
@@ -29,9 +29,15 @@ use curv::BigInt;
 
 pub use curv::arithmetic::traits::Converter;
 use curv::cryptographic_primitives::commitments::traits::Commitment;
-use protocols::{ProofError, Signature};
+use protocols::scheme::{GroupKey, KeyAggregation, MultiPartySignature};
+use protocols::{Signature, SignatureMode, VerificationPolicy, REDACTED};
 use rand::{thread_rng, Rng};
 use sha2::{digest::Digest, Sha512};
+use std::collections::BTreeMap;
+use std::fmt;
+use Error::{
+    self, EmptyInput, InvalidCom, InvalidDecom, InvalidPartialSig, InvalidR, UnknownParty,
+};
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct KeyAgg {
@@ -44,7 +50,7 @@ impl KeyAgg {
         let mut my_hash = Scalar::zero();
         let mut sum = Point::zero();
         pks.iter().enumerate().for_each(|(index, pk)| {
-            let mut hasher = Sha512::new().chain(&[1]).chain(&*pk.to_bytes(true));
+            let mut hasher = Sha512::new().chain([1]).chain(&*pk.to_bytes(true));
             for pk in pks {
                 hasher.update(&*pk.to_bytes(true));
             }
@@ -63,12 +69,21 @@ impl KeyAgg {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Serialize, Deserialize)]
 pub struct EphemeralKey {
     pub r: Scalar<Ed25519>,
     pub R: Point<Ed25519>,
 }
 
+impl fmt::Debug for EphemeralKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("EphemeralKey")
+            .field("r", &REDACTED)
+            .field("R", &self.R)
+            .finish()
+    }
+}
+
 #[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
 pub struct SignFirstMsg {
     pub commitment: BigInt,
@@ -95,8 +110,8 @@ fn create_ephemeral_key_and_commit_rng(
     // here we deviate from the spec, by introducing  non-deterministic element (random number)
     // to the nonce
     let r = Sha512::new()
-        .chain(&[2])
-        .chain(&*keys.expanded_private_key.prefix.to_bytes())
+        .chain([2])
+        .chain(keys.expanded_private_key.prefix)
         .chain(message)
         .chain(rng.gen::<[u8; 32]>())
         .result_scalar();
@@ -109,9 +124,42 @@ fn create_ephemeral_key_and_commit_rng(
         SignSecondMsg { R, blind_factor },
     )
 }
-pub fn get_R_tot(Rs: &[Point<Ed25519>]) -> Point<Ed25519> {
-    let first = Rs[0].clone();
-    Rs[1..].iter().fold(first, |acc, Ri| acc + Ri)
+
+// whether `second_msg` opens the commitment in `first_msg`
+fn opens(first_msg: &SignFirstMsg, second_msg: &SignSecondMsg) -> bool {
+    let commitment = HashCommitment::<Sha512>::create_commitment_with_user_defined_randomness(
+        &second_msg.R.y_coord().unwrap(),
+        &second_msg.blind_factor,
+    );
+    commitment == first_msg.commitment
+}
+
+/// Checks that every party's ephemeral key opens its commitment, to run before `get_R_tot`.
+///
+/// Both slices hold the messages of all the parties ordered by their index, the position of their
+/// public key. On failure the error lists the parties whose opening is wrong or missing.
+pub fn verify_commitments(
+    first_msgs: &[SignFirstMsg],
+    second_msgs: &[SignSecondMsg],
+) -> Result<(), Error> {
+    let bad_parties: Vec<u16> = (0..first_msgs.len().max(second_msgs.len()))
+        .filter(
+            |&party| match (first_msgs.get(party), second_msgs.get(party)) {
+                (Some(first_msg), Some(second_msg)) => !opens(first_msg, second_msg),
+                _ => true,
+            },
+        )
+        .map(|party| party as u16)
+        .collect();
+    if !bad_parties.is_empty() {
+        return Err(InvalidDecom(bad_parties));
+    }
+    Ok(())
+}
+
+pub fn get_R_tot(Rs: &[Point<Ed25519>]) -> Result<Point<Ed25519>, Error> {
+    let (first, rest) = Rs.split_first().ok_or(EmptyInput)?;
+    Ok(rest.iter().fold(first.clone(), |acc, Ri| acc + Ri))
 }
 
 pub fn partial_sign(
@@ -121,8 +169,9 @@ pub fn partial_sign(
     R_tot: &Point<Ed25519>,
     agg_pubkey: &Point<Ed25519>,
     msg: &[u8],
+    mode: &SignatureMode,
 ) -> Signature {
-    let k = Signature::k(R_tot, agg_pubkey, msg);
+    let k = Signature::k(R_tot, agg_pubkey, msg, mode);
     let k_mul_sk = k * &keys.expanded_private_key.private_key;
     let k_mul_sk_mul_ai = k_mul_sk * a;
     let s = r + k_mul_sk_mul_ai;
@@ -133,31 +182,45 @@ pub fn partial_sign(
 }
 
 pub fn sign_single(message: &[u8], keys: &ExpandedKeyPair) -> Signature {
-    let r = Sha512::new()
-        .chain(&*keys.expanded_private_key.prefix.to_bytes())
+    // deterministic nonce as in RFC 8032: the full 512 bit hash reduced mod the group order
+    let mut r = Sha512::new()
+        .chain(keys.expanded_private_key.prefix)
         .chain(message)
-        .result_scalar();
+        .finalize();
+    r.reverse();
+    let r = Scalar::from_bigint(&BigInt::from_bytes(&r));
     let R = &r * Point::generator();
-    let k = Signature::k(&R, &keys.public_key, message);
+    let k = Signature::k(&R, &keys.public_key, message, &SignatureMode::Pure);
 
     let k_mul_sk = k * &keys.expanded_private_key.private_key;
     let s = r + k_mul_sk;
     Signature { R, s }
 }
 
-pub fn add_signature_parts(sigs: &[Signature]) -> Signature {
+// `sigs` are ordered by party index, on failure the error lists the parties whose partial signature
+// is for another R than the first
+pub fn add_signature_parts(sigs: &[Signature]) -> Result<Signature, Error> {
+    let (first, rest) = sigs.split_first().ok_or(EmptyInput)?;
     //test equality of group elements:
-    assert!(sigs[1..].iter().all(|x| x.R == sigs[0].R));
+    let bad_parties: Vec<u16> = sigs
+        .iter()
+        .enumerate()
+        .filter(|(_, sig)| sig.R != first.R)
+        .map(|(party, _)| party as u16)
+        .collect();
+    if !bad_parties.is_empty() {
+        return Err(InvalidR(bad_parties));
+    }
     //sum s part of the signature:
 
-    let s1 = sigs[0].s.clone();
-    let sum = sigs[1..].iter().fold(s1, |acc, si| acc + &si.s);
-    Signature {
+    let sum = rest.iter().fold(first.s.clone(), |acc, si| acc + &si.s);
+    Ok(Signature {
         s: sum,
-        R: sigs[0].R.clone(),
-    }
+        R: first.R.clone(),
+    })
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn verify_partial_sig(
     sig: &Signature,
     message: &[u8],
@@ -165,20 +228,129 @@ pub fn verify_partial_sig(
     partial_R: &Point<Ed25519>,
     partial_public_key: &Point<Ed25519>,
     agg_pubkey: &Point<Ed25519>,
-) -> Result<(), ProofError> {
-    let k = Signature::k(&sig.R, agg_pubkey, message);
-    let A = partial_public_key;
+    mode: &SignatureMode,
+    policy: VerificationPolicy,
+) -> Result<(), Error> {
+    let k = Signature::k(&sig.R, agg_pubkey, message, mode);
+    policy.check(&sig.s, partial_R, &(k * a), partial_public_key)
+}
+
+/// Aggregated signing as a `MultiPartySignature`, with the nonces committed to before they are
+/// revealed.
+pub struct AggSig;
+
+impl KeyAggregation for AggSig {
+    type KeyShare = GroupKey;
+
+    fn party_index(key: &GroupKey) -> u16 {
+        key.party_index()
+    }
+
+    fn public_key(key: &GroupKey) -> Point<Ed25519> {
+        KeyAgg::key_aggregation_n(key.public_keys(), usize::from(key.party_index())).apk
+    }
+
+    fn check_signers(key: &GroupKey, signers: &[u16]) -> Result<(), Error> {
+        key.check_signers(signers)
+    }
+}
+
+impl MultiPartySignature for AggSig {
+    const COMMITS_TO_NONCES: bool = true;
+
+    type NonceCommitment = SignFirstMsg;
+    type SecretNonces = EphemeralKey;
+    type PublicNonces = SignSecondMsg;
+    type PartialSignature = Signature;
+    type Signature = Signature;
+
+    fn generate_nonces(
+        key: &GroupKey,
+        message: &[u8],
+        _mode: &SignatureMode,
+    ) -> (EphemeralKey, SignFirstMsg, SignSecondMsg) {
+        create_ephemeral_key_and_commit(key.keys(), message)
+    }
 
-    let kA = A * k * a;
+    fn check_nonces(commitment: &SignFirstMsg, nonces: &SignSecondMsg) -> Result<(), Error> {
+        if opens(commitment, nonces) {
+            Ok(())
+        } else {
+            Err(InvalidCom)
+        }
+    }
+
+    fn partial_sign(
+        key: &GroupKey,
+        secret_nonces: EphemeralKey,
+        nonces: &BTreeMap<u16, SignSecondMsg>,
+        message: &[u8],
+        mode: &SignatureMode,
+    ) -> Result<Signature, Error> {
+        let key_agg = KeyAgg::key_aggregation_n(key.public_keys(), usize::from(key.party_index()));
+        let Rs: Vec<_> = nonces.values().map(|nonces| nonces.R.clone()).collect();
+        Ok(partial_sign(
+            &secret_nonces.r,
+            key.keys(),
+            &key_agg.hash,
+            &get_R_tot(&Rs)?,
+            &key_agg.apk,
+            message,
+            mode,
+        ))
+    }
 
-    let R_plus_kA = kA + partial_R;
-    let sG = &sig.s * Point::generator();
+    fn verify_partial(
+        key: &GroupKey,
+        nonces: &BTreeMap<u16, SignSecondMsg>,
+        message: &[u8],
+        mode: &SignatureMode,
+        signer: u16,
+        partial_sig: &Signature,
+    ) -> Result<(), Error> {
+        let Rs: Vec<_> = nonces.values().map(|nonces| nonces.R.clone()).collect();
+        let signer_key_agg = KeyAgg::key_aggregation_n(key.public_keys(), usize::from(signer));
+        if partial_sig.R != get_R_tot(&Rs)? {
+            return Err(InvalidPartialSig(vec![signer]));
+        }
+        verify_partial_sig(
+            partial_sig,
+            message,
+            &signer_key_agg.hash,
+            &nonces
+                .get(&signer)
+                .ok_or_else(|| InvalidPartialSig(vec![signer]))?
+                .R,
+            key.public_keys()
+                .get(usize::from(signer))
+                .ok_or(UnknownParty(signer))?,
+            &signer_key_agg.apk,
+            mode,
+            VerificationPolicy::default(),
+        )
+        .map_err(|_| InvalidPartialSig(vec![signer]))
+    }
+
+    fn combine(
+        _key: &GroupKey,
+        _nonces: &BTreeMap<u16, SignSecondMsg>,
+        _message: &[u8],
+        _mode: &SignatureMode,
+        partial_sigs: &BTreeMap<u16, Signature>,
+    ) -> Result<Signature, Error> {
+        let partial_sigs: Vec<_> = partial_sigs.values().cloned().collect();
+        add_signature_parts(&partial_sigs)
+    }
 
-    if R_plus_kA == sG {
-        Ok(())
-    } else {
-        Err(ProofError)
+    fn verify(
+        public_key: &Point<Ed25519>,
+        message: &[u8],
+        mode: &SignatureMode,
+        signature: &Signature,
+    ) -> Result<(), Error> {
+        signature.verify_with_mode(message, public_key, mode, VerificationPolicy::default())
     }
 }
 
+pub mod state_machine;
 mod test;
@@ -0,0 +1,229 @@
+#![allow(non_snake_case)]
+/*
+    multi-party-ed25519
+
+    Copyright 2018 by Kzen Networks
+
+    This file is part of multi-party-ed25519 library
+    (https://github.com/KZen-networks/multisig-schnorr)
+
+    multi-party-ed25519 is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-ed25519/blob/master/LICENSE>
+*/
+
+//! Aggregated signing as a state machine, parties are indexed by their position in the list of
+//! public keys.
+//!
+//! Round 1 broadcasts a commitment to the ephemeral key, round 2 opens it and round 3 broadcasts
+//! the partial signatures, which are checked one by one before they are added up.
+
+use curv::elliptic::curves::{Ed25519, Point};
+use std::collections::BTreeMap;
+
+use protocols::aggsig::{self, EphemeralKey, KeyAgg, SignFirstMsg, SignSecondMsg};
+use protocols::state_machine::{Msg, RoundError, RoundMessage, Rounds, StateMachine};
+use protocols::{ExpandedKeyPair, Signature, SignatureMode, VerificationPolicy};
+use Error::{self, InvalidDecom, InvalidKey, InvalidPartialSig};
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub enum SigningMessage {
+    Commitment(SignFirstMsg),
+    Reveal(SignSecondMsg),
+    PartialSignature(Signature),
+}
+
+impl RoundMessage for SigningMessage {
+    fn round(&self) -> u16 {
+        match self {
+            SigningMessage::Commitment(_) => 1,
+            SigningMessage::Reveal(_) => 2,
+            SigningMessage::PartialSignature(_) => 3,
+        }
+    }
+
+    fn is_broadcast(&self) -> bool {
+        true
+    }
+}
+
+pub struct Signing {
+    rounds: Rounds<SigningMessage>,
+    keys: ExpandedKeyPair,
+    public_keys: Vec<Point<Ed25519>>,
+    key_agg: KeyAgg,
+    message: Vec<u8>,
+    mode: SignatureMode,
+    ephemeral_key: EphemeralKey,
+    second_msg: Option<SignSecondMsg>,
+    commitments: BTreeMap<u16, SignFirstMsg>,
+    Rs: BTreeMap<u16, Point<Ed25519>>,
+    partial_sig: Option<Signature>,
+    output: Option<Signature>,
+}
+
+impl Signing {
+    /// Starts signing `message` as the party at `party_index` in `public_keys`.
+    pub fn new(
+        party_index: u16,
+        keys: ExpandedKeyPair,
+        public_keys: Vec<Point<Ed25519>>,
+        message: &[u8],
+        mode: SignatureMode,
+    ) -> Result<Signing, RoundError> {
+        if public_keys.get(usize::from(party_index)) != Some(&keys.public_key) {
+            return Err(RoundError::Setup(InvalidKey));
+        }
+        let parties = (0..public_keys.len() as u16).collect();
+        let key_agg = KeyAgg::key_aggregation_n(&public_keys, usize::from(party_index));
+        let (ephemeral_key, first_msg, second_msg) =
+            aggsig::create_ephemeral_key_and_commit(&keys, message);
+        let mut signing = Signing {
+            rounds: Rounds::new(party_index, parties, 3)?,
+            keys,
+            public_keys,
+            key_agg,
+            message: message.to_vec(),
+            mode,
+            ephemeral_key,
+            second_msg: Some(second_msg),
+            commitments: BTreeMap::new(),
+            Rs: BTreeMap::new(),
+            partial_sig: None,
+            output: None,
+        };
+        signing
+            .rounds
+            .broadcast(SigningMessage::Commitment(first_msg));
+        signing.proceed()?;
+        Ok(signing)
+    }
+
+    fn proceed(&mut self) -> Result<(), RoundError> {
+        while self.rounds.is_complete() {
+            let round = self.rounds.current_round();
+            let messages = self.rounds.advance();
+            if let Err(error) = self.finish_round(round, messages) {
+                return Err(self.rounds.fail(round, error));
+            }
+        }
+        Ok(())
+    }
+
+    fn finish_round(
+        &mut self,
+        round: u16,
+        messages: BTreeMap<u16, SigningMessage>,
+    ) -> Result<(), Error> {
+        let me = self.rounds.party_index();
+        match round {
+            1 => {
+                for (sender, message) in messages {
+                    if let SigningMessage::Commitment(first_msg) = message {
+                        self.commitments.insert(sender, first_msg);
+                    }
+                }
+                let second_msg = self.second_msg.take().expect("sent once, in round 2");
+                self.rounds.broadcast(SigningMessage::Reveal(second_msg));
+            }
+            2 => {
+                self.Rs.insert(me, self.ephemeral_key.R.clone());
+                let mut bad_senders = Vec::new();
+                for (sender, message) in messages {
+                    if let SigningMessage::Reveal(second_msg) = message {
+                        if !aggsig::opens(&self.commitments[&sender], &second_msg) {
+                            bad_senders.push(sender);
+                        }
+                        self.Rs.insert(sender, second_msg.R);
+                    }
+                }
+                if !bad_senders.is_empty() {
+                    return Err(InvalidDecom(bad_senders));
+                }
+                let Rs: Vec<_> = self.Rs.values().cloned().collect();
+                let partial_sig = aggsig::partial_sign(
+                    &self.ephemeral_key.r,
+                    &self.keys,
+                    &self.key_agg.hash,
+                    &aggsig::get_R_tot(&Rs)?,
+                    &self.key_agg.apk,
+                    &self.message,
+                    &self.mode,
+                );
+                self.partial_sig = Some(partial_sig.clone());
+                self.rounds
+                    .broadcast(SigningMessage::PartialSignature(partial_sig));
+            }
+            _ => {
+                let mut partial_sigs = vec![self.partial_sig.take().expect("set in round 2")];
+                let mut bad_senders = Vec::new();
+                for (sender, message) in messages {
+                    if let SigningMessage::PartialSignature(partial_sig) = message {
+                        let a = KeyAgg::key_aggregation_n(&self.public_keys, usize::from(sender));
+                        if partial_sig.R != partial_sigs[0].R
+                            || aggsig::verify_partial_sig(
+                                &partial_sig,
+                                &self.message,
+                                &a.hash,
+                                &self.Rs[&sender],
+                                &self.public_keys[usize::from(sender)],
+                                &self.key_agg.apk,
+                                &self.mode,
+                                VerificationPolicy::default(),
+                            )
+                            .is_err()
+                        {
+                            bad_senders.push(sender);
+                        }
+                        partial_sigs.push(partial_sig);
+                    }
+                }
+                if !bad_senders.is_empty() {
+                    return Err(InvalidPartialSig(bad_senders));
+                }
+                self.output = Some(aggsig::add_signature_parts(&partial_sigs)?);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl StateMachine for Signing {
+    type MessageBody = SigningMessage;
+    type Output = Signature;
+
+    fn handle_incoming(&mut self, msg: Msg<SigningMessage>) -> Result<(), RoundError> {
+        self.rounds.accept(msg)?;
+        self.proceed()
+    }
+
+    fn outgoing(&mut self) -> Vec<Msg<SigningMessage>> {
+        self.rounds.take_outgoing()
+    }
+
+    fn pick_output(&mut self) -> Result<Signature, RoundError> {
+        self.rounds.check_finished()?;
+        self.output.take().ok_or(RoundError::Finished)
+    }
+
+    fn current_round(&self) -> u16 {
+        self.rounds.current_round()
+    }
+
+    fn total_rounds(&self) -> u16 {
+        self.rounds.total_rounds()
+    }
+
+    fn party_index(&self) -> u16 {
+        self.rounds.party_index()
+    }
+
+    fn parties(&self) -> &[u16] {
+        self.rounds.parties()
+    }
+}
+
+mod test;
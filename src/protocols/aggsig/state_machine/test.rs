@@ -0,0 +1,97 @@
+/*
+    multi-party-ed25519
+
+    Copyright 2018 by Kzen Networks
+
+    This file is part of multi-party-ed25519 library
+    (https://github.com/KZen-networks/multisig-schnorr)
+
+    multi-party-ed25519 is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-ed25519/blob/master/LICENSE>
+*/
+
+#[cfg(test)]
+mod tests {
+    use curv::elliptic::curves::{Ed25519, Point};
+
+    use protocols::aggsig::state_machine::{Signing, SigningMessage};
+    use protocols::aggsig::KeyAgg;
+    use protocols::simulation::Simulation;
+    use protocols::state_machine::{RoundError, StateMachine};
+    use protocols::tests::verify_dalek;
+    use protocols::{ExpandedKeyPair, SignatureMode};
+    use Error;
+
+    fn signing_parties(n: usize, message: &[u8]) -> (Vec<Signing>, Point<Ed25519>) {
+        let keypairs: Vec<_> = (0..n).map(|_| ExpandedKeyPair::create()).collect();
+        let public_keys: Vec<_> = keypairs.iter().map(|k| k.public_key.clone()).collect();
+        let agg_pubkey = KeyAgg::key_aggregation_n(&public_keys, 0).apk;
+        let parties = keypairs
+            .into_iter()
+            .enumerate()
+            .map(|(i, keypair)| {
+                Signing::new(
+                    i as u16,
+                    keypair,
+                    public_keys.clone(),
+                    message,
+                    SignatureMode::Pure,
+                )
+                .unwrap()
+            })
+            .collect();
+        (parties, agg_pubkey)
+    }
+
+    #[test]
+    fn test_signing_state_machine() {
+        let message = [79, 77, 69, 82];
+        for n in 1..5 {
+            let (parties, agg_pubkey) = signing_parties(n, &message);
+            for signature in Simulation::new(parties).run() {
+                let signature = signature.unwrap();
+                assert!(verify_dalek(&agg_pubkey, &signature, &message));
+            }
+        }
+    }
+
+    #[test]
+    fn test_signing_state_machine_rejects_bad_reveal() {
+        let message = [79, 77, 69, 82];
+        let (mut parties, _) = signing_parties(3, &message);
+        let commitments: Vec<_> = parties.iter_mut().flat_map(|p| p.outgoing()).collect();
+        assert_eq!(
+            parties[0].pick_output().unwrap_err(),
+            RoundError::MissingMessages {
+                round: 1,
+                parties: vec![1, 2]
+            }
+        );
+        for msg in commitments {
+            for party in parties.iter_mut().filter(|p| p.party_index() != msg.sender) {
+                party.handle_incoming(msg.clone()).unwrap();
+            }
+        }
+        let mut reveals: Vec<_> = parties.iter_mut().flat_map(|p| p.outgoing()).collect();
+        assert_eq!(reveals.len(), 3);
+        assert!(parties.iter().all(|p| p.current_round() == 2));
+
+        // party 1 opens its commitment to another point
+        if let SigningMessage::Reveal(second_msg) = &mut reveals[1].body {
+            second_msg.R = Point::generator().to_point();
+        }
+        parties[0].handle_incoming(reveals[2].clone()).unwrap();
+        assert_eq!(
+            parties[0].handle_incoming(reveals[1].clone()),
+            Err(RoundError::Protocol {
+                round: 2,
+                error: Error::InvalidDecom(vec![1])
+            })
+        );
+        assert_eq!(parties[0].pick_output().unwrap_err(), RoundError::Finished);
+    }
+}
@@ -18,22 +18,19 @@
 mod tests {
     use std::convert::TryInto;
 
-    use curv::cryptographic_primitives::commitments::{
-        hash_commitment::HashCommitment, traits::Commitment,
-    };
-    use curv::elliptic::curves::{Ed25519, Point, Scalar};
+    use curv::elliptic::curves::{Point, Scalar};
     use curv::{arithmetic::Converter, BigInt};
     use hex::decode;
     use itertools::{izip, MultiUnzip};
     use rand::{Rng, RngCore};
-    use sha2::Sha512;
 
-    use protocols::tests::deterministic_fast_rand;
+    use protocols::tests::{assert_serde_roundtrip, deterministic_fast_rand};
     use protocols::{
         aggsig::{self, KeyAgg},
-        tests::verify_dalek,
-        ExpandedKeyPair, Signature,
+        tests::{verify_dalek, verify_dalek_prehashed},
+        Context, ExpandedKeyPair, Signature, SignatureMode, VerificationPolicy,
     };
+    use Error;
 
     #[test]
     fn test_ed25519_generate_keypair_from_seed() {
@@ -116,27 +113,26 @@ mod tests {
                 // Send first first msg, wait to recieve everyone else's and then send second msg.
 
                 // Verify that the second message matches the first message.
-                first_msgs
-                    .iter()
-                    .zip(second_msgs.iter())
-                    .for_each(|(first_msg, second_msg)| {
-                        assert!(test_com(
-                            &second_msg.R,
-                            &second_msg.blind_factor,
-                            &first_msg.commitment
-                        ));
-                    });
+                aggsig::verify_commitments(&first_msgs, &second_msgs).unwrap();
                 // Each party aggregates the Rs to get the aggregate R
-                let agg_R = aggsig::get_R_tot(&Rs);
+                let agg_R = aggsig::get_R_tot(&Rs).unwrap();
 
                 // keypairs
                 let partial_sigs: Vec<_> = izip!(keypairs.iter(), rs.iter(), agg_keys.iter())
                     .map(|(keypair, r, aggkey)| {
-                        aggsig::partial_sign(r, keypair, &aggkey.hash, &agg_R, &aggkey.apk, msg)
+                        aggsig::partial_sign(
+                            r,
+                            keypair,
+                            &aggkey.hash,
+                            &agg_R,
+                            &aggkey.apk,
+                            msg,
+                            &SignatureMode::Pure,
+                        )
                     })
                     .collect();
 
-                let signature = aggsig::add_signature_parts(&partial_sigs);
+                let signature = aggsig::add_signature_parts(&partial_sigs).unwrap();
                 assert!(verify_dalek(&agg_keys[0].apk, &signature, msg));
             }
         }
@@ -171,20 +167,12 @@ mod tests {
         let (party2_ephemeral_key, party2_sign_first_message, party2_sign_second_message) =
             aggsig::create_ephemeral_key_and_commit_rng(&party2_key, &message, rng);
 
-        let party1_commitment = &party1_sign_first_message.commitment;
-        let party2_commitment = &party2_sign_first_message.commitment;
-
         // round 2: send ephemeral public keys and check commitments
-        assert!(test_com(
-            &party2_sign_second_message.R,
-            &party2_sign_second_message.blind_factor,
-            party2_commitment
-        ));
-        assert!(test_com(
-            &party1_sign_second_message.R,
-            &party1_sign_second_message.blind_factor,
-            party1_commitment
-        ));
+        aggsig::verify_commitments(
+            &[party1_sign_first_message, party2_sign_first_message],
+            &[party1_sign_second_message, party2_sign_second_message],
+        )
+        .unwrap();
 
         // compute apk:
         let pks = [party1_key.public_key.clone(), party2_key.public_key.clone()];
@@ -194,7 +182,7 @@ mod tests {
         // compute R' = sum(Ri):
         let Ri = [party1_ephemeral_key.R, party2_ephemeral_key.R];
         // each party i should run this:
-        let R_tot = aggsig::get_R_tot(&Ri);
+        let R_tot = aggsig::get_R_tot(&Ri).unwrap();
         let s1 = aggsig::partial_sign(
             &party1_ephemeral_key.r,
             &party1_key,
@@ -202,6 +190,7 @@ mod tests {
             &R_tot,
             &party1_key_agg.apk,
             &message,
+            &SignatureMode::Pure,
         );
         let s2 = aggsig::partial_sign(
             &party2_ephemeral_key.r,
@@ -210,15 +199,71 @@ mod tests {
             &R_tot,
             &party2_key_agg.apk,
             &message,
+            &SignatureMode::Pure,
         );
 
         let s = [s1, s2];
-        let signature = aggsig::add_signature_parts(&s);
+        let signature = aggsig::add_signature_parts(&s).unwrap();
 
         // verify:
         assert!(signature.verify(&message, &party1_key_agg.apk).is_ok())
     }
 
+    #[test]
+    fn test_multiparty_signing_with_modes() {
+        let mut rng = deterministic_fast_rand("test_multiparty_signing_with_modes", None);
+        let data = [79u8, 77, 69, 82];
+        let prehash = SignatureMode::prehash(&data);
+        let ctx = SignatureMode::Context(Context::new(b"aggsig").unwrap());
+        let ph = SignatureMode::Prehash(Context::new(b"aggsig").unwrap());
+        for (message, mode) in [(&data[..], &ctx), (&prehash[..], &ph)] {
+            let keys = [ExpandedKeyPair::create(), ExpandedKeyPair::create()];
+            let pks = [keys[0].public_key.clone(), keys[1].public_key.clone()];
+            let ephemeral_keys: Vec<_> = keys
+                .iter()
+                .map(|key| aggsig::create_ephemeral_key_and_commit_rng(key, message, &mut rng).0)
+                .collect();
+            let R_tot =
+                aggsig::get_R_tot(&[ephemeral_keys[0].R.clone(), ephemeral_keys[1].R.clone()])
+                    .unwrap();
+            let partial_sigs: Vec<_> = (0..2)
+                .map(|i| {
+                    let key_agg = KeyAgg::key_aggregation_n(&pks, i);
+                    let partial_sig = aggsig::partial_sign(
+                        &ephemeral_keys[i].r,
+                        &keys[i],
+                        &key_agg.hash,
+                        &R_tot,
+                        &key_agg.apk,
+                        message,
+                        mode,
+                    );
+                    assert!(aggsig::verify_partial_sig(
+                        &partial_sig,
+                        message,
+                        &key_agg.hash,
+                        &ephemeral_keys[i].R,
+                        &pks[i],
+                        &key_agg.apk,
+                        mode,
+                        VerificationPolicy::Strict,
+                    )
+                    .is_ok());
+                    partial_sig
+                })
+                .collect();
+            let signature = aggsig::add_signature_parts(&partial_sigs).unwrap();
+            let apk = KeyAgg::key_aggregation_n(&pks, 0).apk;
+            assert!(signature
+                .verify_with_mode(message, &apk, mode, VerificationPolicy::Strict)
+                .is_ok());
+            assert!(signature.verify(message, &apk).is_err());
+            if let SignatureMode::Prehash(_) = mode {
+                assert!(verify_dalek_prehashed(&apk, &signature, &data, b"aggsig"));
+            }
+        }
+    }
+
     #[test]
     fn test_multiparty_signing_for_three_parties() {
         let mut rng = deterministic_fast_rand("test_multiparty_signing_for_three_parties", None);
@@ -243,26 +288,20 @@ mod tests {
         let (party3_ephemeral_key, party3_sign_first_message, party3_sign_second_message) =
             aggsig::create_ephemeral_key_and_commit_rng(&party3_key, &message, rng);
 
-        let party1_commitment = &party1_sign_first_message.commitment;
-        let party2_commitment = &party2_sign_first_message.commitment;
-        let party3_commitment = &party3_sign_first_message.commitment;
-
         // round 2: send ephemeral public keys and check commitments
-        assert!(test_com(
-            &party2_sign_second_message.R,
-            &party2_sign_second_message.blind_factor,
-            party2_commitment
-        ));
-        assert!(test_com(
-            &party1_sign_second_message.R,
-            &party1_sign_second_message.blind_factor,
-            party1_commitment
-        ));
-        assert!(test_com(
-            &party3_sign_second_message.R,
-            &party3_sign_second_message.blind_factor,
-            party3_commitment
-        ));
+        aggsig::verify_commitments(
+            &[
+                party1_sign_first_message,
+                party2_sign_first_message,
+                party3_sign_first_message,
+            ],
+            &[
+                party1_sign_second_message,
+                party2_sign_second_message,
+                party3_sign_second_message,
+            ],
+        )
+        .unwrap();
 
         // compute apk:
         let pks = [
@@ -282,7 +321,7 @@ mod tests {
             party3_ephemeral_key.R,
         ];
         // each party i should run this:
-        let R_tot = aggsig::get_R_tot(&Ri);
+        let R_tot = aggsig::get_R_tot(&Ri).unwrap();
         let s1 = aggsig::partial_sign(
             &party1_ephemeral_key.r,
             &party1_key,
@@ -290,6 +329,7 @@ mod tests {
             &R_tot,
             &party1_key_agg.apk,
             &message,
+            &SignatureMode::Pure,
         );
         let s2 = aggsig::partial_sign(
             &party2_ephemeral_key.r,
@@ -298,6 +338,7 @@ mod tests {
             &R_tot,
             &party2_key_agg.apk,
             &message,
+            &SignatureMode::Pure,
         );
         let s3 = aggsig::partial_sign(
             &party3_ephemeral_key.r,
@@ -306,10 +347,11 @@ mod tests {
             &R_tot,
             &party3_key_agg.apk,
             &message,
+            &SignatureMode::Pure,
         );
 
         let s = [s1, s2, s3];
-        let signature = aggsig::add_signature_parts(&s);
+        let signature = aggsig::add_signature_parts(&s).unwrap();
 
         // verify:
         assert!(signature.verify(&message, &party1_key_agg.apk).is_ok())
@@ -345,12 +387,71 @@ mod tests {
         assert!(sig.verify(&message, &pk).is_ok())
     }
 
-    pub fn test_com(r_to_test: &Point<Ed25519>, blind_factor: &BigInt, comm: &BigInt) -> bool {
-        let computed_comm =
-            &HashCommitment::<Sha512>::create_commitment_with_user_defined_randomness(
-                &r_to_test.y_coord().unwrap(),
-                blind_factor,
-            );
-        computed_comm == comm
+    #[test]
+    fn test_add_signature_parts_checks_R() {
+        let message: [u8; 4] = [79, 77, 69, 82];
+        let keypairs: Vec<_> = (0..3).map(|_| ExpandedKeyPair::create()).collect();
+        let mut sigs: Vec<_> = keypairs
+            .iter()
+            .map(|keypair| aggsig::sign_single(&message, keypair))
+            .collect();
+        assert_eq!(
+            aggsig::add_signature_parts(&sigs),
+            Err(Error::InvalidR(vec![1, 2]))
+        );
+        sigs[2].R = sigs[0].R.clone();
+        assert_eq!(
+            aggsig::add_signature_parts(&sigs),
+            Err(Error::InvalidR(vec![1]))
+        );
+        assert_eq!(aggsig::add_signature_parts(&[]), Err(Error::EmptyInput));
+        assert_eq!(aggsig::get_R_tot(&[]), Err(Error::EmptyInput));
+    }
+
+    #[test]
+    fn test_verify_commitments() {
+        let message: [u8; 4] = [79, 77, 69, 82];
+        let (first_msgs, mut second_msgs): (Vec<_>, Vec<_>) = (0..4)
+            .map(|_| {
+                let keypair = ExpandedKeyPair::create();
+                let (_, first_msg, second_msg) =
+                    aggsig::create_ephemeral_key_and_commit(&keypair, &message);
+                (first_msg, second_msg)
+            })
+            .unzip();
+        aggsig::verify_commitments(&first_msgs, &second_msgs).unwrap();
+
+        // party 1 opens to another key and party 3 to another blind factor
+        second_msgs[1].R = second_msgs[0].R.clone();
+        second_msgs[3].blind_factor = &second_msgs[3].blind_factor + BigInt::from(1);
+        assert_eq!(
+            aggsig::verify_commitments(&first_msgs, &second_msgs),
+            Err(Error::InvalidDecom(vec![1, 3]))
+        );
+        // and the last party didn't open its commitment
+        assert_eq!(
+            aggsig::verify_commitments(&first_msgs, &second_msgs[..2]),
+            Err(Error::InvalidDecom(vec![1, 2, 3]))
+        );
+    }
+
+    #[test]
+    fn test_serde_roundtrip() {
+        let message: [u8; 4] = [79, 77, 69, 82];
+        let keypair = ExpandedKeyPair::create();
+        let pks = [
+            keypair.public_key.clone(),
+            ExpandedKeyPair::create().public_key,
+        ];
+        let key_agg = KeyAgg::key_aggregation_n(&pks, 0);
+        let decoded = assert_serde_roundtrip(&key_agg);
+        assert_eq!((decoded.apk, decoded.hash), (key_agg.apk, key_agg.hash));
+
+        let (ephemeral_key, first_msg, second_msg) =
+            aggsig::create_ephemeral_key_and_commit(&keypair, &message);
+        let decoded = assert_serde_roundtrip(&ephemeral_key);
+        assert_eq!((decoded.r, decoded.R), (ephemeral_key.r, ephemeral_key.R));
+        assert_eq!(assert_serde_roundtrip(&first_msg), first_msg);
+        assert_eq!(assert_serde_roundtrip(&second_msg), second_msg);
     }
 }
@@ -15,26 +15,58 @@
     @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-ed25519/blob/master/LICENSE>
 */
 use curv::arithmetic::Converter;
-use curv::cryptographic_primitives::proofs::ProofError;
 use curv::elliptic::curves::{Ed25519, Point, Scalar};
 use curv::BigInt;
+use curve25519_dalek::constants::ED25519_BASEPOINT_POINT;
+use curve25519_dalek::edwards::{CompressedEdwardsY, EdwardsPoint};
+use curve25519_dalek::scalar::Scalar as DalekScalar;
+use curve25519_dalek::traits::{IsIdentity, VartimeMultiscalarMul};
 use rand::{thread_rng, Rng};
 use sha2::{Digest, Sha512};
+use std::convert::TryFrom;
+use std::fmt;
+use zeroize::Zeroize;
+use Error;
 
 // simple ed25519 based on rfc8032
 // reference implementation: https://ed25519.cr.yp.to/python/ed25519.py
 pub mod aggsig;
+mod conformance;
 pub mod multisig;
 pub mod musig2;
+pub mod scheme;
+pub mod simulation;
+pub mod state_machine;
 pub mod thresholdsig;
+#[cfg(feature = "async")]
+pub mod transport;
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+// Secret scalars are wiped on drop by curv, and Debug output of secret material is redacted.
+pub(crate) const REDACTED: &str = "<redacted>";
+
+#[derive(Serialize, Deserialize)]
 pub struct ExpandedPrivateKey {
-    pub prefix: Scalar<Ed25519>,
+    // kept as bytes, since a scalar can't hold all 256 bits of the hash that RFC 8032 nonces use
+    pub prefix: [u8; 32],
     private_key: Scalar<Ed25519>,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+impl Drop for ExpandedPrivateKey {
+    fn drop(&mut self) {
+        self.prefix.zeroize();
+    }
+}
+
+impl fmt::Debug for ExpandedPrivateKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ExpandedPrivateKey")
+            .field("prefix", &REDACTED)
+            .field("private_key", &REDACTED)
+            .finish()
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
 pub struct ExpandedKeyPair {
     pub public_key: Point<Ed25519>,
     expanded_private_key: ExpandedPrivateKey,
@@ -42,30 +74,138 @@ pub struct ExpandedKeyPair {
 
 impl ExpandedKeyPair {
     pub fn create() -> ExpandedKeyPair {
-        let secret = thread_rng().gen();
-        Self::create_from_private_key(secret)
+        let mut secret: [u8; 32] = thread_rng().gen();
+        let keypair = Self::create_from_private_key(secret);
+        secret.zeroize();
+        keypair
     }
 
-    pub fn create_from_private_key(secret: [u8; 32]) -> ExpandedKeyPair {
-        let h = Sha512::new().chain(secret).finalize();
-        let mut private_key: [u8; 32] = [0u8; 32];
+    pub fn create_from_private_key(mut secret: [u8; 32]) -> ExpandedKeyPair {
+        let mut h = Sha512::new().chain(secret).finalize();
+        let mut private_key_bytes: [u8; 32] = [0u8; 32];
         let mut prefix: [u8; 32] = [0u8; 32];
         prefix.copy_from_slice(&h[32..64]);
-        private_key.copy_from_slice(&h[0..32]);
-        private_key[0] &= 248;
-        private_key[31] &= 63;
-        private_key[31] |= 64;
-        let private_key = Scalar::from_bytes(&private_key)
+        private_key_bytes.copy_from_slice(&h[0..32]);
+        private_key_bytes[0] &= 248;
+        private_key_bytes[31] &= 63;
+        private_key_bytes[31] |= 64;
+        let private_key = Scalar::from_bytes(&private_key_bytes)
             .expect("private_key is the right length, so can't fail");
-        let prefix =
-            Scalar::from_bytes(&prefix).expect("prefix is the right length, so can't fail");
+        // wipe the intermediate copies of the secret
+        secret.zeroize();
+        h[..].zeroize();
+        private_key_bytes.zeroize();
         let public_key = Point::generator() * &private_key;
+        let expanded_private_key = ExpandedPrivateKey {
+            prefix,
+            private_key,
+        };
+        prefix.zeroize();
         ExpandedKeyPair {
             public_key,
-            expanded_private_key: ExpandedPrivateKey {
-                prefix,
-                private_key,
-            },
+            expanded_private_key,
+        }
+    }
+}
+
+/// Context string of Ed25519ctx and Ed25519ph signatures, at most 255 bytes long.
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(try_from = "Vec<u8>", into = "Vec<u8>")]
+pub struct Context(Vec<u8>);
+
+impl Context {
+    pub fn new(context: &[u8]) -> Option<Context> {
+        if context.len() > 255 {
+            None
+        } else {
+            Some(Context(context.to_vec()))
+        }
+    }
+}
+
+impl TryFrom<Vec<u8>> for Context {
+    type Error = Error;
+
+    fn try_from(context: Vec<u8>) -> Result<Self, Self::Error> {
+        Context::new(&context).ok_or(Error::ContextTooLong(context.len()))
+    }
+}
+
+impl From<Context> for Vec<u8> {
+    fn from(context: Context) -> Self {
+        context.0
+    }
+}
+
+/// The RFC 8032 variant of Ed25519 a signature is made in.
+#[derive(Clone, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub enum SignatureMode {
+    /// Plain Ed25519 (PureEdDSA).
+    #[default]
+    Pure,
+    /// Ed25519ctx, RFC 8032 recommends a non-empty context.
+    Context(Context),
+    /// Ed25519ph, the message passed for signing and verification must be the SHA-512 hash of
+    /// the data, see `SignatureMode::prehash`.
+    Prehash(Context),
+}
+
+impl SignatureMode {
+    /// Computes the SHA-512 prehash of the data to sign in `SignatureMode::Prehash`.
+    pub fn prehash(data: &[u8]) -> [u8; 64] {
+        let mut prehash = [0u8; 64];
+        prehash.copy_from_slice(&Sha512::digest(data));
+        prehash
+    }
+
+    // dom2(phflag, context) from RFC 8032 section 2, empty for plain Ed25519
+    pub(crate) fn dom2(&self) -> Vec<u8> {
+        let (phflag, context) = match self {
+            SignatureMode::Pure => return Vec::new(),
+            SignatureMode::Context(context) => (0u8, context),
+            SignatureMode::Prehash(context) => (1u8, context),
+        };
+        let mut dom2 = b"SigEd25519 no Ed25519 collisions".to_vec();
+        dom2.push(phflag);
+        dom2.push(context.0.len() as u8);
+        dom2.extend_from_slice(&context.0);
+        dom2
+    }
+}
+
+/// The rules used to accept a signature, verifiers have to use the same policy to agree on
+/// edge-case signatures.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub enum VerificationPolicy {
+    /// RFC 8032 with the cofactorless equation `s•B = R + k•A`, rejecting `A` and `R` of small
+    /// order and non-canonical encodings.
+    #[default]
+    Strict,
+    /// The cofactored equation `8•s•B = 8•R + 8•k•A`, rejecting non-canonical encodings.
+    Cofactored,
+    /// The ZIP-215 rules: the cofactored equation, and non-canonical encodings of `A` and `R`
+    /// are accepted.
+    Zip215,
+}
+
+impl VerificationPolicy {
+    // Checks s•B == R + k•A, for points that are already decoded.
+    pub(crate) fn check(
+        &self,
+        s: &Scalar<Ed25519>,
+        R: &Point<Ed25519>,
+        k: &Scalar<Ed25519>,
+        A: &Point<Ed25519>,
+    ) -> Result<(), Error> {
+        // curv points are always in the prime order subgroup, so the identity is the only point
+        // of small order, and the cofactored equation is equivalent to the cofactorless one
+        if *self == VerificationPolicy::Strict && (R.is_zero() || A.is_zero()) {
+            return Err(Error::InvalidSig);
+        }
+        if s * Point::generator() == R + A * k {
+            Ok(())
+        } else {
+            Err(Error::InvalidSig)
         }
     }
 }
@@ -77,23 +217,195 @@ pub struct Signature {
 }
 
 impl Signature {
-    pub fn verify(&self, message: &[u8], public_key: &Point<Ed25519>) -> Result<(), ProofError> {
-        let k = Self::k(&self.R, public_key, message);
-        let A = public_key;
+    /// Encodes the signature as `R || s`, as specified in RFC 8032.
+    pub fn to_bytes(&self) -> [u8; 64] {
+        let mut bytes = [0u8; 64];
+        bytes[..32].copy_from_slice(&self.R.to_bytes(true));
+        bytes[32..].copy_from_slice(&self.s.to_bytes());
+        bytes
+    }
+
+    /// Decodes a signature encoded as `R || s`, rejecting a non-canonical `s`, a non-canonical
+    /// encoding of `R` and an `R` of small order.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Signature, Error> {
+        if bytes.len() != 64 {
+            return Err(Error::InvalidSig);
+        }
+        let R = point_from_bytes_strict(&bytes[..32]).ok_or(Error::InvalidSig)?;
+        let mut s_bytes = [0u8; 32];
+        s_bytes.copy_from_slice(&bytes[32..]);
+        if DalekScalar::from_canonical_bytes(s_bytes).is_none() {
+            return Err(Error::InvalidSig);
+        }
+        let s = Scalar::from_bytes(&s_bytes).map_err(|_| Error::InvalidSig)?;
+        Ok(Signature { R, s })
+    }
+
+    /// Verifies a plain Ed25519 signature with `VerificationPolicy::Strict`.
+    pub fn verify(&self, message: &[u8], public_key: &Point<Ed25519>) -> Result<(), Error> {
+        self.verify_with_mode(
+            message,
+            public_key,
+            &SignatureMode::Pure,
+            VerificationPolicy::Strict,
+        )
+    }
+
+    pub fn verify_with_mode(
+        &self,
+        message: &[u8],
+        public_key: &Point<Ed25519>,
+        mode: &SignatureMode,
+        policy: VerificationPolicy,
+    ) -> Result<(), Error> {
+        let k = Self::k(&self.R, public_key, message, mode);
+        policy.check(&self.s, &self.R, &k, public_key)
+    }
 
-        let kA = A * k;
-        let R_plus_kA = &kA + &self.R;
-        let sG = &self.s * Point::generator();
+    /// Verifies an encoded signature against an encoded public key.
+    ///
+    /// Unlike `Signature::from_bytes` and `PublicKey::from_bytes` this accepts whatever encodings
+    /// `policy` allows, so it can match other verifiers on edge-case signatures.
+    pub fn verify_bytes(
+        message: &[u8],
+        public_key: &[u8],
+        signature: &[u8],
+        mode: &SignatureMode,
+        policy: VerificationPolicy,
+    ) -> Result<(), Error> {
+        if public_key.len() != 32 || signature.len() != 64 {
+            return Err(Error::InvalidSig);
+        }
+        let (R_bytes, s_bytes) = signature.split_at(32);
+        let mut s = [0u8; 32];
+        s.copy_from_slice(s_bytes);
+        let s = DalekScalar::from_canonical_bytes(s).ok_or(Error::InvalidSig)?;
+        // curve25519-dalek accepts non-canonical encodings, as ZIP-215 requires
+        let A = CompressedEdwardsY::from_slice(public_key)
+            .decompress()
+            .ok_or(Error::InvalidSig)?;
+        let R = CompressedEdwardsY::from_slice(R_bytes)
+            .decompress()
+            .ok_or(Error::InvalidSig)?;
+        if policy != VerificationPolicy::Zip215
+            && (A.compress().as_bytes()[..] != *public_key
+                || R.compress().as_bytes()[..] != *R_bytes)
+        {
+            return Err(Error::InvalidSig);
+        }
+        if policy == VerificationPolicy::Strict && (A.is_small_order() || R.is_small_order()) {
+            return Err(Error::InvalidSig);
+        }
+
+        // the challenge is computed over the encodings as received
+        let k = Sha512::new()
+            .chain(mode.dom2())
+            .chain(R_bytes)
+            .chain(public_key)
+            .chain(message)
+            .finalize();
+        let mut k_bytes = [0u8; 64];
+        k_bytes.copy_from_slice(&k);
+        let k = DalekScalar::from_bytes_mod_order_wide(&k_bytes);
+
+        // s•B - k•A - R, A is negated rather than k since A may have a torsion component
+        let difference = EdwardsPoint::vartime_double_scalar_mul_basepoint(&k, &-A, &s) - R;
+        let valid = match policy {
+            VerificationPolicy::Strict => difference.is_identity(),
+            VerificationPolicy::Cofactored | VerificationPolicy::Zip215 => {
+                difference.mul_by_cofactor().is_identity()
+            }
+        };
+        if valid {
+            Ok(())
+        } else {
+            Err(Error::InvalidSig)
+        }
+    }
 
-        if R_plus_kA == sG {
+    /// Verifies a batch of `(message, public_key, signature)` entries made in `mode` at once, by
+    /// checking a random linear combination of their verification equations with a single
+    /// multi-scalar multiplication. An entry is accepted if and only if `verify_with_mode` with
+    /// the same `policy` accepts it.
+    ///
+    /// If the batch fails, it is split in halves until the invalid entries are found, and their
+    /// positions in `batch` are returned.
+    pub fn verify_batch(
+        batch: &[(&[u8], &Point<Ed25519>, &Signature)],
+        mode: &SignatureMode,
+        policy: VerificationPolicy,
+    ) -> Result<(), Vec<usize>> {
+        let mut invalid = Vec::new();
+        Self::find_invalid(batch, mode, policy, 0, &mut invalid);
+        if invalid.is_empty() {
             Ok(())
         } else {
-            Err(ProofError)
+            Err(invalid)
+        }
+    }
+
+    fn find_invalid(
+        batch: &[(&[u8], &Point<Ed25519>, &Signature)],
+        mode: &SignatureMode,
+        policy: VerificationPolicy,
+        offset: usize,
+        invalid: &mut Vec<usize>,
+    ) {
+        if batch.is_empty()
+            || Self::verify_linear_combination(batch, mode, policy, &mut thread_rng())
+        {
+            return;
+        }
+        if batch.len() == 1 {
+            invalid.push(offset);
+            return;
         }
+        let (left, right) = batch.split_at(batch.len() / 2);
+        Self::find_invalid(left, mode, policy, offset, invalid);
+        Self::find_invalid(right, mode, policy, offset + left.len(), invalid);
     }
 
-    pub(crate) fn k(R: &Point<Ed25519>, PK: &Point<Ed25519>, message: &[u8]) -> Scalar<Ed25519> {
+    // Checks that sum(z_i•s_i)•G - sum(z_i•R_i) - sum(z_i•k_i•A_i) == 0 for random 128 bit z_i.
+    // As in `VerificationPolicy::check`, only the identity is of small order, and the strict
+    // policy rejects it.
+    fn verify_linear_combination(
+        batch: &[(&[u8], &Point<Ed25519>, &Signature)],
+        mode: &SignatureMode,
+        policy: VerificationPolicy,
+        rng: &mut impl Rng,
+    ) -> bool {
+        if policy == VerificationPolicy::Strict
+            && batch
+                .iter()
+                .any(|(_, public_key, signature)| public_key.is_zero() || signature.R.is_zero())
+        {
+            return false;
+        }
+        let mut scalars = Vec::with_capacity(2 * batch.len() + 1);
+        let mut points = Vec::with_capacity(2 * batch.len() + 1);
+        let mut s_sum = DalekScalar::zero();
+        for (message, public_key, signature) in batch {
+            let z = DalekScalar::from(rng.gen::<u128>());
+            let k = to_dalek_scalar(&Self::k(&signature.R, public_key, message, mode));
+            s_sum += z * to_dalek_scalar(&signature.s);
+            scalars.push(-z);
+            points.push(to_dalek_point(&signature.R));
+            scalars.push(-(z * k));
+            points.push(to_dalek_point(public_key));
+        }
+        scalars.push(s_sum);
+        points.push(ED25519_BASEPOINT_POINT);
+        EdwardsPoint::vartime_multiscalar_mul(scalars, points).is_identity()
+    }
+
+    pub(crate) fn k(
+        R: &Point<Ed25519>,
+        PK: &Point<Ed25519>,
+        message: &[u8],
+        mode: &SignatureMode,
+    ) -> Scalar<Ed25519> {
         let mut k = Sha512::new()
+            .chain(mode.dom2())
             .chain(&*R.to_bytes(true))
             .chain(&*PK.to_bytes(true))
             .chain(message)
@@ -105,6 +417,85 @@ impl Signature {
     }
 }
 
+/// An Ed25519 public key that is known to be canonically encoded and not of small order.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(try_from = "Point<Ed25519>", into = "Point<Ed25519>")]
+pub struct PublicKey(Point<Ed25519>);
+
+impl PublicKey {
+    /// Decodes a 32 byte public key, rejecting non-canonical encodings and points of small order.
+    pub fn from_bytes(bytes: &[u8]) -> Result<PublicKey, Error> {
+        if bytes.len() != 32 {
+            return Err(Error::InvalidKey);
+        }
+        point_from_bytes_strict(bytes)
+            .map(PublicKey)
+            .ok_or(Error::InvalidKey)
+    }
+
+    pub fn to_bytes(&self) -> [u8; 32] {
+        let mut bytes = [0u8; 32];
+        bytes.copy_from_slice(&self.0.to_bytes(true));
+        bytes
+    }
+
+    pub fn as_point(&self) -> &Point<Ed25519> {
+        &self.0
+    }
+}
+
+impl TryFrom<Point<Ed25519>> for PublicKey {
+    type Error = Error;
+
+    // curv points are always in the prime order subgroup, so only the identity has to be rejected
+    fn try_from(point: Point<Ed25519>) -> Result<Self, Self::Error> {
+        if point.is_zero() {
+            Err(Error::InvalidKey)
+        } else {
+            Ok(PublicKey(point))
+        }
+    }
+}
+
+impl From<PublicKey> for Point<Ed25519> {
+    fn from(public_key: PublicKey) -> Self {
+        public_key.0
+    }
+}
+
+// curv neither checks that point encodings are canonical nor has multi-scalar multiplication,
+// so those are done with curve25519-dalek.
+fn point_from_bytes_strict(bytes: &[u8]) -> Option<Point<Ed25519>> {
+    let point = CompressedEdwardsY::from_slice(bytes).decompress()?;
+    if point.compress().as_bytes()[..] != *bytes || point.is_small_order() {
+        return None;
+    }
+    // points of mixed order are rejected by curv
+    Point::from_bytes(bytes).ok()
+}
+
+fn to_dalek_point(point: &Point<Ed25519>) -> EdwardsPoint {
+    CompressedEdwardsY::from_slice(&point.to_bytes(true))
+        .decompress()
+        .expect("curv points are valid curve points, so can't fail")
+}
+
+fn to_dalek_scalar(scalar: &Scalar<Ed25519>) -> DalekScalar {
+    let mut bytes = [0u8; 32];
+    bytes.copy_from_slice(&scalar.to_bytes());
+    DalekScalar::from_bytes_mod_order(bytes)
+}
+
+// each party may only appear once in `parties`
+pub(crate) fn check_distinct(parties: &[u16]) -> Result<(), Error> {
+    let mut sorted = parties.to_vec();
+    sorted.sort_unstable();
+    match sorted.windows(2).find(|pair| pair[0] == pair[1]) {
+        Some(pair) => Err(Error::DuplicateParty(pair[0])),
+        None => Ok(()),
+    }
+}
+
 #[cfg(test)]
 pub(crate) mod tests {
 
@@ -113,20 +504,73 @@ pub(crate) mod tests {
     use rand::{thread_rng, Rng};
     use rand_xoshiro::rand_core::{RngCore, SeedableRng};
     use rand_xoshiro::Xoshiro256PlusPlus;
+    use serde::de::DeserializeOwned;
+    use serde::Serialize;
+    use serde_cbor;
+    use serde_json;
 
-    use protocols::{ExpandedKeyPair, Signature};
+    use hex::decode;
+    use sha2::{Digest, Sha512};
+    use std::convert::{TryFrom, TryInto};
 
-    pub fn verify_dalek(pk: &Point<Ed25519>, sig: &Signature, msg: &[u8]) -> bool {
-        let mut sig_bytes = [0u8; 64];
-        sig_bytes[..32].copy_from_slice(&*sig.R.to_bytes(true));
-        sig_bytes[32..].copy_from_slice(&sig.s.to_bytes());
+    use curve25519_dalek::constants::{BASEPOINT_ORDER, EIGHT_TORSION};
+    use curve25519_dalek::edwards::EdwardsPoint;
+
+    use protocols::{
+        aggsig, to_dalek_point, Context, ExpandedKeyPair, PublicKey, Signature, SignatureMode,
+        VerificationPolicy,
+    };
+    use Error;
+
+    // 2^255 - 18, a non-canonical encoding of the identity (y = p + 1)
+    const NON_CANONICAL_IDENTITY: [u8; 32] = [
+        0xee, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+        0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+        0xff, 0x7f,
+    ];
+
+    fn mixed_order(point: &Point<Ed25519>) -> [u8; 32] {
+        (to_dalek_point(point) + EIGHT_TORSION[1])
+            .compress()
+            .to_bytes()
+    }
 
-        let dalek_pub = ed25519_dalek::PublicKey::from_bytes(&*pk.to_bytes(true)).unwrap();
+    pub fn verify_dalek(pk: &Point<Ed25519>, sig: &Signature, msg: &[u8]) -> bool {
+        let sig_bytes = sig.to_bytes();
+        let dalek_pub = ed25519_dalek::PublicKey::from_bytes(&pk.to_bytes(true)).unwrap();
         let dalek_sig = ed25519_dalek::Signature::from_bytes(&sig_bytes).unwrap();
 
         dalek_pub.verify(msg, &dalek_sig).is_ok()
     }
 
+    pub fn verify_dalek_prehashed(
+        pk: &Point<Ed25519>,
+        sig: &Signature,
+        data: &[u8],
+        context: &[u8],
+    ) -> bool {
+        let sig_bytes = sig.to_bytes();
+        let dalek_pub = ed25519_dalek::PublicKey::from_bytes(&pk.to_bytes(true)).unwrap();
+        let dalek_sig = ed25519_dalek::Signature::from_bytes(&sig_bytes).unwrap();
+
+        dalek_pub
+            .verify_prehashed(Sha512::new().chain(data), Some(context), &dalek_sig)
+            .is_ok()
+    }
+
+    /// Serializes `value` to JSON and to CBOR, deserializes it back and checks that
+    /// re-serializing gives the same output, returns the deserialized JSON value.
+    pub fn assert_serde_roundtrip<T: Serialize + DeserializeOwned>(value: &T) -> T {
+        let json = serde_json::to_string(value).unwrap();
+        let from_json: T = serde_json::from_str(&json).unwrap();
+        assert_eq!(serde_json::to_string(&from_json).unwrap(), json);
+
+        let binary = serde_cbor::to_vec(value).unwrap();
+        let from_binary: T = serde_cbor::from_slice(&binary).unwrap();
+        assert_eq!(serde_cbor::to_vec(&from_binary).unwrap(), binary);
+        from_json
+    }
+
     /// This will generate a fast deterministic rng and will print the seed,
     /// if a test fails, pass in the printed seed to reproduce.
     pub fn deterministic_fast_rand(name: &str, seed: Option<u64>) -> impl Rng {
@@ -135,6 +579,423 @@ pub(crate) mod tests {
         Xoshiro256PlusPlus::seed_from_u64(seed)
     }
 
+    #[test]
+    fn test_serde_roundtrip() {
+        let keypair = ExpandedKeyPair::create();
+        let decoded = assert_serde_roundtrip(&keypair);
+        assert_eq!(decoded.public_key, keypair.public_key);
+
+        let signature = aggsig::sign_single(&[79, 77, 69, 82], &keypair);
+        assert_eq!(assert_serde_roundtrip(&signature), signature);
+    }
+
+    #[test]
+    fn test_verify_batch() {
+        let mut rng = deterministic_fast_rand("test_verify_batch", None);
+        let mut messages = vec![[0u8; 32]; 16];
+        messages.iter_mut().for_each(|m| rng.fill_bytes(m));
+        let keypairs: Vec<_> = (0..messages.len())
+            .map(|_| ExpandedKeyPair::create())
+            .collect();
+        let mut signatures: Vec<_> = messages
+            .iter()
+            .zip(&keypairs)
+            .map(|(message, keypair)| aggsig::sign_single(message, keypair))
+            .collect();
+        let batch = |signatures: &[Signature]| -> Result<(), Vec<usize>> {
+            let entries: Vec<_> = messages
+                .iter()
+                .zip(&keypairs)
+                .zip(signatures)
+                .map(|((message, keypair), signature)| {
+                    (&message[..], &keypair.public_key, signature)
+                })
+                .collect();
+            Signature::verify_batch(&entries, &SignatureMode::Pure, VerificationPolicy::Strict)
+        };
+        assert_eq!(
+            Signature::verify_batch(&[], &SignatureMode::Pure, VerificationPolicy::Strict),
+            Ok(())
+        );
+        assert_eq!(batch(&signatures), Ok(()));
+
+        signatures[3].s = &signatures[3].s + Scalar::from(1);
+        signatures[10].R = signatures[11].R.clone();
+        assert_eq!(batch(&signatures), Err(vec![3, 10]));
+
+        // a valid signature checked against the wrong message
+        let wrong_message = [1u8; 32];
+        assert_eq!(
+            Signature::verify_batch(
+                &[(&wrong_message[..], &keypairs[0].public_key, &signatures[0])],
+                &SignatureMode::Pure,
+                VerificationPolicy::Strict
+            ),
+            Err(vec![0])
+        );
+
+        // s•B = R + k•A holds for any k with the identity as key, only the strict policy
+        // rejects it
+        let r = Scalar::random();
+        let identity_key = Point::zero();
+        let forged = Signature {
+            R: Point::generator() * &r,
+            s: r,
+        };
+        let entries = [
+            (&messages[0][..], &keypairs[0].public_key, &signatures[0]),
+            (&messages[1][..], &identity_key, &forged),
+        ];
+        assert_eq!(
+            Signature::verify_batch(&entries, &SignatureMode::Pure, VerificationPolicy::Strict),
+            Err(vec![1])
+        );
+        assert_eq!(
+            Signature::verify_batch(
+                &entries,
+                &SignatureMode::Pure,
+                VerificationPolicy::Cofactored
+            ),
+            Ok(())
+        );
+
+        // signatures in a mode only verify in that mode
+        let mode = SignatureMode::Context(Context::new(b"batch").unwrap());
+        let keypair = &keypairs[0];
+        let r = Scalar::random();
+        let R = Point::generator() * &r;
+        let k = Signature::k(&R, &keypair.public_key, &messages[0], &mode);
+        let s = r + k * &keypair.expanded_private_key.private_key;
+        let signature = Signature { R, s };
+        let entries = [(&messages[0][..], &keypair.public_key, &signature)];
+        assert_eq!(
+            Signature::verify_batch(&entries, &mode, VerificationPolicy::Strict),
+            Ok(())
+        );
+        assert_eq!(
+            Signature::verify_batch(&entries, &SignatureMode::Pure, VerificationPolicy::Strict),
+            Err(vec![0])
+        );
+    }
+
+    #[test]
+    fn test_rfc8032_ctx_and_ph_vectors() {
+        // RFC 8032 section 7.2 (Ed25519ctx) and 7.3 (Ed25519ph)
+        let foo = SignatureMode::Context(Context::new(b"foo").unwrap());
+        let bar = SignatureMode::Context(Context::new(b"bar").unwrap());
+        let ph = SignatureMode::Prehash(Context::new(b"").unwrap());
+        let vectors = [
+            (
+                "0305334e381af78f141cb666f6199f57bc3495335a256a95bd2a55bf546663f6",
+                "dfc9425e4f968f7f0c29f0259cf5f9aed6851c2bb4ad8bfb860cfee0ab248292",
+                decode("f726936d19c800494e3fdaff20b276a8").unwrap(),
+                &foo,
+                "55a4cc2f70a54e04288c5f4cd1e45a7bb520b36292911876cada7323198dd87a\
+                 8b36950b95130022907a7fb7c4e9b2d5f6cca685a587b4b21f4b888e4e7edb0d",
+            ),
+            (
+                "0305334e381af78f141cb666f6199f57bc3495335a256a95bd2a55bf546663f6",
+                "dfc9425e4f968f7f0c29f0259cf5f9aed6851c2bb4ad8bfb860cfee0ab248292",
+                decode("f726936d19c800494e3fdaff20b276a8").unwrap(),
+                &bar,
+                "fc60d5872fc46b3aa69f8b5b4351d5808f92bcc044606db097abab6dbcb1aee3\
+                 216c48e8b3b66431b5b186d1d28f8ee15a5ca2df6668346291c2043d4eb3e90d",
+            ),
+            (
+                "833fe62409237b9d62ec77587520911e9a759cec1d19755b7da901b96dca3d42",
+                "ec172b93ad5e563bf4932c70e1245034c35467ef2efd4d64ebf819683467e2bf",
+                SignatureMode::prehash(b"abc").to_vec(),
+                &ph,
+                "98a70222f0b8121aa9d30f813d683f809e462b469c7ff87639499bb94e6dae41\
+                 31f85042463c2a355a2003d062adf5aaa10b8c61e636062aaad11c2a26083406",
+            ),
+        ];
+        for (secret, public, message, mode, signature) in vectors.iter() {
+            let keypair = ExpandedKeyPair::create_from_private_key(
+                decode(secret).unwrap().try_into().unwrap(),
+            );
+            assert_eq!(
+                &*keypair.public_key.to_bytes(true),
+                &decode(public).unwrap()[..]
+            );
+            let signature = decode(signature).unwrap();
+            let signature = Signature {
+                R: Point::from_bytes(&signature[..32]).unwrap(),
+                s: Scalar::from_bytes(&signature[32..]).unwrap(),
+            };
+            assert!(signature
+                .verify_with_mode(
+                    message,
+                    &keypair.public_key,
+                    mode,
+                    VerificationPolicy::Strict
+                )
+                .is_ok());
+            assert!(signature.verify(message, &keypair.public_key).is_err());
+        }
+        // the signatures are bound to their context
+        let (_, public, message, _, signature) = &vectors[0];
+        let signature = decode(signature).unwrap();
+        let signature = Signature {
+            R: Point::from_bytes(&signature[..32]).unwrap(),
+            s: Scalar::from_bytes(&signature[32..]).unwrap(),
+        };
+        let public_key = Point::from_bytes(&decode(public).unwrap()).unwrap();
+        assert!(signature
+            .verify_with_mode(message, &public_key, &bar, VerificationPolicy::Strict)
+            .is_err());
+        assert!(signature
+            .verify_with_mode(message, &public_key, &ph, VerificationPolicy::Strict)
+            .is_err());
+    }
+
+    #[test]
+    fn test_signature_mode_serde() {
+        assert!(Context::new(&[0u8; 256]).is_none());
+        let mode = SignatureMode::Prehash(Context::new(&[7u8; 255]).unwrap());
+        assert_eq!(assert_serde_roundtrip(&mode), mode);
+        assert_eq!(
+            assert_serde_roundtrip(&SignatureMode::default()),
+            SignatureMode::Pure
+        );
+        assert_eq!(
+            Context::try_from(vec![0u8; 256]),
+            Err(Error::ContextTooLong(256))
+        );
+        assert_eq!(
+            Error::ContextTooLong(256).to_string(),
+            "context of 256 bytes is longer than 255 bytes"
+        );
+        let too_long = serde_json::to_string(&vec![0u8; 256]).unwrap();
+        assert!(serde_json::from_str::<Context>(&too_long).is_err());
+    }
+
+    #[test]
+    fn test_verification_policies() {
+        // Edge cases from "Taming the many EdDSAs" (https://github.com/novifinancial/ed25519-speccheck),
+        // as (message, public key, signature, accepted by Strict, Cofactored, Zip215)
+        let cases = [
+            // small order A and R
+            (
+                "8c93255d71dcab10e8f379c26200f3c7bd5f09d9bc3068d3ef4edeb4853022b6",
+                "c7176a703d4dd84fba3c0b760d10670f2a2053fa2c39ccc64ec7fd7792ac03fa",
+                "c7176a703d4dd84fba3c0b760d10670f2a2053fa2c39ccc64ec7fd7792ac037a\
+                 0000000000000000000000000000000000000000000000000000000000000000",
+                [false, true, true],
+            ),
+            // small order A, mixed order R
+            (
+                "9bd9f44f4dcc75bd531b56b2cd280b0bb38fc1cd6d1230e14861d861de092e79",
+                "c7176a703d4dd84fba3c0b760d10670f2a2053fa2c39ccc64ec7fd7792ac03fa",
+                "f7badec5b8abeaf699583992219b7b223f1df3fbbea919844e3f7c554a43dd43\
+                 a5bb704786be79fc476f91d3f3f89b03984d8068dcf1bb7dfc6637b45450ac04",
+                [false, true, true],
+            ),
+            // mixed order A, small order R
+            (
+                "aebf3f2601a0c8c5d39cc7d8911642f740b78168218da8471772b35f9d35b9ab",
+                "f7badec5b8abeaf699583992219b7b223f1df3fbbea919844e3f7c554a43dd43",
+                "c7176a703d4dd84fba3c0b760d10670f2a2053fa2c39ccc64ec7fd7792ac03fa\
+                 8c4bd45aecaca5b24fb97bc10ac27ac8751a7dfe1baff8b953ec9f5833ca260e",
+                [false, true, true],
+            ),
+            // mixed order A and R, both equations hold
+            (
+                "9bd9f44f4dcc75bd531b56b2cd280b0bb38fc1cd6d1230e14861d861de092e79",
+                "cdb267ce40c5cd45306fa5d2f29731459387dbf9eb933b7bd5aed9a765b88d4d",
+                "9046a64750444938de19f227bb80485e92b83fdb4b6506c160484c016cc1852f\
+                 87909e14428a7a1d62e9f22f3d3ad7802db02eb2e688b6c52fcd6648a98bd009",
+                [true, true, true],
+            ),
+            // mixed order A and R, only the cofactored equation holds
+            (
+                "e47d62c63f830dc7a6851a0b1f33ae4bb2f507fb6cffec4011eaccd55b53f56c",
+                "cdb267ce40c5cd45306fa5d2f29731459387dbf9eb933b7bd5aed9a765b88d4d",
+                "160a1cb0dc9c0258cd0a7d23e94d8fa878bcb1925f2c64246b2dee1796bed512\
+                 5ec6bc982a269b723e0668e540911a9a6a58921d6925e434ab10aa7940551a09",
+                [false, true, true],
+            ),
+            // mixed order A, only the cofactored equation holds
+            (
+                "e47d62c63f830dc7a6851a0b1f33ae4bb2f507fb6cffec4011eaccd55b53f56c",
+                "cdb267ce40c5cd45306fa5d2f29731459387dbf9eb933b7bd5aed9a765b88d4d",
+                "21122a84e0b5fca4052f5b1235c80a537878b38f3142356b2c2384ebad4668b7\
+                 e40bc836dac0f71076f9abe3a53f9c03c1ceeeddb658d0030494ace586687405",
+                [false, true, true],
+            ),
+            // s >= L
+            (
+                "85e241a07d148b41e47d62c63f830dc7a6851a0b1f33ae4bb2f507fb6cffec40",
+                "442aad9f089ad9e14647b1ef9099a1ff4798d78589e66f28eca69c11f582a623",
+                "e96f66be976d82e60150baecff9906684aebb1ef181f67a7189ac78ea23b6c0e\
+                 547f7690a0e2ddcd04d87dbc3490dc19b3b3052f7ff0538cb68afb369ba3a514",
+                [false, false, false],
+            ),
+            // s much larger than L
+            (
+                "85e241a07d148b41e47d62c63f830dc7a6851a0b1f33ae4bb2f507fb6cffec40",
+                "442aad9f089ad9e14647b1ef9099a1ff4798d78589e66f28eca69c11f582a623",
+                "8ce5b96c8f26d0ab6c47958c9e68b937104cd36e13c33566acd2fe8d38aa1942\
+                 7e71f98a473474f2f13f06f97c20d58cc3f54b8bd0d272f42b695dd7e89a8c22",
+                [false, false, false],
+            ),
+            // small order R
+            (
+                "9bedc267423725d473888631ebf45988bad3db83851ee85c85e241a07d148b41",
+                "f7badec5b8abeaf699583992219b7b223f1df3fbbea919844e3f7c554a43dd43",
+                "ecffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff7f\
+                 03be9678ac102edcd92b0210bb34d7428d12ffc5df5f37e359941266a4e35f0f",
+                [false, true, true],
+            ),
+            // non-canonical R
+            (
+                "9bedc267423725d473888631ebf45988bad3db83851ee85c85e241a07d148b41",
+                "f7badec5b8abeaf699583992219b7b223f1df3fbbea919844e3f7c554a43dd43",
+                "ecffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff\
+                 ca8c5b64cd208982aa38d4936621a4775aa233aa0505711d8fdcfdaa943d4908",
+                [false, false, true],
+            ),
+            // non-canonical A
+            (
+                "e96b7021eb39c1a163b6da4e3093dcd3f21387da4cc4572be588fafae23c155b",
+                "ecffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff",
+                "a9d55260f765261eb9b84e106f665e00b867287a761990d7135963ee0a7d59dc\
+                 a5bb704786be79fc476f91d3f3f89b03984d8068dcf1bb7dfc6637b45450ac04",
+                [false, false, true],
+            ),
+            (
+                "39a591f5321bbe07fd5a23dc2f39d025d74526615746727ceefd6e82ae65c06f",
+                "ecffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff",
+                "a9d55260f765261eb9b84e106f665e00b867287a761990d7135963ee0a7d59dc\
+                 a5bb704786be79fc476f91d3f3f89b03984d8068dcf1bb7dfc6637b45450ac04",
+                [false, false, true],
+            ),
+        ];
+        let policies = [
+            VerificationPolicy::Strict,
+            VerificationPolicy::Cofactored,
+            VerificationPolicy::Zip215,
+        ];
+        for (i, (message, public_key, signature, expected)) in cases.iter().enumerate() {
+            for (policy, expected) in policies.iter().zip(expected.iter()) {
+                let result = Signature::verify_bytes(
+                    &decode(message).unwrap(),
+                    &decode(public_key).unwrap(),
+                    &decode(signature).unwrap(),
+                    &SignatureMode::Pure,
+                    *policy,
+                );
+                assert_eq!(result.is_ok(), *expected, "case {} with {:?}", i, policy);
+            }
+        }
+
+        // all policies agree on honest signatures
+        let keypair = ExpandedKeyPair::create();
+        let message = [79, 77, 69, 82];
+        let signature = aggsig::sign_single(&message, &keypair);
+        for policy in policies.iter() {
+            assert!(signature
+                .verify_with_mode(&message, &keypair.public_key, &SignatureMode::Pure, *policy)
+                .is_ok());
+            assert!(Signature::verify_bytes(
+                &message,
+                &keypair.public_key.to_bytes(true),
+                &signature.to_bytes(),
+                &SignatureMode::Pure,
+                *policy
+            )
+            .is_ok());
+        }
+        // a signature for the identity public key is only rejected by the strict policy
+        let zero_key_signature = Signature {
+            R: Point::generator() * Scalar::from(3),
+            s: Scalar::from(3),
+        };
+        let policy_results: Vec<_> = policies
+            .iter()
+            .map(|policy| {
+                zero_key_signature
+                    .verify_with_mode(&message, &Point::zero(), &SignatureMode::Pure, *policy)
+                    .is_ok()
+            })
+            .collect();
+        assert_eq!(policy_results, vec![false, true, true]);
+    }
+
+    #[test]
+    fn test_signature_encoding() {
+        let keypair = ExpandedKeyPair::create();
+        let signature = aggsig::sign_single(&[79, 77, 69, 82], &keypair);
+        let bytes = signature.to_bytes();
+        assert_eq!(Signature::from_bytes(&bytes).unwrap(), signature);
+        assert_eq!(Signature::from_bytes(&bytes[..63]), Err(Error::InvalidSig));
+
+        // s + L
+        let mut non_canonical_s = bytes;
+        let mut carry = 0u16;
+        for (byte, l) in non_canonical_s[32..]
+            .iter_mut()
+            .zip(BASEPOINT_ORDER.as_bytes())
+        {
+            let sum = u16::from(*byte) + u16::from(*l) + carry;
+            *byte = sum as u8;
+            carry = sum >> 8;
+        }
+        assert_eq!(
+            Signature::from_bytes(&non_canonical_s),
+            Err(Error::InvalidSig)
+        );
+
+        let bad_Rs = [
+            EdwardsPoint::default().compress().to_bytes(),
+            EIGHT_TORSION[1].compress().to_bytes(),
+            NON_CANONICAL_IDENTITY,
+            mixed_order(&signature.R),
+        ];
+        for bad_R in bad_Rs.iter() {
+            let mut bad_signature = bytes;
+            bad_signature[..32].copy_from_slice(bad_R);
+            assert_eq!(
+                Signature::from_bytes(&bad_signature),
+                Err(Error::InvalidSig)
+            );
+        }
+    }
+
+    #[test]
+    fn test_public_key_encoding() {
+        let keypair = ExpandedKeyPair::create();
+        let bytes = keypair.public_key.to_bytes(true);
+        let public_key = PublicKey::from_bytes(&bytes).unwrap();
+        assert_eq!(public_key.as_point(), &keypair.public_key);
+        assert_eq!(public_key.to_bytes()[..], bytes[..]);
+        assert_eq!(assert_serde_roundtrip(&public_key), public_key);
+        assert_eq!(PublicKey::from_bytes(&bytes[1..]), Err(Error::InvalidKey));
+
+        let bad_keys = [
+            EdwardsPoint::default().compress().to_bytes(),
+            EIGHT_TORSION[1].compress().to_bytes(),
+            NON_CANONICAL_IDENTITY,
+            mixed_order(&keypair.public_key),
+        ];
+        for bad_key in bad_keys.iter() {
+            assert_eq!(PublicKey::from_bytes(bad_key), Err(Error::InvalidKey));
+        }
+        assert_eq!(PublicKey::try_from(Point::zero()), Err(Error::InvalidKey));
+        let identity = serde_json::to_string(&Point::<Ed25519>::zero()).unwrap();
+        assert!(serde_json::from_str::<PublicKey>(&identity).is_err());
+    }
+
+    #[test]
+    fn test_debug_redacts_secrets() {
+        let keypair = ExpandedKeyPair::create();
+        let debug = format!("{:?}", keypair);
+        let private_key = &keypair.expanded_private_key.private_key;
+        assert!(!debug.contains(&format!("{:?}", private_key)));
+        assert!(!debug.contains(&format!("{:?}", keypair.expanded_private_key.prefix)));
+        assert!(debug.contains(&format!("{:?}", keypair.public_key)));
+    }
+
     #[test]
     fn test_generate_pubkey_dalek() {
         let mut rng = deterministic_fast_rand("test_generate_pubkey_dalek", None);
@@ -172,12 +1033,11 @@ pub(crate) mod tests {
                 let dalek_pub = ed25519_dalek::PublicKey::from(&dalek_secret);
                 let dalek_sig = dalek_secret.sign(msg, &dalek_pub);
 
-                let zengo_sig = Signature {
-                    R: Point::from_bytes(&dalek_sig.as_ref()[..32]).unwrap(),
-                    s: Scalar::from_bytes(&dalek_sig.as_ref()[32..]).unwrap(),
-                };
-                let zengo_pubkey = Point::from_bytes(&dalek_pub.to_bytes()).unwrap();
-                zengo_sig.verify(msg, &zengo_pubkey).unwrap();
+                let zengo_sig = Signature::from_bytes(dalek_sig.as_ref()).unwrap();
+                assert_eq!(zengo_sig.to_bytes()[..], dalek_sig.as_ref()[..]);
+                let zengo_pubkey = PublicKey::from_bytes(&dalek_pub.to_bytes()).unwrap();
+                assert_eq!(zengo_pubkey.to_bytes(), dalek_pub.to_bytes());
+                zengo_sig.verify(msg, zengo_pubkey.as_point()).unwrap();
             }
         }
     }
@@ -16,29 +16,49 @@
 */
 //! Schnorr {n,n}-Signatures based on Accountable-Subgroup Multisignatures
 //!
+//! A `Group` can also sign with any subgroup of its members, the `SubgroupSignature` lists the
+//! signers so the verifier knows who signed.
+//!
 //See (https://pdfs.semanticscholar.org/6bf4/f9450e7a8e31c106a8670b961de4735589cf.pdf)
 
 use super::ExpandedKeyPair;
 
+use curv::arithmetic::Converter;
 use curv::cryptographic_primitives::hashing::DigestExt;
 use curv::elliptic::curves::{Ed25519, Point, Scalar};
 use curv::BigInt;
-use protocols::multisig;
+use protocols::scheme::{self, KeyAggregation, MultiPartySignature};
+use protocols::{self, multisig, SignatureMode, VerificationPolicy, REDACTED};
 
 use sha2::{digest::Digest, Sha512};
+use std::collections::BTreeMap;
+use std::fmt;
+use Error::{
+    self, EmptyInput, InvalidKey, InvalidPartialSig, InvalidProof, InvalidSig, MissingParty,
+    UnknownParty, WrongLength,
+};
 
 // I is a private key and public key keypair, X is a commitment of the form X = xG used only in key generation (see p11 in the paper)
-#[derive(Debug, Clone)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct Keys {
     pub I: ExpandedKeyPair,
     pub X: SingleKeyPair,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Serialize, Deserialize)]
 pub struct SingleKeyPair {
     pub public_key: Point<Ed25519>,
     private_key: Scalar<Ed25519>,
 }
+
+impl fmt::Debug for SingleKeyPair {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SingleKeyPair")
+            .field("public_key", &self.public_key)
+            .field("private_key", &REDACTED)
+            .finish()
+    }
+}
 impl SingleKeyPair {
     pub fn create() -> SingleKeyPair {
         let ec_point = Point::generator();
@@ -87,15 +107,15 @@ impl Keys {
         Keys { I, X }
     }
 
-    pub fn create_signing_key(keys: &Keys, eph_key: &EphKey) -> Keys {
+    pub fn create_signing_key(keys: Keys, eph_key: EphKey) -> Keys {
         Keys {
-            I: keys.I.clone(),
-            X: eph_key.eph_key_pair.clone(),
+            I: keys.I,
+            X: eph_key.eph_key_pair,
         }
     }
 
-    pub fn broadcast(keys: Keys) -> Vec<Point<Ed25519>> {
-        return vec![keys.I.public_key, keys.X.public_key];
+    pub fn broadcast(keys: &Keys) -> Vec<Point<Ed25519>> {
+        vec![keys.I.public_key.clone(), keys.X.public_key.clone()]
     }
 
     pub fn collect_and_compute_challenge(ix_vec: &[Vec<Point<Ed25519>>]) -> Scalar<Ed25519> {
@@ -111,7 +131,7 @@ pub fn partial_sign(keys: &Keys, e: Scalar<Ed25519>) -> Scalar<Ed25519> {
     e * &keys.I.expanded_private_key.private_key + &keys.X.private_key
 }
 
-pub fn verify<'a>(I: &Point<Ed25519>, sig: &Signature, e: &Scalar<Ed25519>) -> Result<(), &'a str> {
+pub fn verify(I: &Point<Ed25519>, sig: &Signature, e: &Scalar<Ed25519>) -> Result<(), Error> {
     let X = &sig.X;
     let y = &sig.y;
     let base_point = Point::generator();
@@ -121,7 +141,7 @@ pub fn verify<'a>(I: &Point<Ed25519>, sig: &Signature, e: &Scalar<Ed25519>) -> R
     if yG == X_plus_eI {
         Ok(())
     } else {
-        Err("error verification")
+        Err(InvalidSig)
     }
 }
 
@@ -135,6 +155,41 @@ fn hash_4(key_list: &[Point<Ed25519>]) -> Scalar<Ed25519> {
         .result_scalar()
 }
 
+// the sums of the public keys and of the ephemeral public keys, one per public key
+fn sum_keys(
+    pub_key_vec: &[Point<Ed25519>],
+    eph_pub_key_vec: &[Point<Ed25519>],
+) -> Result<(Point<Ed25519>, Point<Ed25519>), Error> {
+    if eph_pub_key_vec.len() != pub_key_vec.len() {
+        return Err(WrongLength {
+            expected: pub_key_vec.len(),
+            received: eph_pub_key_vec.len(),
+        });
+    }
+    let (first_pub_key, pub_key_vec) = pub_key_vec.split_first().ok_or(EmptyInput)?;
+    let sum_pub = pub_key_vec
+        .iter()
+        .fold(first_pub_key.clone(), |acc, x| acc + x);
+    let (first_eph_pub_key, eph_pub_key_vec) = eph_pub_key_vec.split_first().ok_or(EmptyInput)?;
+    let sum_pub_eph = eph_pub_key_vec
+        .iter()
+        .fold(first_eph_pub_key.clone(), |acc, x| acc + x);
+    Ok((sum_pub, sum_pub_eph))
+}
+
+// the challenge `e` for the aggregated ephemeral key `X` and the aggregated public key `I`
+fn challenge(X: &Point<Ed25519>, message: &BigInt, I: &Point<Ed25519>) -> Scalar<Ed25519> {
+    //TODO: maybe there is a better way?
+    let m_fe = Scalar::from_bigint(message);
+    let base_point = Point::generator();
+    let m_ge = base_point * m_fe;
+    multisig::hash_4(&[X.clone(), m_ge, I.clone()])
+}
+
+/// The aggregated public key, the aggregated ephemeral key and the challenge `e`.
+pub type JointCommitment = (Point<Ed25519>, Point<Ed25519>, Scalar<Ed25519>);
+
+#[derive(Debug, Serialize, Deserialize)]
 pub struct EphKey {
     pub eph_key_pair: SingleKeyPair,
 }
@@ -145,7 +200,7 @@ impl EphKey {
         // here we deviate from the spec, by introducing  non-deterministic element (random number)
         // to the nonce
         let r = Sha512::new()
-            .chain_scalar(&key_gen_key_pair.expanded_private_key.prefix)
+            .chain(key_gen_key_pair.expanded_private_key.prefix)
             .chain_bigint(message)
             .chain_scalar(&Scalar::<Ed25519>::random())
             .result_bigint();
@@ -159,23 +214,29 @@ impl EphKey {
     }
     //signing steps 2,3
     // we treat S as a list of public keys and compute a sum.
+    // there must be one ephemeral public key per public key.
     pub fn compute_joint_comm_e(
-        mut pub_key_vec: Vec<Point<Ed25519>>,
-        mut eph_pub_key_vec: Vec<Point<Ed25519>>,
+        pub_key_vec: Vec<Point<Ed25519>>,
+        eph_pub_key_vec: Vec<Point<Ed25519>>,
         message: &BigInt,
-    ) -> (Point<Ed25519>, Point<Ed25519>, Scalar<Ed25519>) {
-        let first_pub_key = pub_key_vec.remove(0);
-        let sum_pub = pub_key_vec.iter().fold(first_pub_key, |acc, x| acc + x);
-        let first_eph_pub_key = eph_pub_key_vec.remove(0);
-        let sum_pub_eph = eph_pub_key_vec
-            .iter()
-            .fold(first_eph_pub_key, |acc, x| acc + x);
-        //TODO: maybe there is a better way?
-        let m_fe = Scalar::from_bigint(message);
-        let base_point = Point::generator();
-        let m_ge = base_point * m_fe;
-        let e = multisig::hash_4(&[sum_pub_eph.clone(), m_ge, sum_pub.clone()]);
-        (sum_pub, sum_pub_eph, e)
+    ) -> Result<JointCommitment, Error> {
+        let (sum_pub, sum_pub_eph) = sum_keys(&pub_key_vec, &eph_pub_key_vec)?;
+        let e = challenge(&sum_pub_eph, message, &sum_pub);
+        Ok((sum_pub, sum_pub_eph, e))
+    }
+
+    /// Like `compute_joint_comm_e`, with the challenge of Ed25519 for the bytes of `message`
+    /// instead, so that the signature is an Ed25519 signature for the aggregated public key, see
+    /// `Signature::to_ed25519`.
+    pub fn compute_joint_comm_k(
+        pub_key_vec: Vec<Point<Ed25519>>,
+        eph_pub_key_vec: Vec<Point<Ed25519>>,
+        message: &[u8],
+        mode: &SignatureMode,
+    ) -> Result<JointCommitment, Error> {
+        let (sum_pub, sum_pub_eph) = sum_keys(&pub_key_vec, &eph_pub_key_vec)?;
+        let k = protocols::Signature::k(&sum_pub_eph, &sum_pub, message, mode);
+        Ok((sum_pub, sum_pub_eph, k))
     }
 
     pub fn partial_sign(
@@ -186,14 +247,14 @@ impl EphKey {
         es * &local_keys.expanded_private_key.private_key + &self.eph_key_pair.private_key
     }
 
-    pub fn add_signature_parts(sig_vec: Vec<Scalar<Ed25519>>) -> Scalar<Ed25519> {
-        let mut sig_vec_c = sig_vec;
-        let first_sig = sig_vec_c.remove(0);
+    pub fn add_signature_parts(sig_vec: Vec<Scalar<Ed25519>>) -> Result<Scalar<Ed25519>, Error> {
+        let (first_sig, sig_vec) = sig_vec.split_first().ok_or(EmptyInput)?;
 
-        sig_vec_c.iter().fold(first_sig, |acc, x| acc + x)
+        Ok(sig_vec.iter().fold(first_sig.clone(), |acc, x| acc + x))
     }
 }
 
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
 pub struct Signature {
     X: Point<Ed25519>,
     y: Scalar<Ed25519>,
@@ -206,6 +267,433 @@ impl Signature {
             y: y.clone(),
         }
     }
+
+    /// The signature as an Ed25519 signature, it verifies with `protocols::Signature::verify` if
+    /// it was made with the challenge of `EphKey::compute_joint_comm_k`.
+    pub fn to_ed25519(&self) -> protocols::Signature {
+        protocols::Signature {
+            R: self.X.clone(),
+            s: self.y.clone(),
+        }
+    }
+}
+
+/// The members of a group, any subgroup of which can sign. Members are indexed by their
+/// position in the group.
+///
+/// The group is set up once (p11 in the paper): every member broadcasts `Keys::broadcast`, and
+/// once all of them are in proves it knows its secret key with `partial_sign`, for the challenge
+/// of `Keys::collect_and_compute_challenge`. The proofs keep a member from choosing its key
+/// after seeing the others, which would let it sign for subgroups it's not part of.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Group {
+    public_keys: Vec<Point<Ed25519>>,
+}
+
+impl Group {
+    /// Checks the proofs of the members, `ix_vec` are their broadcasts and `proofs` the outputs of
+    /// their `partial_sign`, both in member order.
+    pub fn setup(
+        ix_vec: &[Vec<Point<Ed25519>>],
+        proofs: &[Scalar<Ed25519>],
+    ) -> Result<Group, Error> {
+        if ix_vec.is_empty() {
+            return Err(EmptyInput);
+        }
+        if proofs.len() != ix_vec.len() {
+            return Err(WrongLength {
+                expected: ix_vec.len(),
+                received: proofs.len(),
+            });
+        }
+        if let Some(ix) = ix_vec.iter().find(|ix| ix.len() != 2) {
+            return Err(WrongLength {
+                expected: 2,
+                received: ix.len(),
+            });
+        }
+        let e = Keys::collect_and_compute_challenge(ix_vec);
+        let bad_members: Vec<u16> = ix_vec
+            .iter()
+            .zip(proofs)
+            .enumerate()
+            .filter(|(_, (ix, y))| {
+                verify(&ix[0], &Signature::set_signature(&ix[1], y), &e).is_err()
+            })
+            .map(|(i, _)| i as u16)
+            .collect();
+        if !bad_members.is_empty() {
+            return Err(InvalidProof(bad_members));
+        }
+        Ok(Group {
+            public_keys: ix_vec.iter().map(|ix| ix[0].clone()).collect(),
+        })
+    }
+
+    pub fn public_keys(&self) -> &[Point<Ed25519>] {
+        &self.public_keys
+    }
+
+    /// The public keys of `signers`, which must be members listed in increasing order.
+    pub fn subgroup_public_keys(&self, signers: &[u16]) -> Result<Vec<Point<Ed25519>>, Error> {
+        if signers.is_empty() {
+            return Err(EmptyInput);
+        }
+        if signers.windows(2).any(|pair| pair[0] >= pair[1]) {
+            return Err(InvalidKey);
+        }
+        signers
+            .iter()
+            .map(|&i| {
+                self.public_keys
+                    .get(usize::from(i))
+                    .cloned()
+                    .ok_or(UnknownParty(i))
+            })
+            .collect()
+    }
+}
+
+/// The key a member signs with for the subgroups it's part of.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MembershipKey {
+    keys: ExpandedKeyPair,
+    group: Group,
+    index: u16,
+}
+
+impl MembershipKey {
+    pub fn new(keys: ExpandedKeyPair, group: Group) -> Result<MembershipKey, Error> {
+        let index = group
+            .public_keys
+            .iter()
+            .position(|public_key| *public_key == keys.public_key)
+            .ok_or(InvalidKey)?;
+        Ok(MembershipKey {
+            keys,
+            group,
+            index: index as u16,
+        })
+    }
+
+    pub fn keys(&self) -> &ExpandedKeyPair {
+        &self.keys
+    }
+
+    pub fn group(&self) -> &Group {
+        &self.group
+    }
+
+    pub fn index(&self) -> u16 {
+        self.index
+    }
+
+    /// Signs `message` for the subgroup of `signers`, this member included, `eph_pub_key_vec` are
+    /// the ephemeral public keys of the signers in the same order.
+    pub fn subgroup_sign(
+        &self,
+        eph_key: EphKey,
+        signers: &[u16],
+        eph_pub_key_vec: Vec<Point<Ed25519>>,
+        message: &BigInt,
+    ) -> Result<Scalar<Ed25519>, Error> {
+        if !signers.contains(&self.index) {
+            return Err(MissingParty(self.index));
+        }
+        let (_, _, es) = EphKey::compute_joint_comm_e(
+            self.group.subgroup_public_keys(signers)?,
+            eph_pub_key_vec,
+            message,
+        )?;
+        Ok(eph_key.partial_sign(&self.keys, es))
+    }
+}
+
+/// A signature of a subgroup, along with the members that made it.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct SubgroupSignature {
+    pub signers: Vec<u16>,
+    pub signature: Signature,
+}
+
+impl SubgroupSignature {
+    /// Adds up the partial signatures of `signers`, `eph_pub_key_vec` and `sig_vec` are in the
+    /// same order as `signers`.
+    pub fn combine(
+        signers: Vec<u16>,
+        eph_pub_key_vec: &[Point<Ed25519>],
+        sig_vec: Vec<Scalar<Ed25519>>,
+    ) -> Result<SubgroupSignature, Error> {
+        for received in [eph_pub_key_vec.len(), sig_vec.len()] {
+            if received != signers.len() {
+                return Err(WrongLength {
+                    expected: signers.len(),
+                    received,
+                });
+            }
+        }
+        let X = eph_pub_key_vec.iter().sum();
+        let y = EphKey::add_signature_parts(sig_vec)?;
+        Ok(SubgroupSignature {
+            signers,
+            signature: Signature { X, y },
+        })
+    }
+}
+
+/// Verifies the signature of a subgroup of `group`, with the aggregated public key of the
+/// members the signature lists.
+pub fn verify_subgroup(
+    group: &Group,
+    message: &BigInt,
+    signature: &SubgroupSignature,
+) -> Result<(), Error> {
+    let I: Point<Ed25519> = group.subgroup_public_keys(&signature.signers)?.iter().sum();
+    let e = challenge(&signature.signature.X, message, &I);
+    verify(&I, &signature.signature, &e)
+}
+
+/// {n,n} signing as a `MultiPartySignature`, the message is read as a big endian number. The
+/// signatures are not Ed25519 signatures, they verify with `verify`.
+///
+/// The public keys are simply added up, so the signers sign with a `MembershipKey` of a group
+/// that went through `Group::setup`, where every member proved it knows its secret key. There
+/// are no signature modes, signing fails in any mode but `SignatureMode::Pure`.
+pub struct MultiSig;
+
+fn joint_commitment(
+    key: &MembershipKey,
+    nonces: &BTreeMap<u16, Point<Ed25519>>,
+    message: &[u8],
+    mode: &SignatureMode,
+) -> Result<JointCommitment, Error> {
+    if *mode != SignatureMode::Pure {
+        return Err(InvalidKey);
+    }
+    EphKey::compute_joint_comm_e(
+        key.group.public_keys.clone(),
+        nonces.values().cloned().collect(),
+        &BigInt::from_bytes(message),
+    )
+}
+
+// checks the partial signature of `signer` for the challenge `es`
+fn verify_partial(
+    key: &MembershipKey,
+    nonces: &BTreeMap<u16, Point<Ed25519>>,
+    signer: u16,
+    partial_sig: &Scalar<Ed25519>,
+    es: &Scalar<Ed25519>,
+) -> Result<(), Error> {
+    let signer_nonce = nonces
+        .get(&signer)
+        .ok_or_else(|| InvalidPartialSig(vec![signer]))?;
+    let signer_public_key = key
+        .group
+        .public_keys
+        .get(usize::from(signer))
+        .ok_or(UnknownParty(signer))?;
+    verify(
+        signer_public_key,
+        &Signature::set_signature(signer_nonce, partial_sig),
+        es,
+    )
+    .map_err(|_| InvalidPartialSig(vec![signer]))
+}
+
+impl KeyAggregation for MultiSig {
+    type KeyShare = MembershipKey;
+
+    fn party_index(key: &MembershipKey) -> u16 {
+        key.index
+    }
+
+    fn public_key(key: &MembershipKey) -> Point<Ed25519> {
+        key.group.public_keys.iter().sum()
+    }
+
+    // every member has to sign, subgroups sign with `MembershipKey::subgroup_sign`
+    fn check_signers(key: &MembershipKey, signers: &[u16]) -> Result<(), Error> {
+        scheme::check_all_signers(signers, key.group.public_keys.len())
+    }
+}
+
+impl MultiPartySignature for MultiSig {
+    const COMMITS_TO_NONCES: bool = false;
+
+    type NonceCommitment = ();
+    type SecretNonces = EphKey;
+    type PublicNonces = Point<Ed25519>;
+    type PartialSignature = Scalar<Ed25519>;
+    type Signature = Signature;
+
+    fn generate_nonces(
+        key: &MembershipKey,
+        message: &[u8],
+        _mode: &SignatureMode,
+    ) -> (EphKey, (), Point<Ed25519>) {
+        let eph_key = EphKey::gen_commit(&key.keys, &BigInt::from_bytes(message));
+        let public_nonce = eph_key.eph_key_pair.public_key.clone();
+        (eph_key, (), public_nonce)
+    }
+
+    fn check_nonces(_commitment: &(), _nonces: &Point<Ed25519>) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn partial_sign(
+        key: &MembershipKey,
+        secret_nonces: EphKey,
+        nonces: &BTreeMap<u16, Point<Ed25519>>,
+        message: &[u8],
+        mode: &SignatureMode,
+    ) -> Result<Scalar<Ed25519>, Error> {
+        let (_, _, es) = joint_commitment(key, nonces, message, mode)?;
+        Ok(secret_nonces.partial_sign(&key.keys, es))
+    }
+
+    fn verify_partial(
+        key: &MembershipKey,
+        nonces: &BTreeMap<u16, Point<Ed25519>>,
+        message: &[u8],
+        mode: &SignatureMode,
+        signer: u16,
+        partial_sig: &Scalar<Ed25519>,
+    ) -> Result<(), Error> {
+        let (_, _, es) = joint_commitment(key, nonces, message, mode)?;
+        verify_partial(key, nonces, signer, partial_sig, &es)
+    }
+
+    fn combine(
+        key: &MembershipKey,
+        nonces: &BTreeMap<u16, Point<Ed25519>>,
+        message: &[u8],
+        mode: &SignatureMode,
+        partial_sigs: &BTreeMap<u16, Scalar<Ed25519>>,
+    ) -> Result<Signature, Error> {
+        let (_, Xt, _) = joint_commitment(key, nonces, message, mode)?;
+        let y = EphKey::add_signature_parts(partial_sigs.values().cloned().collect())?;
+        Ok(Signature::set_signature(&Xt, &y))
+    }
+
+    fn verify(
+        public_key: &Point<Ed25519>,
+        message: &[u8],
+        mode: &SignatureMode,
+        signature: &Signature,
+    ) -> Result<(), Error> {
+        if *mode != SignatureMode::Pure {
+            return Err(InvalidSig);
+        }
+        let (_, _, es) = EphKey::compute_joint_comm_e(
+            vec![public_key.clone()],
+            vec![signature.X.clone()],
+            &BigInt::from_bytes(message),
+        )?;
+        verify(public_key, signature, &es)
+    }
+}
+
+/// {n,n} signing as a `MultiPartySignature` with the challenge of Ed25519, see
+/// `EphKey::compute_joint_comm_k`. The signatures are plain Ed25519 signatures for the sum of the
+/// public keys, which are set up like for `MultiSig`.
+pub struct Ed25519MultiSig;
+
+fn joint_commitment_k(
+    key: &MembershipKey,
+    nonces: &BTreeMap<u16, Point<Ed25519>>,
+    message: &[u8],
+    mode: &SignatureMode,
+) -> Result<JointCommitment, Error> {
+    EphKey::compute_joint_comm_k(
+        key.group.public_keys.clone(),
+        nonces.values().cloned().collect(),
+        message,
+        mode,
+    )
+}
+
+impl KeyAggregation for Ed25519MultiSig {
+    type KeyShare = MembershipKey;
+
+    fn party_index(key: &MembershipKey) -> u16 {
+        MultiSig::party_index(key)
+    }
+
+    fn public_key(key: &MembershipKey) -> Point<Ed25519> {
+        MultiSig::public_key(key)
+    }
+
+    fn check_signers(key: &MembershipKey, signers: &[u16]) -> Result<(), Error> {
+        MultiSig::check_signers(key, signers)
+    }
+}
+
+impl MultiPartySignature for Ed25519MultiSig {
+    const COMMITS_TO_NONCES: bool = false;
+
+    type NonceCommitment = ();
+    type SecretNonces = EphKey;
+    type PublicNonces = Point<Ed25519>;
+    type PartialSignature = Scalar<Ed25519>;
+    type Signature = protocols::Signature;
+
+    fn generate_nonces(
+        key: &MembershipKey,
+        message: &[u8],
+        mode: &SignatureMode,
+    ) -> (EphKey, (), Point<Ed25519>) {
+        MultiSig::generate_nonces(key, message, mode)
+    }
+
+    fn check_nonces(_commitment: &(), _nonces: &Point<Ed25519>) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn partial_sign(
+        key: &MembershipKey,
+        secret_nonces: EphKey,
+        nonces: &BTreeMap<u16, Point<Ed25519>>,
+        message: &[u8],
+        mode: &SignatureMode,
+    ) -> Result<Scalar<Ed25519>, Error> {
+        let (_, _, k) = joint_commitment_k(key, nonces, message, mode)?;
+        Ok(secret_nonces.partial_sign(&key.keys, k))
+    }
+
+    fn verify_partial(
+        key: &MembershipKey,
+        nonces: &BTreeMap<u16, Point<Ed25519>>,
+        message: &[u8],
+        mode: &SignatureMode,
+        signer: u16,
+        partial_sig: &Scalar<Ed25519>,
+    ) -> Result<(), Error> {
+        let (_, _, k) = joint_commitment_k(key, nonces, message, mode)?;
+        verify_partial(key, nonces, signer, partial_sig, &k)
+    }
+
+    fn combine(
+        key: &MembershipKey,
+        nonces: &BTreeMap<u16, Point<Ed25519>>,
+        message: &[u8],
+        mode: &SignatureMode,
+        partial_sigs: &BTreeMap<u16, Scalar<Ed25519>>,
+    ) -> Result<protocols::Signature, Error> {
+        let (_, Xt, _) = joint_commitment_k(key, nonces, message, mode)?;
+        let y = EphKey::add_signature_parts(partial_sigs.values().cloned().collect())?;
+        Ok(Signature::set_signature(&Xt, &y).to_ed25519())
+    }
+
+    fn verify(
+        public_key: &Point<Ed25519>,
+        message: &[u8],
+        mode: &SignatureMode,
+        signature: &protocols::Signature,
+    ) -> Result<(), Error> {
+        signature.verify_with_mode(message, public_key, mode, VerificationPolicy::default())
+    }
 }
 
+pub mod state_machine;
 mod test;
@@ -0,0 +1,184 @@
+#![allow(non_snake_case)]
+/*
+    multi-party-ed25519
+
+    Copyright 2018 by Kzen Networks
+
+    This file is part of multi-party-ed25519 library
+    (https://github.com/KZen-networks/multisig-schnorr)
+
+    multi-party-ed25519 is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-ed25519/blob/master/LICENSE>
+*/
+
+//! {n,n}-signing as a state machine for all the members of a `Group`, parties are indexed by their
+//! position in the group.
+//!
+//! Round 1 broadcasts the ephemeral public keys and round 2 the partial signatures, which are
+//! checked one by one before they are added up.
+
+use curv::elliptic::curves::{Ed25519, Point, Scalar};
+use curv::BigInt;
+use std::collections::BTreeMap;
+
+use protocols::multisig::{self, EphKey, MembershipKey, Signature};
+use protocols::state_machine::{Msg, RoundError, RoundMessage, Rounds, StateMachine};
+use Error::{self, InvalidPartialSig};
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub enum SigningMessage {
+    EphemeralKey(Point<Ed25519>),
+    PartialSignature(Scalar<Ed25519>),
+}
+
+impl RoundMessage for SigningMessage {
+    fn round(&self) -> u16 {
+        match self {
+            SigningMessage::EphemeralKey(_) => 1,
+            SigningMessage::PartialSignature(_) => 2,
+        }
+    }
+
+    fn is_broadcast(&self) -> bool {
+        true
+    }
+}
+
+pub struct Signing {
+    rounds: Rounds<SigningMessage>,
+    key: MembershipKey,
+    message: BigInt,
+    eph_key: EphKey,
+    eph_public_keys: BTreeMap<u16, Point<Ed25519>>,
+    // the aggregated public key, the aggregated ephemeral key and the challenge
+    joint: Option<(Point<Ed25519>, Point<Ed25519>, Scalar<Ed25519>)>,
+    partial_sig: Option<Scalar<Ed25519>>,
+    output: Option<(Point<Ed25519>, Signature, Scalar<Ed25519>)>,
+}
+
+impl Signing {
+    /// Starts signing `message` with the key of a group member, the group comes from
+    /// `Group::setup` so the public keys can be added up safely.
+    pub fn new(key: MembershipKey, message: &BigInt) -> Result<Signing, RoundError> {
+        let party_index = key.index();
+        let parties = (0..key.group().public_keys().len() as u16).collect();
+        let eph_key = EphKey::gen_commit(key.keys(), message);
+        let eph_public_key = eph_key.eph_key_pair.public_key.clone();
+        let mut signing = Signing {
+            rounds: Rounds::new(party_index, parties, 2)?,
+            key,
+            message: message.clone(),
+            eph_key,
+            eph_public_keys: BTreeMap::new(),
+            joint: None,
+            partial_sig: None,
+            output: None,
+        };
+        signing
+            .eph_public_keys
+            .insert(party_index, eph_public_key.clone());
+        signing
+            .rounds
+            .broadcast(SigningMessage::EphemeralKey(eph_public_key));
+        signing.proceed()?;
+        Ok(signing)
+    }
+
+    fn proceed(&mut self) -> Result<(), RoundError> {
+        while self.rounds.is_complete() {
+            let round = self.rounds.current_round();
+            let messages = self.rounds.advance();
+            if let Err(error) = self.finish_round(round, messages) {
+                return Err(self.rounds.fail(round, error));
+            }
+        }
+        Ok(())
+    }
+
+    fn finish_round(
+        &mut self,
+        round: u16,
+        messages: BTreeMap<u16, SigningMessage>,
+    ) -> Result<(), Error> {
+        if round == 1 {
+            for (sender, message) in messages {
+                if let SigningMessage::EphemeralKey(eph_public_key) = message {
+                    self.eph_public_keys.insert(sender, eph_public_key);
+                }
+            }
+            let (It, Xt, es) = EphKey::compute_joint_comm_e(
+                self.key.group().public_keys().to_vec(),
+                self.eph_public_keys.values().cloned().collect(),
+                &self.message,
+            )?;
+            let partial_sig = self.eph_key.partial_sign(self.key.keys(), es.clone());
+            self.joint = Some((It, Xt, es));
+            self.partial_sig = Some(partial_sig.clone());
+            self.rounds
+                .broadcast(SigningMessage::PartialSignature(partial_sig));
+        } else {
+            let (It, Xt, es) = self.joint.take().expect("set in round 1");
+            let mut partial_sigs = vec![self.partial_sig.take().expect("set in round 1")];
+            let mut bad_senders = Vec::new();
+            for (sender, message) in messages {
+                if let SigningMessage::PartialSignature(y) = message {
+                    let sig = Signature::set_signature(&self.eph_public_keys[&sender], &y);
+                    let public_key = &self.key.group().public_keys()[usize::from(sender)];
+                    if multisig::verify(public_key, &sig, &es).is_err() {
+                        bad_senders.push(sender);
+                    }
+                    partial_sigs.push(y);
+                }
+            }
+            if !bad_senders.is_empty() {
+                return Err(InvalidPartialSig(bad_senders));
+            }
+            let y = EphKey::add_signature_parts(partial_sigs)?;
+            let signature = Signature::set_signature(&Xt, &y);
+            self.output = Some((It, signature, es));
+        }
+        Ok(())
+    }
+}
+
+impl StateMachine for Signing {
+    type MessageBody = SigningMessage;
+    /// The aggregated public key, the signature and the challenge to verify it with.
+    type Output = (Point<Ed25519>, Signature, Scalar<Ed25519>);
+
+    fn handle_incoming(&mut self, msg: Msg<SigningMessage>) -> Result<(), RoundError> {
+        self.rounds.accept(msg)?;
+        self.proceed()
+    }
+
+    fn outgoing(&mut self) -> Vec<Msg<SigningMessage>> {
+        self.rounds.take_outgoing()
+    }
+
+    fn pick_output(&mut self) -> Result<Self::Output, RoundError> {
+        self.rounds.check_finished()?;
+        self.output.take().ok_or(RoundError::Finished)
+    }
+
+    fn current_round(&self) -> u16 {
+        self.rounds.current_round()
+    }
+
+    fn total_rounds(&self) -> u16 {
+        self.rounds.total_rounds()
+    }
+
+    fn party_index(&self) -> u16 {
+        self.rounds.party_index()
+    }
+
+    fn parties(&self) -> &[u16] {
+        self.rounds.parties()
+    }
+}
+
+mod test;
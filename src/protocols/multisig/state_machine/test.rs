@@ -0,0 +1,87 @@
+/*
+    multi-party-ed25519
+
+    Copyright 2018 by Kzen Networks
+
+    This file is part of multi-party-ed25519 library
+    (https://github.com/KZen-networks/multisig-schnorr)
+
+    multi-party-ed25519 is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-ed25519/blob/master/LICENSE>
+*/
+
+#[cfg(test)]
+mod tests {
+    use curv::elliptic::curves::Scalar;
+    use curv::BigInt;
+
+    use protocols::multisig::state_machine::{Signing, SigningMessage};
+    use protocols::multisig::{partial_sign, verify, Group, Keys, MembershipKey};
+    use protocols::simulation::Simulation;
+    use protocols::state_machine::{RoundError, StateMachine};
+    use Error;
+
+    fn signing_parties(n: usize, message: &BigInt) -> Vec<Signing> {
+        let keys: Vec<_> = (0..n).map(|_| Keys::create()).collect();
+        let ix_vec: Vec<_> = keys
+            .iter()
+            .map(|k| vec![k.I.public_key.clone(), k.X.public_key.clone()])
+            .collect();
+        let e = Keys::collect_and_compute_challenge(&ix_vec);
+        let proofs: Vec<_> = keys.iter().map(|k| partial_sign(k, e.clone())).collect();
+        let group = Group::setup(&ix_vec, &proofs).unwrap();
+        keys.into_iter()
+            .map(|k| {
+                let key = MembershipKey::new(k.I, group.clone()).unwrap();
+                Signing::new(key, message).unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_signing_state_machine() {
+        let message = BigInt::from(1234);
+        for n in 1..5 {
+            let outputs = Simulation::new(signing_parties(n, &message))
+                .run()
+                .into_iter()
+                .map(Result::unwrap)
+                .collect::<Vec<_>>();
+            for (It, signature, es) in &outputs {
+                assert_eq!(signature, &outputs[0].1);
+                assert!(verify(It, signature, es).is_ok());
+            }
+        }
+    }
+
+    #[test]
+    fn test_signing_state_machine_rejects_bad_partial_signature() {
+        let message = BigInt::from(1234);
+        let mut parties = signing_parties(2, &message);
+        let eph_keys: Vec<_> = parties.iter_mut().flat_map(|p| p.outgoing()).collect();
+        parties[0].handle_incoming(eph_keys[1].clone()).unwrap();
+        let partial_sig = parties[0].outgoing().pop().unwrap();
+
+        // party 0 is a round ahead, its partial signature waits for its ephemeral key
+        parties[1].handle_incoming(partial_sig).unwrap();
+        assert_eq!(parties[1].current_round(), 1);
+        parties[1].handle_incoming(eph_keys[0].clone()).unwrap();
+        assert!(parties[1].is_finished());
+        let (It, signature, es) = parties[1].pick_output().unwrap();
+        assert!(verify(&It, &signature, &es).is_ok());
+
+        let mut bad_partial_sig = parties[1].outgoing().pop().unwrap();
+        bad_partial_sig.body = SigningMessage::PartialSignature(Scalar::random());
+        assert_eq!(
+            parties[0].handle_incoming(bad_partial_sig),
+            Err(RoundError::Protocol {
+                round: 2,
+                error: Error::InvalidPartialSig(vec![1])
+            })
+        );
+    }
+}
@@ -20,10 +20,16 @@ mod tests {
     use curv::arithmetic::Converter;
     use curv::cryptographic_primitives::hashing::merkle_tree::MT256;
     use curv::cryptographic_primitives::hashing::DigestExt;
-    use curv::elliptic::curves::Scalar;
+    use curv::elliptic::curves::{Ed25519, Point, Scalar};
     use curv::BigInt;
-    use protocols::multisig::{partial_sign, verify, EphKey, Keys, Signature};
+    use protocols::multisig::{
+        partial_sign, verify, verify_subgroup, EphKey, Group, Keys, MembershipKey, Signature,
+        SubgroupSignature,
+    };
+    use protocols::tests::{assert_serde_roundtrip, verify_dalek};
+    use protocols::{Context, SignatureMode, VerificationPolicy};
     use sha2::{digest::Digest, Sha256};
+    use Error;
 
     #[test]
     fn two_party_key_gen() {
@@ -33,19 +39,19 @@ mod tests {
     }
 
     fn two_party_key_gen_internal() {
-        let message_vec = vec![79, 77, 69, 82];
+        let message_vec = [79, 77, 69, 82];
         let message_bn = BigInt::from_bytes(&message_vec[..]);
         let message = Sha256::new().chain_bigint(&message_bn).result_bigint();
 
         // party1 key gen:
-        let keys_1 = Keys::create();
+        let mut keys_1 = Keys::create();
 
-        keys_1.clone().I.update_key_pair(Scalar::zero());
+        keys_1.I.update_key_pair(Scalar::zero());
 
-        let broadcast1 = Keys::broadcast(keys_1.clone());
+        let broadcast1 = Keys::broadcast(&keys_1);
         // party2 key gen:
         let keys_2 = Keys::create();
-        let broadcast2 = Keys::broadcast(keys_2.clone());
+        let broadcast2 = Keys::broadcast(&keys_2);
         let ix_vec = vec![broadcast1, broadcast2];
         let e = Keys::collect_and_compute_challenge(&ix_vec);
 
@@ -77,15 +83,226 @@ mod tests {
         ];
         let pub_key_vec = vec![keys_1.I.public_key.clone(), keys_2.I.public_key.clone()];
 
-        let (It, Xt, es) = EphKey::compute_joint_comm_e(pub_key_vec, eph_pub_key_vec, &message);
+        let (It, Xt, es) =
+            EphKey::compute_joint_comm_e(pub_key_vec, eph_pub_key_vec, &message).unwrap();
 
         let y1 = party1_com.partial_sign(&keys_1.I, es.clone());
         let y2 = party2_com.partial_sign(&keys_2.I, es.clone());
-        let y = EphKey::add_signature_parts(vec![y1, y2]);
+        let y = EphKey::add_signature_parts(vec![y1, y2]).unwrap();
         let sig = Signature::set_signature(&Xt, &y);
         assert!(verify(&It, &sig, &es).is_ok());
 
         assert!(proof1.verify(&root).is_ok());
         assert!(proof2.verify(&root).is_ok());
     }
+
+    #[test]
+    fn test_ed25519_signature() {
+        let message = [79, 77, 69, 82];
+        let keys: Vec<_> = (0..3).map(|_| Keys::create()).collect();
+        let pub_key_vec: Vec<_> = keys.iter().map(|k| k.I.public_key.clone()).collect();
+        let modes = [
+            SignatureMode::Pure,
+            SignatureMode::Context(Context::new(b"multisig").unwrap()),
+        ];
+        for mode in &modes {
+            let eph_keys: Vec<_> = keys
+                .iter()
+                .map(|k| EphKey::gen_commit(&k.I, &BigInt::from_bytes(&message)))
+                .collect();
+            let eph_pub_key_vec: Vec<_> = eph_keys
+                .iter()
+                .map(|eph_key| eph_key.eph_key_pair.public_key.clone())
+                .collect();
+            let (It, Xt, k) = EphKey::compute_joint_comm_k(
+                pub_key_vec.clone(),
+                eph_pub_key_vec.clone(),
+                &message,
+                mode,
+            )
+            .unwrap();
+            let sig_vec: Vec<_> = keys
+                .iter()
+                .zip(eph_keys)
+                .map(|(keys, eph_key)| eph_key.partial_sign(&keys.I, k.clone()))
+                .collect();
+            // the partial signatures verify as before
+            for ((keys, eph_pub_key), y) in keys.iter().zip(&eph_pub_key_vec).zip(&sig_vec) {
+                let partial_sig = Signature::set_signature(eph_pub_key, y);
+                assert!(verify(&keys.I.public_key, &partial_sig, &k).is_ok());
+            }
+            let y = EphKey::add_signature_parts(sig_vec).unwrap();
+            let signature = Signature::set_signature(&Xt, &y).to_ed25519();
+            signature
+                .verify_with_mode(&message, &It, mode, VerificationPolicy::Strict)
+                .unwrap();
+            assert!(signature
+                .verify_with_mode(&[79, 77, 69, 83], &It, mode, VerificationPolicy::Strict)
+                .is_err());
+            if *mode == SignatureMode::Pure {
+                assert!(verify_dalek(&It, &signature, &message));
+            }
+        }
+    }
+
+    #[test]
+    fn test_joint_commitment_needs_one_ephemeral_key_per_key() {
+        let message = BigInt::from(1234);
+        let keys = Keys::create();
+        let eph_key = EphKey::gen_commit(&keys.I, &message);
+        let public_key = keys.I.public_key.clone();
+        let eph_public_key = eph_key.eph_key_pair.public_key.clone();
+        assert_eq!(
+            EphKey::compute_joint_comm_e(
+                vec![public_key.clone(), public_key],
+                vec![eph_public_key],
+                &message
+            )
+            .err(),
+            Some(Error::WrongLength {
+                expected: 2,
+                received: 1
+            })
+        );
+        assert_eq!(
+            EphKey::compute_joint_comm_e(vec![], vec![], &message).err(),
+            Some(Error::EmptyInput)
+        );
+        assert_eq!(EphKey::add_signature_parts(vec![]), Err(Error::EmptyInput));
+    }
+
+    // sets up a group of `n` members, with the proof of member `cheater` made for another challenge
+    fn group_setup(n: usize, cheater: Option<usize>) -> (Vec<Keys>, Result<Group, Error>) {
+        let keys: Vec<_> = (0..n).map(|_| Keys::create()).collect();
+        let ix_vec: Vec<_> = keys.iter().map(Keys::broadcast).collect();
+        let e = Keys::collect_and_compute_challenge(&ix_vec);
+        let proofs: Vec<_> = keys
+            .iter()
+            .enumerate()
+            .map(|(i, k)| match cheater {
+                Some(cheater) if cheater == i => partial_sign(k, Scalar::random()),
+                _ => partial_sign(k, e.clone()),
+            })
+            .collect();
+        let group = Group::setup(&ix_vec, &proofs);
+        (keys, group)
+    }
+
+    fn subgroup_sign(
+        keys: &[MembershipKey],
+        signers: &[u16],
+        message: &BigInt,
+    ) -> SubgroupSignature {
+        let eph_keys: Vec<_> = signers
+            .iter()
+            .map(|&i| EphKey::gen_commit(keys[usize::from(i)].keys(), message))
+            .collect();
+        let eph_pub_key_vec: Vec<Point<Ed25519>> = eph_keys
+            .iter()
+            .map(|eph_key| eph_key.eph_key_pair.public_key.clone())
+            .collect();
+        let sig_vec = signers
+            .iter()
+            .zip(eph_keys)
+            .map(|(&i, eph_key)| {
+                keys[usize::from(i)]
+                    .subgroup_sign(eph_key, signers, eph_pub_key_vec.clone(), message)
+                    .unwrap()
+            })
+            .collect();
+        SubgroupSignature::combine(signers.to_vec(), &eph_pub_key_vec, sig_vec).unwrap()
+    }
+
+    #[test]
+    fn test_subgroup_signature() {
+        let message = BigInt::from(1234);
+        let (keys, group) = group_setup(4, None);
+        let group = group.unwrap();
+        let keys: Vec<_> = keys
+            .into_iter()
+            .map(|k| MembershipKey::new(k.I, group.clone()).unwrap())
+            .collect();
+        assert_eq!(keys[2].index(), 2);
+
+        let signature = subgroup_sign(&keys, &[0, 2, 3], &message);
+        assert!(verify_subgroup(&group, &message, &signature).is_ok());
+        assert!(verify_subgroup(&group, &BigInt::from(1235), &signature).is_err());
+        assert_eq!(assert_serde_roundtrip(&signature), signature);
+
+        // the signature doesn't hold for another subgroup
+        for signers in [vec![0, 2], vec![0, 1, 2, 3], vec![1, 2, 3]] {
+            let mut other = signature.clone();
+            other.signers = signers;
+            assert_eq!(
+                verify_subgroup(&group, &message, &other),
+                Err(Error::InvalidSig)
+            );
+        }
+        for signers in [vec![0, 3, 2], vec![0, 2, 4], vec![]] {
+            let mut other = signature.clone();
+            other.signers = signers;
+            assert!(verify_subgroup(&group, &message, &other).is_err());
+        }
+
+        let signature = subgroup_sign(&keys, &[1], &message);
+        assert!(verify_subgroup(&group, &message, &signature).is_ok());
+
+        // only members can sign, and only for subgroups they are in
+        assert_eq!(
+            MembershipKey::new(Keys::create().I, group).err(),
+            Some(Error::InvalidKey)
+        );
+        let eph_key = EphKey::gen_commit(keys[0].keys(), &message);
+        let eph_pub_key = eph_key.eph_key_pair.public_key.clone();
+        assert_eq!(
+            keys[0].subgroup_sign(eph_key, &[1], vec![eph_pub_key], &message),
+            Err(Error::MissingParty(0))
+        );
+    }
+
+    #[test]
+    fn test_group_setup_identifies_bad_proofs() {
+        assert_eq!(group_setup(3, Some(1)).1, Err(Error::InvalidProof(vec![1])));
+        assert_eq!(group_setup(0, None).1, Err(Error::EmptyInput));
+
+        let keys = Keys::create();
+        let ix_vec = vec![Keys::broadcast(&keys)];
+        let e = Keys::collect_and_compute_challenge(&ix_vec);
+        let proof = partial_sign(&keys, e);
+        assert_eq!(
+            Group::setup(&ix_vec, &[proof.clone(), proof]),
+            Err(Error::WrongLength {
+                expected: 1,
+                received: 2
+            })
+        );
+    }
+
+    #[test]
+    fn test_serde_roundtrip() {
+        let message = BigInt::from(1234);
+        let keys = Keys::create();
+        let decoded = assert_serde_roundtrip(&keys);
+        assert_eq!(decoded.I.public_key, keys.I.public_key);
+        assert_eq!(decoded.X.public_key, keys.X.public_key);
+        let decoded = assert_serde_roundtrip(&keys.X);
+        assert_eq!(decoded.public_key, keys.X.public_key);
+
+        let eph_key = EphKey::gen_commit(&keys.I, &message);
+        let decoded = assert_serde_roundtrip(&eph_key);
+        assert_eq!(
+            decoded.eph_key_pair.public_key,
+            eph_key.eph_key_pair.public_key
+        );
+
+        let (_, Xt, es) = EphKey::compute_joint_comm_e(
+            vec![keys.I.public_key.clone()],
+            vec![eph_key.eph_key_pair.public_key.clone()],
+            &message,
+        )
+        .unwrap();
+        let y = eph_key.partial_sign(&keys.I, es);
+        let sig = Signature::set_signature(&Xt, &y);
+        assert_eq!(assert_serde_roundtrip(&sig), sig);
+    }
 }
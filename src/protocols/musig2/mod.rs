@@ -6,12 +6,16 @@
 //! This is an implementation of the Musig2 protocol as shown in https://eprint.iacr.org/2020/1261.pdf with the addition named Musig2* suggested in Section B of the paper.
 //! We implement the v = 2 (NUMBER_OF_NONCES) version, meaning there are 2 nonces generated by each party.
 
-use super::{ExpandedKeyPair, Signature};
+use super::{ExpandedKeyPair, Signature, SignatureMode, VerificationPolicy, REDACTED};
 use curv::arithmetic::Converter;
 use curv::elliptic::curves::{Ed25519, Point, Scalar};
 use curv::BigInt;
+use protocols::scheme::{GroupKey, KeyAggregation, MultiPartySignature};
 use protocols::Rng;
 use sha2::{digest::Digest, Sha512};
+use std::collections::BTreeMap;
+use std::fmt;
+use Error::{self, EmptyInput, InvalidKey, InvalidPartialSig, InvalidR, InvalidSig, UnknownParty};
 
 pub const NUMBER_OF_NONCES: usize = 2;
 
@@ -31,7 +35,7 @@ impl PublicKeyAgg {
         // of one them to 1 - saving a scalar multiplication operation - proof in Section B of the Musig2 paper linked above.
         // We therefore find the second public key (by lexicographic order) and later set its musig coefficient to 1.
         public_keys.sort_by(|left, right| left.to_bytes(false).cmp(&right.to_bytes(false)));
-        let mut second_public_key = &public_keys[0];
+        let mut second_public_key = public_keys.first()?;
         for public_key in &public_keys[1..] {
             if *public_key.to_bytes(false) > *public_keys[0].to_bytes(false) {
                 second_public_key = public_key;
@@ -44,7 +48,7 @@ impl PublicKeyAgg {
             |(mut agg_pub_key, mut musig_coeff), public_key| {
                 let mut musig_coefficient: Scalar<Ed25519> = Scalar::from(1);
                 if public_key != second_public_key {
-                    let mut hasher = Sha512::new().chain(&[1]).chain(&*public_key.to_bytes(true));
+                    let mut hasher = Sha512::new().chain([1]).chain(&*public_key.to_bytes(true));
                     for pk in &public_keys {
                         hasher.update(&*pk.to_bytes(true));
                     }
@@ -74,9 +78,97 @@ impl PublicKeyAgg {
         }
     }
 }
-#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+
+/// Secret nonces for a single signature, bound to the key, the signature mode and (optionally)
+/// the message they were generated for.
+///
+/// `partial_sign` consumes them, and they can't be cloned or serialized, except through
+/// `dangerous_clone` and `dangerous_export`. Signing two different messages with the same nonces
+/// leaks the private key.
 pub struct PrivatePartialNonces {
-    pub r: [Scalar<Ed25519>; NUMBER_OF_NONCES],
+    r: [Scalar<Ed25519>; NUMBER_OF_NONCES],
+    R: [Point<Ed25519>; NUMBER_OF_NONCES],
+    public_key: Point<Ed25519>,
+    message: Option<Vec<u8>>,
+    mode: SignatureMode,
+}
+
+impl PrivatePartialNonces {
+    pub fn public_nonces(&self) -> PublicPartialNonces {
+        PublicPartialNonces { R: self.R.clone() }
+    }
+
+    /// Copies the secret nonces, the caller must make sure only one of the copies is ever used.
+    pub fn dangerous_clone(&self) -> PrivatePartialNonces {
+        PrivatePartialNonces {
+            r: self.r.clone(),
+            R: self.R.clone(),
+            public_key: self.public_key.clone(),
+            message: self.message.clone(),
+            mode: self.mode.clone(),
+        }
+    }
+
+    /// Converts the nonces into a serializable form, for applications that have to persist them
+    /// between rounds. The caller must make sure they are restored at most once.
+    pub fn dangerous_export(self) -> ExportedPartialNonces {
+        ExportedPartialNonces {
+            r: self.r,
+            R: self.R,
+            public_key: self.public_key,
+            message: self.message,
+            mode: self.mode,
+        }
+    }
+
+    fn check_binding(
+        &self,
+        keys: &ExpandedKeyPair,
+        message: &[u8],
+        mode: &SignatureMode,
+    ) -> Result<(), Error> {
+        if self.public_key != keys.public_key || self.mode != *mode {
+            return Err(Error::InvalidNonce);
+        }
+        match &self.message {
+            Some(bound_message) if bound_message.as_slice() != message => Err(Error::InvalidNonce),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl fmt::Debug for PrivatePartialNonces {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("PrivatePartialNonces")
+            .field("r", &REDACTED)
+            .field("R", &self.R)
+            .field("public_key", &self.public_key)
+            .field("message", &self.message)
+            .field("mode", &self.mode)
+            .finish()
+    }
+}
+
+/// Serializable form of `PrivatePartialNonces`, see `PrivatePartialNonces::dangerous_export`.
+#[derive(Serialize, Deserialize)]
+pub struct ExportedPartialNonces {
+    r: [Scalar<Ed25519>; NUMBER_OF_NONCES],
+    R: [Point<Ed25519>; NUMBER_OF_NONCES],
+    public_key: Point<Ed25519>,
+    message: Option<Vec<u8>>,
+    mode: SignatureMode,
+}
+
+impl ExportedPartialNonces {
+    pub fn dangerous_import(self) -> PrivatePartialNonces {
+        PrivatePartialNonces {
+            r: self.r,
+            R: self.R,
+            public_key: self.public_key,
+            message: self.message,
+            mode: self.mode,
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
@@ -93,22 +185,24 @@ pub struct PartialSignature {
 pub fn generate_partial_nonces(
     keys: &ExpandedKeyPair,
     message: Option<&[u8]>,
+    mode: &SignatureMode,
 ) -> (PrivatePartialNonces, PublicPartialNonces) {
     let mut rng = rand::thread_rng();
-    generate_partial_nonces_internal(keys, message, &mut rng)
+    generate_partial_nonces_internal(keys, message, mode, &mut rng)
 }
 
 fn generate_partial_nonces_internal(
     keys: &ExpandedKeyPair,
     message: Option<&[u8]>,
+    mode: &SignatureMode,
     rng: &mut impl Rng,
 ) -> (PrivatePartialNonces, PublicPartialNonces) {
     // here we deviate from the spec, by introducing  non-deterministic element (random number)
     // to the nonce, this is important for MPC implementations
     let r: [Scalar<Ed25519>; NUMBER_OF_NONCES] = [(); NUMBER_OF_NONCES].map(|_| {
         let mut hash_result = Sha512::new()
-            .chain(&[2])
-            .chain(&*keys.expanded_private_key.prefix.to_bytes())
+            .chain([2])
+            .chain(keys.expanded_private_key.prefix)
             .chain(message.unwrap_or(&[]))
             .chain(rng.gen::<[u8; 32]>())
             .finalize();
@@ -118,42 +212,35 @@ fn generate_partial_nonces_internal(
         Scalar::from_bigint(&BigInt::from_bytes(&hash_result))
     });
     let R: [Point<Ed25519>; NUMBER_OF_NONCES] = r.clone().map(|scalar| Point::generator() * scalar);
-    (PrivatePartialNonces { r }, PublicPartialNonces { R })
+    (
+        PrivatePartialNonces {
+            r,
+            R: R.clone(),
+            public_key: keys.public_key.clone(),
+            message: message.map(|message| message.to_vec()),
+            mode: mode.clone(),
+        },
+        PublicPartialNonces { R },
+    )
 }
 
+/// Computes this party's partial signature, consuming its nonces.
+///
+/// Fails with `Error::InvalidNonce` if the nonces were generated for a different key, mode or message.
 pub fn partial_sign(
     nonces_from_other_parties: &[[Point<Ed25519>; NUMBER_OF_NONCES]],
     my_private_partial_nonces: PrivatePartialNonces,
-    my_public_partial_nonces: PublicPartialNonces,
     agg_public_key: &PublicKeyAgg,
     my_keypair: &ExpandedKeyPair,
     message: &[u8],
-) -> PartialSignature {
-    // Sum up the partial nonces from all parties index-wise, meaning,  R[i]
-    // is the sum of partial_nonces[i] from all parties
-    let R: [Point<Ed25519>; NUMBER_OF_NONCES] = nonces_from_other_parties.iter().fold(
-        my_public_partial_nonces.R,
-        |mut sum_partial_nonces, partial_nonce_array| {
-            for (accum_nonce, nonce) in sum_partial_nonces.iter_mut().zip(partial_nonce_array) {
-                *accum_nonce = &*accum_nonce + nonce;
-            }
-            sum_partial_nonces
-        },
+    mode: &SignatureMode,
+) -> Result<PartialSignature, Error> {
+    my_private_partial_nonces.check_binding(my_keypair, message, mode)?;
+    let R = sum_partial_nonces(
+        nonces_from_other_parties,
+        my_private_partial_nonces.R.clone(),
     );
-
-    // Compute b as hash of nonces
-    let mut hasher = Sha512::new()
-        .chain(&[3])
-        .chain(&*agg_public_key.agg_public_key.to_bytes(false));
-    for nonce in &R {
-        hasher.update(&*nonce.to_bytes(false));
-    }
-    hasher.update(message);
-    let mut hash_result = hasher.finalize();
-    // Reverse because BigInt uses big-endian
-    hash_result.reverse();
-    // Reduce modulu the group order
-    let b: Scalar<Ed25519> = Scalar::from_bigint(&BigInt::from_bytes(&hash_result));
+    let b = compute_nonce_coefficient(&R, &agg_public_key.agg_public_key, message, mode);
     // Compute effective nonce
     // The idea is to compute R and r s.t. R = R_0 + b•R_1 + ... + b^(v-1)•R_v and r = r_0 + b•r_1 + ... + b^(v-1)•r_v
     let (effective_R, effective_r, _) = R[1..]
@@ -174,17 +261,109 @@ pub fn partial_sign(
             },
         );
     // Compute Fiat-Shamir challenge of signature
-    let sig_challenge = Signature::k(&effective_R, &agg_public_key.agg_public_key, message);
+    let sig_challenge = Signature::k(&effective_R, &agg_public_key.agg_public_key, message, mode);
 
     // Computes the partial signature
     let partial_signature: Scalar<Ed25519> = sig_challenge
         * &agg_public_key.musig_coefficient
         * &my_keypair.expanded_private_key.private_key
         + effective_r;
-    PartialSignature {
+    Ok(PartialSignature {
         R: effective_R,
         my_partial_s: partial_signature,
+    })
+}
+
+/// Checks a single party's partial signature, so a bad share can be attributed to its signer
+/// before aggregation.
+///
+/// `signer_key_agg` is the output of `PublicKeyAgg::key_aggregation_n` for the signer's public key,
+/// and `nonces_from_other_parties` are the public nonces of every party except the signer.
+#[allow(clippy::too_many_arguments)]
+pub fn verify_partial_signature(
+    partial_sig: &PartialSignature,
+    nonces_from_other_parties: &[[Point<Ed25519>; NUMBER_OF_NONCES]],
+    signer_public_partial_nonces: &PublicPartialNonces,
+    signer_key_agg: &PublicKeyAgg,
+    signer_public_key: &Point<Ed25519>,
+    message: &[u8],
+    mode: &SignatureMode,
+    policy: VerificationPolicy,
+) -> Result<(), Error> {
+    let R = sum_partial_nonces(
+        nonces_from_other_parties,
+        signer_public_partial_nonces.R.clone(),
+    );
+    let b = compute_nonce_coefficient(&R, &signer_key_agg.agg_public_key, message, mode);
+    // Compute the effective nonce, both aggregated and for the signer alone
+    let (effective_R, signer_effective_R, _) = R[1..]
+        .iter()
+        .zip(signer_public_partial_nonces.R[1..].iter())
+        .fold(
+            (
+                R[0].clone(),
+                signer_public_partial_nonces.R[0].clone(),
+                b.clone(),
+            ),
+            |(eff_R, signer_eff_R, b_exp), (nonce_R_i, signer_nonce_R_i)| {
+                (
+                    eff_R + &b_exp * nonce_R_i,
+                    signer_eff_R + &b_exp * signer_nonce_R_i,
+                    b_exp * &b,
+                )
+            },
+        );
+    if effective_R != partial_sig.R {
+        return Err(InvalidSig);
     }
+    let sig_challenge = Signature::k(&effective_R, &signer_key_agg.agg_public_key, message, mode);
+
+    // s_i•G == R_i + c•a_i•X_i
+    policy.check(
+        &partial_sig.my_partial_s,
+        &signer_effective_R,
+        &(sig_challenge * &signer_key_agg.musig_coefficient),
+        signer_public_key,
+    )
+}
+
+// Sum up the partial nonces from all parties index-wise, meaning,  R[i]
+// is the sum of partial_nonces[i] from all parties
+fn sum_partial_nonces(
+    nonces_from_other_parties: &[[Point<Ed25519>; NUMBER_OF_NONCES]],
+    my_partial_nonces: [Point<Ed25519>; NUMBER_OF_NONCES],
+) -> [Point<Ed25519>; NUMBER_OF_NONCES] {
+    nonces_from_other_parties.iter().fold(
+        my_partial_nonces,
+        |mut sum_partial_nonces, partial_nonce_array| {
+            for (accum_nonce, nonce) in sum_partial_nonces.iter_mut().zip(partial_nonce_array) {
+                *accum_nonce = &*accum_nonce + nonce;
+            }
+            sum_partial_nonces
+        },
+    )
+}
+
+// Compute b as hash of nonces, it also binds the signature mode since the challenge depends on it
+fn compute_nonce_coefficient(
+    R: &[Point<Ed25519>; NUMBER_OF_NONCES],
+    agg_public_key: &Point<Ed25519>,
+    message: &[u8],
+    mode: &SignatureMode,
+) -> Scalar<Ed25519> {
+    let mut hasher = Sha512::new()
+        .chain([3])
+        .chain(&*agg_public_key.to_bytes(false));
+    for nonce in R {
+        hasher.update(&*nonce.to_bytes(false));
+    }
+    hasher.update(mode.dom2());
+    hasher.update(message);
+    let mut hash_result = hasher.finalize();
+    // Reverse because BigInt uses big-endian
+    hash_result.reverse();
+    // Reduce modulu the group order
+    Scalar::from_bigint(&BigInt::from_bytes(&hash_result))
 }
 
 pub fn aggregate_partial_signatures(
@@ -202,4 +381,145 @@ pub fn aggregate_partial_signatures(
     }
 }
 
+/// MuSig2 as a `MultiPartySignature`.
+pub struct MuSig2;
+
+// the public nonces of every signer except `signer`
+fn nonces_except(
+    nonces: &BTreeMap<u16, PublicPartialNonces>,
+    signer: u16,
+) -> Vec<[Point<Ed25519>; NUMBER_OF_NONCES]> {
+    nonces
+        .iter()
+        .filter(|(index, _)| **index != signer)
+        .map(|(_, nonces)| nonces.R.clone())
+        .collect()
+}
+
+impl KeyAggregation for MuSig2 {
+    type KeyShare = GroupKey;
+
+    fn party_index(key: &GroupKey) -> u16 {
+        key.party_index()
+    }
+
+    fn public_key(key: &GroupKey) -> Point<Ed25519> {
+        PublicKeyAgg::key_aggregation_n(key.public_keys().to_vec(), &key.keys().public_key)
+            .expect("the key is one of the public keys")
+            .agg_public_key
+    }
+
+    fn check_signers(key: &GroupKey, signers: &[u16]) -> Result<(), Error> {
+        key.check_signers(signers)
+    }
+}
+
+impl MultiPartySignature for MuSig2 {
+    const COMMITS_TO_NONCES: bool = false;
+
+    type NonceCommitment = ();
+    type SecretNonces = PrivatePartialNonces;
+    type PublicNonces = PublicPartialNonces;
+    type PartialSignature = PartialSignature;
+    type Signature = Signature;
+
+    fn generate_nonces(
+        key: &GroupKey,
+        message: &[u8],
+        mode: &SignatureMode,
+    ) -> (PrivatePartialNonces, (), PublicPartialNonces) {
+        let (private_nonces, public_nonces) =
+            generate_partial_nonces(key.keys(), Some(message), mode);
+        (private_nonces, (), public_nonces)
+    }
+
+    fn check_nonces(_commitment: &(), _nonces: &PublicPartialNonces) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn partial_sign(
+        key: &GroupKey,
+        secret_nonces: PrivatePartialNonces,
+        nonces: &BTreeMap<u16, PublicPartialNonces>,
+        message: &[u8],
+        mode: &SignatureMode,
+    ) -> Result<PartialSignature, Error> {
+        let key_agg =
+            PublicKeyAgg::key_aggregation_n(key.public_keys().to_vec(), &key.keys().public_key)
+                .ok_or(InvalidKey)?;
+        partial_sign(
+            &nonces_except(nonces, key.party_index()),
+            secret_nonces,
+            &key_agg,
+            key.keys(),
+            message,
+            mode,
+        )
+    }
+
+    fn verify_partial(
+        key: &GroupKey,
+        nonces: &BTreeMap<u16, PublicPartialNonces>,
+        message: &[u8],
+        mode: &SignatureMode,
+        signer: u16,
+        partial_sig: &PartialSignature,
+    ) -> Result<(), Error> {
+        let signer_public_key = key
+            .public_keys()
+            .get(usize::from(signer))
+            .ok_or(UnknownParty(signer))?;
+        let signer_key_agg =
+            PublicKeyAgg::key_aggregation_n(key.public_keys().to_vec(), signer_public_key)
+                .ok_or(InvalidKey)?;
+        verify_partial_signature(
+            partial_sig,
+            &nonces_except(nonces, signer),
+            nonces
+                .get(&signer)
+                .ok_or_else(|| InvalidPartialSig(vec![signer]))?,
+            &signer_key_agg,
+            signer_public_key,
+            message,
+            mode,
+            VerificationPolicy::default(),
+        )
+        .map_err(|_| InvalidPartialSig(vec![signer]))
+    }
+
+    fn combine(
+        _key: &GroupKey,
+        _nonces: &BTreeMap<u16, PublicPartialNonces>,
+        _message: &[u8],
+        _mode: &SignatureMode,
+        partial_sigs: &BTreeMap<u16, PartialSignature>,
+    ) -> Result<Signature, Error> {
+        let first = partial_sigs.values().next().ok_or(EmptyInput)?;
+        let bad_signers: Vec<u16> = partial_sigs
+            .iter()
+            .filter(|(_, sig)| sig.R != first.R)
+            .map(|(&signer, _)| signer)
+            .collect();
+        if !bad_signers.is_empty() {
+            return Err(InvalidR(bad_signers));
+        }
+        let others: Vec<_> = partial_sigs
+            .values()
+            .skip(1)
+            .map(|sig| sig.my_partial_s.clone())
+            .collect();
+        Ok(aggregate_partial_signatures(first, &others))
+    }
+
+    fn verify(
+        public_key: &Point<Ed25519>,
+        message: &[u8],
+        mode: &SignatureMode,
+        signature: &Signature,
+    ) -> Result<(), Error> {
+        signature.verify_with_mode(message, public_key, mode, VerificationPolicy::default())
+    }
+}
+
+pub mod state_machine;
 mod test;
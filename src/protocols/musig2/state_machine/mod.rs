@@ -0,0 +1,222 @@
+#![allow(non_snake_case)]
+/*
+    multi-party-ed25519
+
+    Copyright 2018 by Kzen Networks
+
+    This file is part of multi-party-ed25519 library
+    (https://github.com/KZen-networks/multisig-schnorr)
+
+    multi-party-ed25519 is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-ed25519/blob/master/LICENSE>
+*/
+
+//! MuSig2 signing as a state machine, parties are indexed by their position in the list of
+//! public keys.
+//!
+//! Round 1 broadcasts the public nonces and round 2 the partial signatures, which are checked one
+//! by one before they are aggregated.
+
+use curv::elliptic::curves::{Ed25519, Point};
+use std::collections::BTreeMap;
+
+use protocols::musig2::{self, PartialSignature, PrivatePartialNonces, PublicKeyAgg};
+use protocols::musig2::{PublicPartialNonces, NUMBER_OF_NONCES};
+use protocols::state_machine::{Msg, RoundError, RoundMessage, Rounds, StateMachine};
+use protocols::{ExpandedKeyPair, Signature, SignatureMode, VerificationPolicy};
+use Error::{self, InvalidKey, InvalidPartialSig};
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub enum SigningMessage {
+    Nonces(PublicPartialNonces),
+    PartialSignature(PartialSignature),
+}
+
+impl RoundMessage for SigningMessage {
+    fn round(&self) -> u16 {
+        match self {
+            SigningMessage::Nonces(_) => 1,
+            SigningMessage::PartialSignature(_) => 2,
+        }
+    }
+
+    fn is_broadcast(&self) -> bool {
+        true
+    }
+}
+
+pub struct Signing {
+    rounds: Rounds<SigningMessage>,
+    keys: ExpandedKeyPair,
+    public_keys: Vec<Point<Ed25519>>,
+    key_agg: PublicKeyAgg,
+    message: Vec<u8>,
+    mode: SignatureMode,
+    private_nonces: Option<PrivatePartialNonces>,
+    nonces: BTreeMap<u16, PublicPartialNonces>,
+    partial_sig: Option<PartialSignature>,
+    output: Option<Signature>,
+}
+
+impl Signing {
+    /// Starts signing `message` as the party at `party_index` in `public_keys`.
+    pub fn new(
+        party_index: u16,
+        keys: ExpandedKeyPair,
+        public_keys: Vec<Point<Ed25519>>,
+        message: &[u8],
+        mode: SignatureMode,
+    ) -> Result<Signing, RoundError> {
+        if public_keys.get(usize::from(party_index)) != Some(&keys.public_key) {
+            return Err(RoundError::Setup(InvalidKey));
+        }
+        let parties = (0..public_keys.len() as u16).collect();
+        let key_agg = PublicKeyAgg::key_aggregation_n(public_keys.clone(), &keys.public_key)
+            .ok_or(RoundError::Setup(InvalidKey))?;
+        let (private_nonces, public_nonces) =
+            musig2::generate_partial_nonces(&keys, Some(message), &mode);
+        let mut signing = Signing {
+            rounds: Rounds::new(party_index, parties, 2)?,
+            keys,
+            public_keys,
+            key_agg,
+            message: message.to_vec(),
+            mode,
+            private_nonces: Some(private_nonces),
+            nonces: BTreeMap::new(),
+            partial_sig: None,
+            output: None,
+        };
+        signing.nonces.insert(party_index, public_nonces.clone());
+        signing
+            .rounds
+            .broadcast(SigningMessage::Nonces(public_nonces));
+        signing.proceed()?;
+        Ok(signing)
+    }
+
+    fn proceed(&mut self) -> Result<(), RoundError> {
+        while self.rounds.is_complete() {
+            let round = self.rounds.current_round();
+            let messages = self.rounds.advance();
+            if let Err(error) = self.finish_round(round, messages) {
+                return Err(self.rounds.fail(round, error));
+            }
+        }
+        Ok(())
+    }
+
+    // the public nonces of every party except `party`
+    fn nonces_except(&self, party: u16) -> Vec<[Point<Ed25519>; NUMBER_OF_NONCES]> {
+        self.nonces
+            .iter()
+            .filter(|(index, _)| **index != party)
+            .map(|(_, nonces)| nonces.R.clone())
+            .collect()
+    }
+
+    fn finish_round(
+        &mut self,
+        round: u16,
+        messages: BTreeMap<u16, SigningMessage>,
+    ) -> Result<(), Error> {
+        let me = self.rounds.party_index();
+        if round == 1 {
+            for (sender, message) in messages {
+                if let SigningMessage::Nonces(nonces) = message {
+                    self.nonces.insert(sender, nonces);
+                }
+            }
+            let private_nonces = self.private_nonces.take().expect("used once, in round 1");
+            let partial_sig = musig2::partial_sign(
+                &self.nonces_except(me),
+                private_nonces,
+                &self.key_agg,
+                &self.keys,
+                &self.message,
+                &self.mode,
+            )?;
+            self.partial_sig = Some(partial_sig.clone());
+            self.rounds
+                .broadcast(SigningMessage::PartialSignature(partial_sig));
+        } else {
+            let mut partial_sigs = Vec::new();
+            let mut bad_senders = Vec::new();
+            for (sender, message) in messages {
+                if let SigningMessage::PartialSignature(partial_sig) = message {
+                    let signer_public_key = &self.public_keys[usize::from(sender)];
+                    let signer_key_agg = PublicKeyAgg::key_aggregation_n(
+                        self.public_keys.clone(),
+                        signer_public_key,
+                    )
+                    .ok_or(InvalidKey)?;
+                    if musig2::verify_partial_signature(
+                        &partial_sig,
+                        &self.nonces_except(sender),
+                        &self.nonces[&sender],
+                        &signer_key_agg,
+                        signer_public_key,
+                        &self.message,
+                        &self.mode,
+                        VerificationPolicy::default(),
+                    )
+                    .is_err()
+                    {
+                        bad_senders.push(sender);
+                    }
+                    partial_sigs.push(partial_sig.my_partial_s);
+                }
+            }
+            if !bad_senders.is_empty() {
+                return Err(InvalidPartialSig(bad_senders));
+            }
+            let my_partial_sig = self.partial_sig.take().expect("set in round 1");
+            self.output = Some(musig2::aggregate_partial_signatures(
+                &my_partial_sig,
+                &partial_sigs,
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl StateMachine for Signing {
+    type MessageBody = SigningMessage;
+    type Output = Signature;
+
+    fn handle_incoming(&mut self, msg: Msg<SigningMessage>) -> Result<(), RoundError> {
+        self.rounds.accept(msg)?;
+        self.proceed()
+    }
+
+    fn outgoing(&mut self) -> Vec<Msg<SigningMessage>> {
+        self.rounds.take_outgoing()
+    }
+
+    fn pick_output(&mut self) -> Result<Signature, RoundError> {
+        self.rounds.check_finished()?;
+        self.output.take().ok_or(RoundError::Finished)
+    }
+
+    fn current_round(&self) -> u16 {
+        self.rounds.current_round()
+    }
+
+    fn total_rounds(&self) -> u16 {
+        self.rounds.total_rounds()
+    }
+
+    fn party_index(&self) -> u16 {
+        self.rounds.party_index()
+    }
+
+    fn parties(&self) -> &[u16] {
+        self.rounds.parties()
+    }
+}
+
+mod test;
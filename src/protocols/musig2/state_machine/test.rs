@@ -0,0 +1,102 @@
+/*
+    multi-party-ed25519
+
+    Copyright 2018 by Kzen Networks
+
+    This file is part of multi-party-ed25519 library
+    (https://github.com/KZen-networks/multisig-schnorr)
+
+    multi-party-ed25519 is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-ed25519/blob/master/LICENSE>
+*/
+
+#[cfg(test)]
+mod tests {
+    use curv::elliptic::curves::{Ed25519, Point, Scalar};
+
+    use protocols::musig2::state_machine::{Signing, SigningMessage};
+    use protocols::musig2::{self, PublicKeyAgg};
+    use protocols::simulation::Simulation;
+    use protocols::state_machine::{RoundError, StateMachine};
+    use protocols::tests::verify_dalek;
+    use protocols::{ExpandedKeyPair, SignatureMode};
+    use Error;
+
+    fn signing_parties(n: usize, message: &[u8]) -> (Vec<Signing>, Point<Ed25519>) {
+        let keypairs: Vec<_> = (0..n).map(|_| ExpandedKeyPair::create()).collect();
+        let public_keys: Vec<_> = keypairs.iter().map(|k| k.public_key.clone()).collect();
+        let agg_public_key = PublicKeyAgg::key_aggregation_n(public_keys.clone(), &public_keys[0])
+            .unwrap()
+            .agg_public_key;
+        let parties = keypairs
+            .into_iter()
+            .enumerate()
+            .map(|(i, keypair)| {
+                Signing::new(
+                    i as u16,
+                    keypair,
+                    public_keys.clone(),
+                    message,
+                    SignatureMode::Pure,
+                )
+                .unwrap()
+            })
+            .collect();
+        (parties, agg_public_key)
+    }
+
+    #[test]
+    fn test_signing_state_machine() {
+        let message = [79, 77, 69, 82];
+        for n in 1..5 {
+            let (parties, agg_public_key) = signing_parties(n, &message);
+            for signature in Simulation::new(parties).run() {
+                let signature = signature.unwrap();
+                assert!(verify_dalek(&agg_public_key, &signature, &message));
+            }
+        }
+    }
+
+    #[test]
+    fn test_signing_state_machine_rejects_bad_partial_signature() {
+        let message = [79, 77, 69, 82];
+        let (mut parties, _) = signing_parties(3, &message);
+        let nonces: Vec<_> = parties.iter_mut().flat_map(|p| p.outgoing()).collect();
+        for msg in nonces {
+            for party in parties.iter_mut().filter(|p| p.party_index() != msg.sender) {
+                party.handle_incoming(msg.clone()).unwrap();
+            }
+        }
+        let mut partial_sigs: Vec<_> = parties.iter_mut().flat_map(|p| p.outgoing()).collect();
+        assert_eq!(partial_sigs.len(), 3);
+
+        // a round 1 message after round 1 is over
+        let mut late_nonces = partial_sigs[1].clone();
+        let (_, public_nonces) =
+            musig2::generate_partial_nonces(&ExpandedKeyPair::create(), None, &SignatureMode::Pure);
+        late_nonces.body = SigningMessage::Nonces(public_nonces);
+        assert_eq!(
+            parties[0].handle_incoming(late_nonces),
+            Err(RoundError::Duplicate {
+                sender: 1,
+                round: 1
+            })
+        );
+
+        if let SigningMessage::PartialSignature(partial_sig) = &mut partial_sigs[2].body {
+            partial_sig.my_partial_s = &partial_sig.my_partial_s + Scalar::from(1);
+        }
+        parties[0].handle_incoming(partial_sigs[1].clone()).unwrap();
+        assert_eq!(
+            parties[0].handle_incoming(partial_sigs[2].clone()),
+            Err(RoundError::Protocol {
+                round: 2,
+                error: Error::InvalidPartialSig(vec![2])
+            })
+        );
+    }
+}
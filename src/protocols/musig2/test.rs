@@ -17,16 +17,18 @@
 #[cfg(test)]
 mod tests {
     use curv::arithmetic::Converter;
+    use curv::elliptic::curves::Scalar;
     use hex::decode;
     use rand::{Rng, RngCore};
     use std::convert::TryInto;
 
-    use protocols::tests::deterministic_fast_rand;
+    use protocols::tests::{assert_serde_roundtrip, deterministic_fast_rand};
     use protocols::{
         musig2::{self, PublicKeyAgg},
-        tests::verify_dalek,
-        ExpandedKeyPair,
+        tests::{verify_dalek, verify_dalek_prehashed},
+        Context, ExpandedKeyPair, SignatureMode, VerificationPolicy,
     };
+    use Error;
 
     #[test]
     fn test_ed25519_generate_keypair_from_seed() {
@@ -91,6 +93,7 @@ mod tests {
                         musig2::generate_partial_nonces_internal(
                             keypair,
                             Option::Some(msg),
+                            &SignatureMode::Pure,
                             &mut rng,
                         )
                     })
@@ -100,10 +103,11 @@ mod tests {
                 // Compute partial signatures
                 let partial_sigs: Vec<_> = keypairs
                     .iter()
+                    .zip(private_partial_nonces)
                     .enumerate()
-                    .map(|(index, keypair)| {
+                    .map(|(index, (keypair, private_partial_nonces))| {
                         let mut pub_partial_nonces_without_signer = public_partial_nonces.clone();
-                        let my_pub_partial_nonces = pub_partial_nonces_without_signer.remove(index);
+                        pub_partial_nonces_without_signer.remove(index);
                         let partial_nonce_slice = pub_partial_nonces_without_signer
                             .iter()
                             .map(|partial_nonce| partial_nonce.R.clone())
@@ -111,18 +115,18 @@ mod tests {
 
                         musig2::partial_sign(
                             partial_nonce_slice.as_slice(),
-                            private_partial_nonces[index].clone(),
-                            my_pub_partial_nonces,
+                            private_partial_nonces,
                             &agg_pub_keys[index],
                             keypair,
                             msg,
+                            &SignatureMode::Pure,
                         )
+                        .unwrap()
                     })
                     .collect();
 
                 // Compute signature
                 let signatures: Vec<_> = (0..signers)
-                    .into_iter()
                     .map(|index| {
                         let mut partial_sigs_without_signer = partial_sigs.clone();
                         let my_partial_sig = partial_sigs_without_signer.remove(index);
@@ -172,10 +176,18 @@ mod tests {
         let party0_key = ExpandedKeyPair::create();
         let party1_key = ExpandedKeyPair::create();
 
-        let (p0_private_nonces, p0_public_nonces) =
-            musig2::generate_partial_nonces_internal(&party0_key, Option::Some(&message), rng);
-        let (p1_private_nonces, p1_public_nonces) =
-            musig2::generate_partial_nonces_internal(&party1_key, Option::Some(&message), rng);
+        let (p0_private_nonces, p0_public_nonces) = musig2::generate_partial_nonces_internal(
+            &party0_key,
+            Option::Some(&message),
+            &SignatureMode::Pure,
+            rng,
+        );
+        let (p1_private_nonces, p1_public_nonces) = musig2::generate_partial_nonces_internal(
+            &party1_key,
+            Option::Some(&message),
+            &SignatureMode::Pure,
+            rng,
+        );
 
         // compute aggregated public key:
         let pks = vec![party0_key.public_key.clone(), party1_key.public_key.clone()];
@@ -191,24 +203,52 @@ mod tests {
         assert_eq!(party0_key_agg.agg_public_key, party1_key_agg.agg_public_key);
         // Compute partial signatures
         let s0 = musig2::partial_sign(
-            &[p1_public_nonces.R.clone()],
+            std::slice::from_ref(&p1_public_nonces.R),
             p0_private_nonces,
-            p0_public_nonces.clone(),
             &party0_key_agg,
             &party0_key,
             &message,
-        );
+            &SignatureMode::Pure,
+        )
+        .unwrap();
         let s1 = musig2::partial_sign(
-            &[p0_public_nonces.R],
+            std::slice::from_ref(&p0_public_nonces.R),
             p1_private_nonces,
-            p1_public_nonces,
             &party1_key_agg,
             &party1_key,
             &message,
-        );
+            &SignatureMode::Pure,
+        )
+        .unwrap();
+
+        // verify partial signatures:
+        assert!(musig2::verify_partial_signature(
+            &s0,
+            std::slice::from_ref(&p1_public_nonces.R),
+            &p0_public_nonces,
+            &party0_key_agg,
+            &party0_key.public_key,
+            &message,
+            &SignatureMode::Pure,
+            VerificationPolicy::Strict,
+        )
+        .is_ok());
+        assert!(musig2::verify_partial_signature(
+            &s1,
+            std::slice::from_ref(&p0_public_nonces.R),
+            &p1_public_nonces,
+            &party1_key_agg,
+            &party1_key.public_key,
+            &message,
+            &SignatureMode::Pure,
+            VerificationPolicy::Strict,
+        )
+        .is_ok());
 
-        let signature0 = musig2::aggregate_partial_signatures(&s0, &[s1.my_partial_s.clone()]);
-        let signature1 = musig2::aggregate_partial_signatures(&s1, &[s0.my_partial_s.clone()]);
+        let signature0 =
+            musig2::aggregate_partial_signatures(&s0, std::slice::from_ref(&s1.my_partial_s));
+        let signature1 =
+            musig2::aggregate_partial_signatures(&s1, std::slice::from_ref(&s0.my_partial_s));
         assert!(s0.R == s1.R, "Different partial nonce aggregation!");
         assert!(signature0.s == signature1.s);
         // debugging asserts
@@ -226,4 +266,265 @@ mod tests {
             "Dalek signature verification failed!"
         );
     }
+
+    #[test]
+    fn test_verify_partial_signature_identifies_bad_share() {
+        let mut rng =
+            deterministic_fast_rand("test_verify_partial_signature_identifies_bad_share", None);
+        let message: [u8; 4] = [79, 77, 69, 82];
+        let keypairs: Vec<_> = (0..3).map(|_| ExpandedKeyPair::create()).collect();
+        let pubkeys_list: Vec<_> = keypairs.iter().map(|k| k.public_key.clone()).collect();
+        let agg_pub_keys: Vec<_> = pubkeys_list
+            .iter()
+            .map(|pubkey| PublicKeyAgg::key_aggregation_n(pubkeys_list.clone(), pubkey).unwrap())
+            .collect();
+        let (private_partial_nonces, public_partial_nonces): (Vec<_>, Vec<_>) = keypairs
+            .iter()
+            .map(|keypair| {
+                musig2::generate_partial_nonces_internal(
+                    keypair,
+                    Some(&message),
+                    &SignatureMode::Pure,
+                    &mut rng,
+                )
+            })
+            .unzip();
+        let nonces_without = |index: usize| -> Vec<_> {
+            public_partial_nonces
+                .iter()
+                .enumerate()
+                .filter(|(j, _)| *j != index)
+                .map(|(_, nonces)| nonces.R.clone())
+                .collect()
+        };
+
+        let mut partial_sigs: Vec<_> = private_partial_nonces
+            .into_iter()
+            .enumerate()
+            .map(|(index, private_nonces)| {
+                musig2::partial_sign(
+                    &nonces_without(index),
+                    private_nonces,
+                    &agg_pub_keys[index],
+                    &keypairs[index],
+                    &message,
+                    &SignatureMode::Pure,
+                )
+                .unwrap()
+            })
+            .collect();
+
+        // Party 1 sends a corrupted share
+        partial_sigs[1].my_partial_s = &partial_sigs[1].my_partial_s + Scalar::from(1);
+
+        let culprits: Vec<_> = (0..keypairs.len())
+            .filter(|&index| {
+                musig2::verify_partial_signature(
+                    &partial_sigs[index],
+                    &nonces_without(index),
+                    &public_partial_nonces[index],
+                    &agg_pub_keys[index],
+                    &pubkeys_list[index],
+                    &message,
+                    &SignatureMode::Pure,
+                    VerificationPolicy::Strict,
+                )
+                .is_err()
+            })
+            .collect();
+        assert_eq!(culprits, vec![1]);
+
+        // A share checked against the wrong message must be rejected as well
+        assert!(musig2::verify_partial_signature(
+            &partial_sigs[0],
+            &nonces_without(0),
+            &public_partial_nonces[0],
+            &agg_pub_keys[0],
+            &pubkeys_list[0],
+            &message[1..],
+            &SignatureMode::Pure,
+            VerificationPolicy::Strict,
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_serde_roundtrip() {
+        let message: [u8; 4] = [79, 77, 69, 82];
+        let keypair = ExpandedKeyPair::create();
+        let agg_pub_key =
+            PublicKeyAgg::key_aggregation_n(vec![keypair.public_key.clone()], &keypair.public_key)
+                .unwrap();
+        assert_eq!(assert_serde_roundtrip(&agg_pub_key), agg_pub_key);
+
+        let (private_nonces, public_nonces) =
+            musig2::generate_partial_nonces(&keypair, Some(&message), &SignatureMode::Pure);
+        assert_eq!(assert_serde_roundtrip(&public_nonces), public_nonces);
+        let exported = assert_serde_roundtrip(&private_nonces.dangerous_export());
+        let private_nonces = exported.dangerous_import();
+        assert_eq!(private_nonces.public_nonces(), public_nonces);
+
+        let partial_sig = musig2::partial_sign(
+            &[],
+            private_nonces,
+            &agg_pub_key,
+            &keypair,
+            &message,
+            &SignatureMode::Pure,
+        )
+        .unwrap();
+        assert_eq!(assert_serde_roundtrip(&partial_sig), partial_sig);
+    }
+
+    #[test]
+    fn test_multiparty_signing_with_modes() {
+        let mut rng = deterministic_fast_rand("test_multiparty_signing_with_modes", None);
+        let data = [79u8, 77, 69, 82];
+        let prehash = SignatureMode::prehash(&data);
+        let ctx = SignatureMode::Context(Context::new(b"musig2").unwrap());
+        let ph = SignatureMode::Prehash(Context::new(b"musig2").unwrap());
+        for (message, mode) in [(&data[..], &ctx), (&prehash[..], &ph)] {
+            let keypairs = [ExpandedKeyPair::create(), ExpandedKeyPair::create()];
+            let pks: Vec<_> = keypairs.iter().map(|k| k.public_key.clone()).collect();
+            let key_aggs: Vec<_> = pks
+                .iter()
+                .map(|pk| PublicKeyAgg::key_aggregation_n(pks.clone(), pk).unwrap())
+                .collect();
+            let (private_nonces, public_nonces): (Vec<_>, Vec<_>) = keypairs
+                .iter()
+                .map(|keypair| {
+                    musig2::generate_partial_nonces_internal(keypair, Some(message), mode, &mut rng)
+                })
+                .unzip();
+            let partial_sigs: Vec<_> = private_nonces
+                .into_iter()
+                .enumerate()
+                .map(|(i, private_nonces)| {
+                    let other_nonces = [public_nonces[1 - i].R.clone()];
+                    let partial_sig = musig2::partial_sign(
+                        &other_nonces,
+                        private_nonces,
+                        &key_aggs[i],
+                        &keypairs[i],
+                        message,
+                        mode,
+                    )
+                    .unwrap();
+                    assert!(musig2::verify_partial_signature(
+                        &partial_sig,
+                        &other_nonces,
+                        &public_nonces[i],
+                        &key_aggs[i],
+                        &pks[i],
+                        message,
+                        mode,
+                        VerificationPolicy::Strict,
+                    )
+                    .is_ok());
+                    partial_sig
+                })
+                .collect();
+            let signature = musig2::aggregate_partial_signatures(
+                &partial_sigs[0],
+                &[partial_sigs[1].my_partial_s.clone()],
+            );
+            let apk = &key_aggs[0].agg_public_key;
+            assert!(signature
+                .verify_with_mode(message, apk, mode, VerificationPolicy::Strict)
+                .is_ok());
+            assert!(signature.verify(message, apk).is_err());
+            if let SignatureMode::Prehash(_) = mode {
+                assert!(verify_dalek_prehashed(apk, &signature, &data, b"musig2"));
+            }
+        }
+    }
+
+    #[test]
+    fn test_nonces_bound_to_key_mode_and_message() {
+        let mut rng = deterministic_fast_rand("test_nonces_bound_to_key_mode_and_message", None);
+        let message: [u8; 4] = [79, 77, 69, 82];
+        let keypair = ExpandedKeyPair::create();
+        let other_keypair = ExpandedKeyPair::create();
+        let agg_pub_key =
+            PublicKeyAgg::key_aggregation_n(vec![keypair.public_key.clone()], &keypair.public_key)
+                .unwrap();
+
+        // nonces generated for another message
+        let (private_nonces, _) = musig2::generate_partial_nonces_internal(
+            &keypair,
+            Some(&message),
+            &SignatureMode::Pure,
+            &mut rng,
+        );
+        assert_eq!(
+            musig2::partial_sign(
+                &[],
+                private_nonces,
+                &agg_pub_key,
+                &keypair,
+                &message[1..],
+                &SignatureMode::Pure
+            )
+            .unwrap_err(),
+            Error::InvalidNonce
+        );
+
+        // nonces generated for another mode
+        let ctx = SignatureMode::Context(Context::new(b"musig2").unwrap());
+        let (private_nonces, _) =
+            musig2::generate_partial_nonces_internal(&keypair, Some(&message), &ctx, &mut rng);
+        assert_eq!(
+            musig2::partial_sign(
+                &[],
+                private_nonces,
+                &agg_pub_key,
+                &keypair,
+                &message,
+                &SignatureMode::Pure
+            )
+            .unwrap_err(),
+            Error::InvalidNonce
+        );
+
+        // nonces generated for another key
+        let (private_nonces, _) = musig2::generate_partial_nonces_internal(
+            &other_keypair,
+            Some(&message),
+            &SignatureMode::Pure,
+            &mut rng,
+        );
+        assert_eq!(
+            musig2::partial_sign(
+                &[],
+                private_nonces,
+                &agg_pub_key,
+                &keypair,
+                &message,
+                &SignatureMode::Pure
+            )
+            .unwrap_err(),
+            Error::InvalidNonce
+        );
+
+        // nonces generated without a message can sign any message
+        let (private_nonces, _) = musig2::generate_partial_nonces_internal(
+            &keypair,
+            None,
+            &SignatureMode::Pure,
+            &mut rng,
+        );
+        let partial_sig = musig2::partial_sign(
+            &[],
+            private_nonces,
+            &agg_pub_key,
+            &keypair,
+            &message,
+            &SignatureMode::Pure,
+        )
+        .unwrap();
+        let signature = musig2::aggregate_partial_signatures(&partial_sig, &[]);
+        assert!(signature
+            .verify(&message, &agg_pub_key.agg_public_key)
+            .is_ok());
+    }
 }
@@ -0,0 +1,417 @@
+/*
+    multi-party-ed25519
+
+    Copyright 2018 by Kzen Networks
+
+    This file is part of multi-party-ed25519 library
+    (https://github.com/KZen-networks/multisig-schnorr)
+
+    multi-party-ed25519 is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-ed25519/blob/master/LICENSE>
+*/
+
+//! One interface for the signing schemes of this crate, so the same code can sign with any of
+//! them: `aggsig::AggSig`, `musig2::MuSig2`, `multisig::MultiSig` and `multisig::Ed25519MultiSig`
+//! for n-of-n keys, and `thresholdsig::frost::Frost` for t-of-n keys.
+//!
+//! The original `thresholdsig` signing is left out: its signers deal shares of an ephemeral key
+//! to each other over private channels, which doesn't fit the broadcast rounds below. It runs as
+//! `thresholdsig::state_machine::Signing` instead, and `Frost` signs with the same keys.
+//!
+//! Signing goes the same way for all of them: every signer generates nonces and broadcasts the
+//! public part, after a commitment to it for the schemes that need one. Once all the nonces are
+//! there every signer broadcasts a partial signature, and the partial signatures are checked and
+//! combined into the signature. `Signing` runs these rounds as a state machine.
+//!
+//! Signatures are made in a `SignatureMode`, which all the signers and the verifier have to agree
+//! on. `MultiSig` has its own challenge rather than the one of Ed25519, so it only signs in
+//! `SignatureMode::Pure`.
+
+use curv::elliptic::curves::{Ed25519, Point};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+use protocols::state_machine::{Msg, RoundError, RoundMessage, Rounds, StateMachine};
+use protocols::{check_distinct, ExpandedKeyPair, SignatureMode};
+use Error::{self, InvalidDecom, InvalidKey, InvalidPartialSig, MissingParty, UnknownParty};
+
+/// The key a party signs with, and the public key its group signs for.
+pub trait KeyAggregation {
+    /// The party's secret key, along with what it knows of the keys of the other parties.
+    type KeyShare;
+
+    fn party_index(key: &Self::KeyShare) -> u16;
+
+    /// The public key the signatures of the group verify with.
+    fn public_key(key: &Self::KeyShare) -> Point<Ed25519>;
+
+    /// Checks that `signers`, the indices of the signing parties, can sign with this key.
+    fn check_signers(key: &Self::KeyShare, signers: &[u16]) -> Result<(), Error>;
+}
+
+pub trait MultiPartySignature: KeyAggregation {
+    /// Whether the public nonces are committed to in a round of their own before they are sent.
+    const COMMITS_TO_NONCES: bool;
+
+    /// `()` if the scheme doesn't commit to the nonces.
+    type NonceCommitment: Clone + Serialize + DeserializeOwned;
+    /// Must be used for a single signature only, which `partial_sign` enforces by taking them.
+    type SecretNonces;
+    type PublicNonces: Clone + Serialize + DeserializeOwned;
+    type PartialSignature: Clone + Serialize + DeserializeOwned;
+    type Signature;
+
+    fn generate_nonces(
+        key: &Self::KeyShare,
+        message: &[u8],
+        mode: &SignatureMode,
+    ) -> (
+        Self::SecretNonces,
+        Self::NonceCommitment,
+        Self::PublicNonces,
+    );
+
+    /// Checks that `nonces` are the ones `commitment` was made to.
+    fn check_nonces(
+        commitment: &Self::NonceCommitment,
+        nonces: &Self::PublicNonces,
+    ) -> Result<(), Error>;
+
+    /// Signs `message`, `nonces` are the public nonces of all the signers by index, this one
+    /// included.
+    fn partial_sign(
+        key: &Self::KeyShare,
+        secret_nonces: Self::SecretNonces,
+        nonces: &BTreeMap<u16, Self::PublicNonces>,
+        message: &[u8],
+        mode: &SignatureMode,
+    ) -> Result<Self::PartialSignature, Error>;
+
+    /// Checks the partial signature of `signer`, fails with `Error::InvalidPartialSig` if it's
+    /// wrong.
+    fn verify_partial(
+        key: &Self::KeyShare,
+        nonces: &BTreeMap<u16, Self::PublicNonces>,
+        message: &[u8],
+        mode: &SignatureMode,
+        signer: u16,
+        partial_sig: &Self::PartialSignature,
+    ) -> Result<(), Error>;
+
+    /// Combines the partial signatures of all the signers, which should have been checked with
+    /// `verify_partial`.
+    fn combine(
+        key: &Self::KeyShare,
+        nonces: &BTreeMap<u16, Self::PublicNonces>,
+        message: &[u8],
+        mode: &SignatureMode,
+        partial_sigs: &BTreeMap<u16, Self::PartialSignature>,
+    ) -> Result<Self::Signature, Error>;
+
+    fn verify(
+        public_key: &Point<Ed25519>,
+        message: &[u8],
+        mode: &SignatureMode,
+        signature: &Self::Signature,
+    ) -> Result<(), Error>;
+}
+
+/// The key of a party in an n-of-n scheme: its key pair and the public keys of all the parties.
+/// Parties are indexed by the position of their public key.
+///
+/// The public keys are taken as they are, which is safe for `AggSig` and `MuSig2` as they weigh
+/// each key by a coefficient. `MultiSig` adds the keys up and uses `multisig::MembershipKey`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GroupKey {
+    keys: ExpandedKeyPair,
+    public_keys: Vec<Point<Ed25519>>,
+    party_index: u16,
+}
+
+impl GroupKey {
+    pub fn new(keys: ExpandedKeyPair, public_keys: Vec<Point<Ed25519>>) -> Result<GroupKey, Error> {
+        let party_index = public_keys
+            .iter()
+            .position(|public_key| *public_key == keys.public_key)
+            .ok_or(InvalidKey)?;
+        Ok(GroupKey {
+            keys,
+            public_keys,
+            party_index: party_index as u16,
+        })
+    }
+
+    pub fn keys(&self) -> &ExpandedKeyPair {
+        &self.keys
+    }
+
+    pub fn public_keys(&self) -> &[Point<Ed25519>] {
+        &self.public_keys
+    }
+
+    pub fn party_index(&self) -> u16 {
+        self.party_index
+    }
+
+    // every party has to sign
+    pub(crate) fn check_signers(&self, signers: &[u16]) -> Result<(), Error> {
+        check_all_signers(signers, self.public_keys.len())
+    }
+}
+
+// `signers` must be all of the `count` parties of an n-of-n key, indexed from 0
+pub(crate) fn check_all_signers(signers: &[u16], count: usize) -> Result<(), Error> {
+    check_distinct(signers)?;
+    if let Some(&signer) = signers.iter().find(|&&signer| usize::from(signer) >= count) {
+        return Err(UnknownParty(signer));
+    }
+    match (0..count as u16).find(|party| !signers.contains(party)) {
+        Some(party) => Err(MissingParty(party)),
+        None => Ok(()),
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "")]
+pub enum SchemeMessage<S: MultiPartySignature> {
+    NonceCommitment(S::NonceCommitment),
+    Nonces(S::PublicNonces),
+    PartialSignature(S::PartialSignature),
+}
+
+impl<S: MultiPartySignature> Clone for SchemeMessage<S> {
+    fn clone(&self) -> Self {
+        match self {
+            SchemeMessage::NonceCommitment(commitment) => {
+                SchemeMessage::NonceCommitment(commitment.clone())
+            }
+            SchemeMessage::Nonces(nonces) => SchemeMessage::Nonces(nonces.clone()),
+            SchemeMessage::PartialSignature(partial_sig) => {
+                SchemeMessage::PartialSignature(partial_sig.clone())
+            }
+        }
+    }
+}
+
+// the rounds are numbered as if every scheme committed to its nonces
+fn phase<S: MultiPartySignature>(round: u16) -> u16 {
+    if S::COMMITS_TO_NONCES {
+        round
+    } else {
+        round + 1
+    }
+}
+
+impl<S: MultiPartySignature> RoundMessage for SchemeMessage<S> {
+    fn round(&self) -> u16 {
+        let phase = match self {
+            SchemeMessage::NonceCommitment(_) => 1,
+            SchemeMessage::Nonces(_) => 2,
+            SchemeMessage::PartialSignature(_) => 3,
+        };
+        if S::COMMITS_TO_NONCES {
+            phase
+        } else {
+            phase - 1
+        }
+    }
+
+    fn is_broadcast(&self) -> bool {
+        true
+    }
+}
+
+/// Signing with any scheme as a state machine, in 3 rounds for the schemes that commit to their
+/// nonces and in 2 rounds for the others.
+pub struct Signing<S: MultiPartySignature> {
+    rounds: Rounds<SchemeMessage<S>>,
+    key: S::KeyShare,
+    message: Vec<u8>,
+    mode: SignatureMode,
+    secret_nonces: Option<S::SecretNonces>,
+    // sent once the commitments are in, for the schemes that commit to their nonces
+    public_nonces: Option<S::PublicNonces>,
+    commitments: BTreeMap<u16, S::NonceCommitment>,
+    nonces: BTreeMap<u16, S::PublicNonces>,
+    partial_sigs: BTreeMap<u16, S::PartialSignature>,
+    output: Option<S::Signature>,
+}
+
+impl<S: MultiPartySignature> Signing<S> {
+    /// Starts signing `message` in `mode` with `key`, `signers` are the indices of the signing
+    /// parties, including this one.
+    pub fn new(
+        key: S::KeyShare,
+        signers: Vec<u16>,
+        message: &[u8],
+        mode: SignatureMode,
+    ) -> Result<Signing<S>, RoundError> {
+        let me = S::party_index(&key);
+        S::check_signers(&key, &signers)?;
+        let total_rounds = if S::COMMITS_TO_NONCES { 3 } else { 2 };
+        let (secret_nonces, commitment, public_nonces) = S::generate_nonces(&key, message, &mode);
+        let mut signing = Signing {
+            rounds: Rounds::new(me, signers, total_rounds)?,
+            key,
+            message: message.to_vec(),
+            mode,
+            secret_nonces: Some(secret_nonces),
+            public_nonces: None,
+            commitments: BTreeMap::new(),
+            nonces: BTreeMap::new(),
+            partial_sigs: BTreeMap::new(),
+            output: None,
+        };
+        if S::COMMITS_TO_NONCES {
+            signing.commitments.insert(me, commitment.clone());
+            signing.public_nonces = Some(public_nonces);
+            signing
+                .rounds
+                .broadcast(SchemeMessage::NonceCommitment(commitment));
+        } else {
+            signing.nonces.insert(me, public_nonces.clone());
+            signing
+                .rounds
+                .broadcast(SchemeMessage::Nonces(public_nonces));
+        }
+        signing.proceed()?;
+        Ok(signing)
+    }
+
+    fn proceed(&mut self) -> Result<(), RoundError> {
+        while self.rounds.is_complete() {
+            let round = self.rounds.current_round();
+            let messages = self.rounds.advance();
+            if let Err(error) = self.finish_round(round, messages) {
+                return Err(self.rounds.fail(round, error));
+            }
+        }
+        Ok(())
+    }
+
+    fn finish_round(
+        &mut self,
+        round: u16,
+        messages: BTreeMap<u16, SchemeMessage<S>>,
+    ) -> Result<(), Error> {
+        let me = self.rounds.party_index();
+        match phase::<S>(round) {
+            1 => {
+                for (sender, message) in messages {
+                    if let SchemeMessage::NonceCommitment(commitment) = message {
+                        self.commitments.insert(sender, commitment);
+                    }
+                }
+                let public_nonces = self.public_nonces.take().expect("sent once, in round 2");
+                self.nonces.insert(me, public_nonces.clone());
+                self.rounds.broadcast(SchemeMessage::Nonces(public_nonces));
+            }
+            2 => {
+                let mut bad_senders = Vec::new();
+                for (sender, message) in messages {
+                    if let SchemeMessage::Nonces(nonces) = message {
+                        if S::COMMITS_TO_NONCES
+                            && S::check_nonces(&self.commitments[&sender], &nonces).is_err()
+                        {
+                            bad_senders.push(sender);
+                        }
+                        self.nonces.insert(sender, nonces);
+                    }
+                }
+                if !bad_senders.is_empty() {
+                    return Err(InvalidDecom(bad_senders));
+                }
+                let secret_nonces = self.secret_nonces.take().expect("used once");
+                let partial_sig = S::partial_sign(
+                    &self.key,
+                    secret_nonces,
+                    &self.nonces,
+                    &self.message,
+                    &self.mode,
+                )?;
+                self.partial_sigs.insert(me, partial_sig.clone());
+                self.rounds
+                    .broadcast(SchemeMessage::PartialSignature(partial_sig));
+            }
+            _ => {
+                let mut bad_senders = Vec::new();
+                for (sender, message) in messages {
+                    if let SchemeMessage::PartialSignature(partial_sig) = message {
+                        match S::verify_partial(
+                            &self.key,
+                            &self.nonces,
+                            &self.message,
+                            &self.mode,
+                            sender,
+                            &partial_sig,
+                        ) {
+                            Err(InvalidPartialSig(_)) => bad_senders.push(sender),
+                            result => result?,
+                        }
+                        self.partial_sigs.insert(sender, partial_sig);
+                    }
+                }
+                if !bad_senders.is_empty() {
+                    return Err(InvalidPartialSig(bad_senders));
+                }
+                let signature = S::combine(
+                    &self.key,
+                    &self.nonces,
+                    &self.message,
+                    &self.mode,
+                    &self.partial_sigs,
+                )?;
+                S::verify(
+                    &S::public_key(&self.key),
+                    &self.message,
+                    &self.mode,
+                    &signature,
+                )?;
+                self.output = Some(signature);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<S: MultiPartySignature> StateMachine for Signing<S> {
+    type MessageBody = SchemeMessage<S>;
+    type Output = S::Signature;
+
+    fn handle_incoming(&mut self, msg: Msg<SchemeMessage<S>>) -> Result<(), RoundError> {
+        self.rounds.accept(msg)?;
+        self.proceed()
+    }
+
+    fn outgoing(&mut self) -> Vec<Msg<SchemeMessage<S>>> {
+        self.rounds.take_outgoing()
+    }
+
+    fn pick_output(&mut self) -> Result<S::Signature, RoundError> {
+        self.rounds.check_finished()?;
+        self.output.take().ok_or(RoundError::Finished)
+    }
+
+    fn current_round(&self) -> u16 {
+        self.rounds.current_round()
+    }
+
+    fn total_rounds(&self) -> u16 {
+        self.rounds.total_rounds()
+    }
+
+    fn party_index(&self) -> u16 {
+        self.rounds.party_index()
+    }
+
+    fn parties(&self) -> &[u16] {
+        self.rounds.parties()
+    }
+}
+
+mod test;
@@ -0,0 +1,189 @@
+/*
+    multi-party-ed25519
+
+    Copyright 2018 by Kzen Networks
+
+    This file is part of multi-party-ed25519 library
+    (https://github.com/KZen-networks/multisig-schnorr)
+
+    multi-party-ed25519 is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-ed25519/blob/master/LICENSE>
+*/
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use protocols::aggsig::AggSig;
+    use protocols::multisig::{self, Ed25519MultiSig, Group, MembershipKey, MultiSig};
+    use protocols::musig2::MuSig2;
+    use protocols::scheme::{GroupKey, MultiPartySignature, Signing};
+    use protocols::simulation::Simulation;
+    use protocols::state_machine::RoundError;
+    use protocols::thresholdsig::frost::Frost;
+    use protocols::thresholdsig::state_machine::{KeyGen, LocalKey};
+    use protocols::thresholdsig::{Keys, Parameters};
+    use protocols::{Context, ExpandedKeyPair, SignatureMode};
+    use Error;
+
+    fn group_keys(n: usize) -> Vec<GroupKey> {
+        let keypairs: Vec<_> = (0..n).map(|_| ExpandedKeyPair::create()).collect();
+        let public_keys: Vec<_> = keypairs.iter().map(|k| k.public_key.clone()).collect();
+        keypairs
+            .into_iter()
+            .map(|keys| GroupKey::new(keys, public_keys.clone()).unwrap())
+            .collect()
+    }
+
+    fn membership_keys(n: usize) -> Vec<MembershipKey> {
+        let keys: Vec<_> = (0..n).map(|_| multisig::Keys::create()).collect();
+        let ix_vec: Vec<_> = keys
+            .iter()
+            .map(|k| vec![k.I.public_key.clone(), k.X.public_key.clone()])
+            .collect();
+        let e = multisig::Keys::collect_and_compute_challenge(&ix_vec);
+        let proofs: Vec<_> = keys
+            .iter()
+            .map(|k| multisig::partial_sign(k, e.clone()))
+            .collect();
+        let group = Group::setup(&ix_vec, &proofs).unwrap();
+        keys.into_iter()
+            .map(|k| MembershipKey::new(k.I, group.clone()).unwrap())
+            .collect()
+    }
+
+    // signs with the keys of `signers` in `mode` through the functions of the scheme, then through `Signing`
+    fn sign<S: MultiPartySignature>(keys: Vec<S::KeyShare>, signers: &[u16], mode: SignatureMode) {
+        let message = [79, 77, 69, 82];
+        let keys: Vec<_> = keys
+            .into_iter()
+            .filter(|key| signers.contains(&S::party_index(key)))
+            .collect();
+        let public_key = S::public_key(&keys[0]);
+
+        let mut secret_nonces = BTreeMap::new();
+        let mut nonces = BTreeMap::new();
+        for key in &keys {
+            S::check_signers(key, signers).unwrap();
+            let (secret, commitment, public) = S::generate_nonces(key, &message, &mode);
+            S::check_nonces(&commitment, &public).unwrap();
+            secret_nonces.insert(S::party_index(key), secret);
+            nonces.insert(S::party_index(key), public);
+        }
+        let mut partial_sigs = BTreeMap::new();
+        for key in &keys {
+            let secret = secret_nonces.remove(&S::party_index(key)).unwrap();
+            let partial_sig = S::partial_sign(key, secret, &nonces, &message, &mode).unwrap();
+            partial_sigs.insert(S::party_index(key), partial_sig);
+        }
+        for (&signer, partial_sig) in &partial_sigs {
+            S::verify_partial(&keys[0], &nonces, &message, &mode, signer, partial_sig).unwrap();
+            let other_signer = *signers.iter().find(|&&i| i != signer).unwrap();
+            assert_eq!(
+                S::verify_partial(
+                    &keys[0],
+                    &nonces,
+                    &message,
+                    &mode,
+                    other_signer,
+                    partial_sig
+                ),
+                Err(Error::InvalidPartialSig(vec![other_signer]))
+            );
+        }
+        let signature = S::combine(&keys[0], &nonces, &message, &mode, &partial_sigs).unwrap();
+        S::verify(&public_key, &message, &mode, &signature).unwrap();
+        assert!(S::verify(&public_key, &[79, 77, 69, 83], &mode, &signature).is_err());
+
+        let parties = keys
+            .into_iter()
+            .map(|key| Signing::<S>::new(key, signers.to_vec(), &message, mode.clone()).unwrap())
+            .collect();
+        for signature in Simulation::new(parties).run() {
+            S::verify(&public_key, &message, &mode, &signature.unwrap()).unwrap();
+        }
+    }
+
+    fn threshold_keys(t: u16, n: u16) -> Vec<LocalKey> {
+        let params = Parameters {
+            threshold: t,
+            share_count: n,
+        };
+        let parties: Vec<_> = (1..=n).collect();
+        let keygen = parties
+            .iter()
+            .map(|&i| KeyGen::new(Keys::phase1_create(i), params.clone(), parties.clone()).unwrap())
+            .collect();
+        Simulation::new(keygen)
+            .run()
+            .into_iter()
+            .map(Result::unwrap)
+            .collect()
+    }
+
+    #[test]
+    fn test_n_of_n_schemes() {
+        let ctx = SignatureMode::Context(Context::new(b"scheme").unwrap());
+        for mode in [SignatureMode::Pure, ctx.clone()] {
+            sign::<AggSig>(group_keys(3), &[0, 1, 2], mode.clone());
+            sign::<MuSig2>(group_keys(3), &[0, 1, 2], mode.clone());
+            sign::<Ed25519MultiSig>(membership_keys(3), &[0, 1, 2], mode);
+        }
+        sign::<MultiSig>(membership_keys(3), &[0, 1, 2], SignatureMode::Pure);
+
+        // MultiSig has no signature modes
+        let keys = membership_keys(2);
+        let (secret, _, public) = MultiSig::generate_nonces(&keys[0], &[], &ctx);
+        let nonces: BTreeMap<_, _> = vec![(0, public.clone()), (1, public)].into_iter().collect();
+        assert_eq!(
+            MultiSig::partial_sign(&keys[0], secret, &nonces, &[], &ctx).err(),
+            Some(Error::InvalidKey)
+        );
+
+        // every party has to sign
+        let mut keys = group_keys(3);
+        assert!(GroupKey::new(ExpandedKeyPair::create(), keys[0].public_keys().to_vec()).is_err());
+        let key = keys.remove(0);
+        assert_eq!(
+            Signing::<MuSig2>::new(key, vec![0, 1], &[], SignatureMode::Pure).err(),
+            Some(RoundError::Setup(Error::MissingParty(2)))
+        );
+        let key = keys.remove(0);
+        assert_eq!(
+            Signing::<AggSig>::new(key, vec![0, 1, 2, 3], &[], SignatureMode::Pure).err(),
+            Some(RoundError::Setup(Error::UnknownParty(3)))
+        );
+        let key = membership_keys(3).remove(1);
+        assert_eq!(
+            Signing::<MultiSig>::new(key, vec![1, 2], &[], SignatureMode::Pure).err(),
+            Some(RoundError::Setup(Error::MissingParty(0)))
+        );
+    }
+
+    #[test]
+    fn test_t_of_n_scheme() {
+        sign::<Frost>(threshold_keys(1, 3), &[1, 3], SignatureMode::Pure);
+        let ctx = SignatureMode::Prehash(Context::new(b"scheme").unwrap());
+        sign::<Frost>(threshold_keys(2, 4), &[4, 2, 1], ctx);
+
+        let mut local_keys = threshold_keys(1, 3);
+        let key = local_keys.remove(0);
+        assert_eq!(
+            Signing::<Frost>::new(key, vec![1], &[], SignatureMode::Pure).err(),
+            Some(RoundError::Setup(Error::WrongPartyCount {
+                threshold: 1,
+                share_count: 3,
+                received: 1
+            }))
+        );
+        let key = local_keys.remove(0);
+        assert_eq!(
+            Signing::<Frost>::new(key, vec![1, 2, 4], &[], SignatureMode::Pure).err(),
+            Some(RoundError::Setup(Error::UnknownParty(4)))
+        );
+    }
+}
@@ -0,0 +1,183 @@
+/*
+    multi-party-ed25519
+
+    Copyright 2018 by Kzen Networks
+
+    This file is part of multi-party-ed25519 library
+    (https://github.com/KZen-networks/multisig-schnorr)
+
+    multi-party-ed25519 is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-ed25519/blob/master/LICENSE>
+*/
+
+//! Runs all the parties of a protocol in one process, over an in-memory network.
+//!
+//! The network works in steps: in each step the messages the parties sent so far are handed to
+//! the interceptor, one copy per receiver, and the messages that are due are delivered. The
+//! interceptor can change a message, drop it or delay it by some steps, and with reordering the
+//! messages of a step are delivered in random order. This way honest runs and misbehaving
+//! networks or parties are tested the same way.
+
+use rand::seq::SliceRandom;
+use rand::RngCore;
+use std::collections::BTreeMap;
+
+use protocols::state_machine::{Msg, RoundError, StateMachine};
+
+/// What the network does with a message.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Fate {
+    Deliver,
+    Drop,
+    /// Deliver it this many steps later.
+    Delay(usize),
+}
+
+type Interceptor<B> = Box<dyn FnMut(u16, &mut Msg<B>) -> Fate>;
+
+pub struct Simulation<M: StateMachine> {
+    parties: Vec<M>,
+    // messages on their way, with the receiver and the step they're delivered at
+    in_flight: Vec<(usize, u16, Msg<M::MessageBody>)>,
+    step: usize,
+    interceptor: Option<Interceptor<M::MessageBody>>,
+    reordering: Option<Box<dyn RngCore>>,
+    rejected: Vec<(u16, Msg<M::MessageBody>, RoundError)>,
+    failures: BTreeMap<u16, RoundError>,
+}
+
+impl<M> Simulation<M>
+where
+    M: StateMachine,
+    M::MessageBody: Clone,
+{
+    pub fn new(parties: Vec<M>) -> Simulation<M> {
+        Simulation {
+            parties,
+            in_flight: Vec::new(),
+            step: 0,
+            interceptor: None,
+            reordering: None,
+            rejected: Vec::new(),
+            failures: BTreeMap::new(),
+        }
+    }
+
+    /// Passes every message to `interceptor` before it's delivered, along with its receiver. A
+    /// broadcast message is passed once for each receiver, so it can be changed for some of them.
+    pub fn with_interceptor<F>(mut self, interceptor: F) -> Simulation<M>
+    where
+        F: FnMut(u16, &mut Msg<M::MessageBody>) -> Fate + 'static,
+    {
+        self.interceptor = Some(Box::new(interceptor));
+        self
+    }
+
+    /// Shuffles the messages delivered in each step with `rng`.
+    pub fn with_reordering<R: RngCore + 'static>(mut self, rng: R) -> Simulation<M> {
+        self.reordering = Some(Box::new(rng));
+        self
+    }
+
+    pub fn parties(&self) -> &[M] {
+        &self.parties
+    }
+
+    pub fn parties_mut(&mut self) -> &mut [M] {
+        &mut self.parties
+    }
+
+    /// Every message a party refused, with the party's index and the reason.
+    pub fn rejected(&self) -> &[(u16, Msg<M::MessageBody>, RoundError)] {
+        &self.rejected
+    }
+
+    /// Adds a message to the network as if a party sent it, e.g. to replay or forge messages.
+    pub fn inject(&mut self, receiver: u16, msg: Msg<M::MessageBody>) {
+        self.in_flight.push((self.step, receiver, msg));
+    }
+
+    /// Moves the network one step, returns false once there is nothing left to deliver.
+    pub fn step(&mut self) -> bool {
+        let mut sent = Vec::new();
+        for party in self.parties.iter_mut() {
+            sent.extend(party.outgoing());
+        }
+        for msg in sent {
+            let receivers: Vec<_> = match msg.receiver {
+                Some(receiver) => vec![receiver],
+                None => self
+                    .parties
+                    .iter()
+                    .map(|party| party.party_index())
+                    .filter(|index| *index != msg.sender)
+                    .collect(),
+            };
+            for receiver in receivers {
+                let mut msg = msg.clone();
+                let fate = match &mut self.interceptor {
+                    Some(interceptor) => interceptor(receiver, &mut msg),
+                    None => Fate::Deliver,
+                };
+                match fate {
+                    Fate::Deliver => self.in_flight.push((self.step, receiver, msg)),
+                    Fate::Delay(steps) => self.in_flight.push((self.step + steps, receiver, msg)),
+                    Fate::Drop => (),
+                }
+            }
+        }
+        if self.in_flight.is_empty() {
+            return false;
+        }
+
+        let step = self.step;
+        let (mut due, later): (Vec<_>, Vec<_>) = self
+            .in_flight
+            .drain(..)
+            .partition(|(deliver_at, _, _)| *deliver_at <= step);
+        self.in_flight = later;
+        if let Some(rng) = &mut self.reordering {
+            due.shuffle(rng);
+        }
+        for (_, receiver, msg) in due {
+            let party = match self
+                .parties
+                .iter_mut()
+                .find(|party| party.party_index() == receiver)
+            {
+                Some(party) => party,
+                None => continue,
+            };
+            if let Err(error) = party.handle_incoming(msg.clone()) {
+                if let RoundError::Protocol { .. } = error {
+                    self.failures
+                        .entry(receiver)
+                        .or_insert_with(|| error.clone());
+                }
+                self.rejected.push((receiver, msg, error));
+            }
+        }
+        self.step += 1;
+        true
+    }
+
+    /// Runs the network until nothing is left to deliver, and returns the result of each party.
+    /// A party that failed verification returns the error it failed with.
+    pub fn run(&mut self) -> Vec<Result<M::Output, RoundError>> {
+        while self.step() {}
+        let failures = &self.failures;
+        self.parties
+            .iter_mut()
+            .map(|party| match failures.get(&party.party_index()) {
+                Some(error) => Err(error.clone()),
+                None => party.pick_output(),
+            })
+            .collect()
+    }
+}
+
+mod test;
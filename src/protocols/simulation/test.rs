@@ -0,0 +1,225 @@
+/*
+    multi-party-ed25519
+
+    Copyright 2018 by Kzen Networks
+
+    This file is part of multi-party-ed25519 library
+    (https://github.com/KZen-networks/multisig-schnorr)
+
+    multi-party-ed25519 is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-ed25519/blob/master/LICENSE>
+*/
+
+#[cfg(test)]
+mod tests {
+    use curv::elliptic::curves::Scalar;
+    use rand::Rng;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use protocols::aggsig::state_machine::{Signing, SigningMessage};
+    use protocols::simulation::{Fate, Simulation};
+    use protocols::state_machine::{RoundError, StateMachine};
+    use protocols::tests::{deterministic_fast_rand, verify_dalek};
+    use protocols::thresholdsig::state_machine::{self, KeyGen, ThresholdMessage};
+    use protocols::thresholdsig::{Keys, Parameters};
+    use protocols::{ExpandedKeyPair, SignatureMode};
+    use Error;
+
+    fn keygen_parties(t: u16, n: u16) -> Vec<KeyGen> {
+        let params = Parameters {
+            threshold: t,
+            share_count: n,
+        };
+        let parties: Vec<_> = (1..=n).collect();
+        parties
+            .iter()
+            .map(|&i| KeyGen::new(Keys::phase1_create(i), params.clone(), parties.clone()).unwrap())
+            .collect()
+    }
+
+    fn aggsig_parties(n: usize, message: &[u8]) -> Vec<Signing> {
+        let keypairs: Vec<_> = (0..n).map(|_| ExpandedKeyPair::create()).collect();
+        let public_keys: Vec<_> = keypairs.iter().map(|k| k.public_key.clone()).collect();
+        keypairs
+            .into_iter()
+            .enumerate()
+            .map(|(i, keypair)| {
+                Signing::new(
+                    i as u16,
+                    keypair,
+                    public_keys.clone(),
+                    message,
+                    SignatureMode::Pure,
+                )
+                .unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_reordered_and_delayed_messages() {
+        let mut rng = deterministic_fast_rand("test_reordered_and_delayed_messages", None);
+        let mut delays = deterministic_fast_rand("test_reordered_and_delayed_messages", None);
+        let message = [79, 77, 69, 82];
+
+        let local_keys: Vec<_> = Simulation::new(keygen_parties(2, 5))
+            .with_reordering(deterministic_fast_rand(
+                "keygen reordering",
+                Some(rng.gen()),
+            ))
+            .with_interceptor(move |_, _| Fate::Delay(delays.gen_range(0..4)))
+            .run()
+            .into_iter()
+            .map(Result::unwrap)
+            .collect();
+
+        let signers = vec![1, 3, 4, 5];
+        let parties = signers
+            .iter()
+            .map(|&i| {
+                state_machine::Signing::new(
+                    &local_keys[usize::from(i - 1)],
+                    signers.clone(),
+                    &message,
+                    SignatureMode::Pure,
+                )
+                .unwrap()
+            })
+            .collect();
+        let mut simulation = Simulation::new(parties).with_reordering(rng);
+        for signature in simulation.run() {
+            assert!(verify_dalek(
+                &local_keys[0].shared_keys.y,
+                &signature.unwrap(),
+                &message
+            ));
+        }
+        assert!(simulation.rejected().is_empty());
+    }
+
+    #[test]
+    fn test_dropped_message() {
+        let message = [79, 77, 69, 82];
+        let results = Simulation::new(aggsig_parties(3, &message))
+            .with_interceptor(|receiver, msg| match msg.body {
+                SigningMessage::Reveal(_) if msg.sender == 2 && receiver == 0 => Fate::Drop,
+                _ => Fate::Deliver,
+            })
+            .run();
+        assert_eq!(
+            results[0],
+            Err(RoundError::MissingMessages {
+                round: 2,
+                parties: vec![2]
+            })
+        );
+        // party 0 never sends its partial signature
+        for result in &results[1..] {
+            assert_eq!(
+                result,
+                &Err(RoundError::MissingMessages {
+                    round: 3,
+                    parties: vec![0]
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn test_corrupted_message() {
+        let results = Simulation::new(keygen_parties(1, 3))
+            .with_interceptor(|receiver, msg| {
+                if let ThresholdMessage::Share { share, .. } = &mut msg.body {
+                    if msg.sender == 3 && receiver == 1 {
+                        *share = &*share + Scalar::from(1);
+                    }
+                }
+                Fate::Deliver
+            })
+            .run();
+        assert_eq!(
+            results[0].as_ref().unwrap_err(),
+            &RoundError::Protocol {
+                round: 3,
+                error: Error::InvalidSS(vec![3])
+            }
+        );
+        assert!(results[1..].iter().all(Result::is_ok));
+    }
+
+    #[test]
+    fn test_injected_empty_vss() {
+        // party 3's share for party 1 is held back and replaced by one with no commitments
+        let held_back = Rc::new(RefCell::new(None));
+        let stash = held_back.clone();
+        let mut simulation =
+            Simulation::new(keygen_parties(1, 3)).with_interceptor(move |receiver, msg| {
+                if let ThresholdMessage::Share { .. } = msg.body {
+                    if msg.sender == 3 && receiver == 1 {
+                        *stash.borrow_mut() = Some(msg.clone());
+                        return Fate::Drop;
+                    }
+                }
+                Fate::Deliver
+            });
+        while held_back.borrow().is_none() {
+            assert!(simulation.step());
+        }
+        let mut forged = held_back.borrow_mut().take().unwrap();
+        if let ThresholdMessage::Share { vss, .. } = &mut forged.body {
+            vss.commitments.clear();
+        }
+        simulation.inject(1, forged);
+
+        let results = simulation.run();
+        assert_eq!(
+            results[0].as_ref().unwrap_err(),
+            &RoundError::Protocol {
+                round: 3,
+                error: Error::InvalidSS(vec![3])
+            }
+        );
+        assert!(results[1..].iter().all(Result::is_ok));
+    }
+
+    #[test]
+    fn test_replayed_message() {
+        let message = [79, 77, 69, 82];
+        let mut parties = aggsig_parties(3, &message);
+        let commitment = parties[1].outgoing().pop().unwrap();
+        let mut simulation = Simulation::new(parties);
+        for receiver in [0, 2, 2] {
+            simulation.inject(receiver, commitment.clone());
+        }
+        // a message from outside the protocol
+        let mut forged = commitment.clone();
+        forged.sender = 7;
+        simulation.inject(0, forged);
+
+        let results = simulation.run();
+        assert!(results.iter().all(Result::is_ok));
+        let rejected: Vec<_> = simulation
+            .rejected()
+            .iter()
+            .map(|(receiver, _, error)| (*receiver, error.clone()))
+            .collect();
+        assert_eq!(
+            rejected,
+            vec![
+                (
+                    2,
+                    RoundError::Duplicate {
+                        sender: 1,
+                        round: 1
+                    }
+                ),
+                (0, RoundError::UnknownSender(7))
+            ]
+        );
+    }
+}
@@ -0,0 +1,303 @@
+/*
+    multi-party-ed25519
+
+    Copyright 2018 by Kzen Networks
+
+    This file is part of multi-party-ed25519 library
+    (https://github.com/KZen-networks/multisig-schnorr)
+
+    multi-party-ed25519 is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-ed25519/blob/master/LICENSE>
+*/
+
+//! Round based state machines for the protocols of this crate.
+//!
+//! Each party of a protocol is driven by a `StateMachine`: the messages it sends are taken from
+//! `outgoing`, and the messages addressed to it are passed to `handle_incoming`, in any order and
+//! over any transport. Messages of the next round may arrive before the current one is done, they
+//! are kept until the machine gets there. Anything else that doesn't fit the round structure is
+//! rejected with a `RoundError`.
+
+use protocols::check_distinct;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::mem;
+use Error::UnknownParty;
+use {Error, Parties};
+
+/// A message of a protocol, `receiver` is `None` for broadcast messages.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Msg<B> {
+    pub sender: u16,
+    pub receiver: Option<u16>,
+    pub body: B,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum RoundError {
+    /// The sender is not one of the other parties of the protocol.
+    UnknownSender(u16),
+    /// A point-to-point message was addressed to another party, or a message was sent
+    /// point-to-point where a broadcast was expected or the other way around.
+    WrongReceiver { sender: u16, round: u16 },
+    /// The message is for a round that is already over, or more than one round ahead.
+    OutOfOrder {
+        sender: u16,
+        round: u16,
+        current_round: u16,
+    },
+    /// The sender already sent its message for this round.
+    Duplicate { sender: u16, round: u16 },
+    /// The output was requested before the protocol finished, holds the parties whose message for
+    /// the current round didn't arrive yet.
+    MissingMessages { round: u16, parties: Vec<u16> },
+    /// The output was already taken, or the protocol failed before.
+    Finished,
+    /// The protocol can't start with the key or the parties it was given.
+    Setup(Error),
+    /// The messages of `round` failed verification.
+    Protocol { round: u16, error: Error },
+}
+
+impl fmt::Display for RoundError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RoundError::UnknownSender(sender) => write!(f, "message from unknown party {}", sender),
+            RoundError::WrongReceiver { sender, round } => write!(
+                f,
+                "round {} message from party {} was sent to the wrong receiver",
+                round, sender
+            ),
+            RoundError::OutOfOrder {
+                sender,
+                round,
+                current_round,
+            } => write!(
+                f,
+                "round {} message from party {} arrived in round {}",
+                round, sender, current_round
+            ),
+            RoundError::Duplicate { sender, round } => {
+                write!(f, "party {} sent its round {} message twice", sender, round)
+            }
+            RoundError::MissingMessages { round, parties } => write!(
+                f,
+                "round {} is waiting for the messages of parties {}",
+                round,
+                Parties(parties)
+            ),
+            RoundError::Finished => f.write_str("the protocol is over"),
+            RoundError::Setup(error) => write!(f, "can't start the protocol: {}", error),
+            RoundError::Protocol { round, error } => write!(f, "round {} failed: {}", round, error),
+        }
+    }
+}
+
+impl std::error::Error for RoundError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            RoundError::Setup(error) | RoundError::Protocol { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<Error> for RoundError {
+    fn from(error: Error) -> Self {
+        RoundError::Setup(error)
+    }
+}
+
+pub trait StateMachine {
+    type MessageBody;
+    type Output;
+
+    /// Takes a message addressed to this party. Once a round has all its messages the machine
+    /// moves on, so this also fails with `RoundError::Protocol` if the round fails verification.
+    fn handle_incoming(&mut self, msg: Msg<Self::MessageBody>) -> Result<(), RoundError>;
+
+    /// Takes the messages this party has to send so far.
+    fn outgoing(&mut self) -> Vec<Msg<Self::MessageBody>>;
+
+    /// Takes the result of the protocol, fails with `RoundError::MissingMessages` until all the
+    /// messages have been handled.
+    fn pick_output(&mut self) -> Result<Self::Output, RoundError>;
+
+    /// The round this party is waiting for messages of, starting at 1.
+    fn current_round(&self) -> u16;
+
+    fn total_rounds(&self) -> u16;
+
+    fn party_index(&self) -> u16;
+
+    /// The indices of all the parties of the protocol, including this one.
+    fn parties(&self) -> &[u16];
+
+    fn is_finished(&self) -> bool {
+        self.current_round() > self.total_rounds()
+    }
+}
+
+/// Implemented by the message bodies of each protocol, so `Rounds` can sort them.
+pub(crate) trait RoundMessage {
+    fn round(&self) -> u16;
+    fn is_broadcast(&self) -> bool;
+}
+
+/// Bookkeeping shared by the state machines: collects the messages of each round from every other
+/// party and queues the messages to send.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct Rounds<B> {
+    party_index: u16,
+    parties: Vec<u16>,
+    current_round: u16,
+    total_rounds: u16,
+    // messages of the current and the next round, by sender
+    current: BTreeMap<u16, B>,
+    next: BTreeMap<u16, B>,
+    outgoing: Vec<Msg<B>>,
+    failed: bool,
+}
+
+impl<B: RoundMessage> Rounds<B> {
+    /// Fails if `parties` has duplicates or doesn't contain `party_index`.
+    pub fn new(
+        party_index: u16,
+        mut parties: Vec<u16>,
+        total_rounds: u16,
+    ) -> Result<Rounds<B>, RoundError> {
+        check_distinct(&parties)?;
+        if !parties.contains(&party_index) {
+            return Err(RoundError::Setup(UnknownParty(party_index)));
+        }
+        parties.sort_unstable();
+        Ok(Rounds {
+            party_index,
+            parties,
+            current_round: 1,
+            total_rounds,
+            current: BTreeMap::new(),
+            next: BTreeMap::new(),
+            outgoing: Vec::new(),
+            failed: false,
+        })
+    }
+
+    pub fn party_index(&self) -> u16 {
+        self.party_index
+    }
+
+    pub fn parties(&self) -> &[u16] {
+        &self.parties
+    }
+
+    pub fn current_round(&self) -> u16 {
+        self.current_round
+    }
+
+    pub fn total_rounds(&self) -> u16 {
+        self.total_rounds
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.current_round > self.total_rounds
+    }
+
+    pub fn accept(&mut self, msg: Msg<B>) -> Result<(), RoundError> {
+        if self.failed {
+            return Err(RoundError::Finished);
+        }
+        let sender = msg.sender;
+        let round = msg.body.round();
+        if sender == self.party_index || !self.parties.contains(&sender) {
+            return Err(RoundError::UnknownSender(sender));
+        }
+        if msg.receiver.is_none() != msg.body.is_broadcast()
+            || matches!(msg.receiver, Some(receiver) if receiver != self.party_index)
+        {
+            return Err(RoundError::WrongReceiver { sender, round });
+        }
+        let messages = if round == self.current_round && !self.is_finished() {
+            &mut self.current
+        } else if round == self.current_round + 1 && round <= self.total_rounds {
+            &mut self.next
+        } else if round > 0 && round < self.current_round {
+            // a round is only over once every other party sent its message
+            return Err(RoundError::Duplicate { sender, round });
+        } else {
+            return Err(RoundError::OutOfOrder {
+                sender,
+                round,
+                current_round: self.current_round,
+            });
+        };
+        if messages.contains_key(&sender) {
+            return Err(RoundError::Duplicate { sender, round });
+        }
+        messages.insert(sender, msg.body);
+        Ok(())
+    }
+
+    /// Whether all the other parties sent their message for the current round.
+    pub fn is_complete(&self) -> bool {
+        !self.failed && !self.is_finished() && self.current.len() + 1 == self.parties.len()
+    }
+
+    /// Ends the current round, returning its messages by sender.
+    pub fn advance(&mut self) -> BTreeMap<u16, B> {
+        let next = mem::take(&mut self.next);
+        self.current_round += 1;
+        mem::replace(&mut self.current, next)
+    }
+
+    /// Records that `round` failed, the machine rejects everything afterwards.
+    pub fn fail(&mut self, round: u16, error: Error) -> RoundError {
+        self.failed = true;
+        RoundError::Protocol { round, error }
+    }
+
+    /// Fails unless every round is over.
+    pub fn check_finished(&self) -> Result<(), RoundError> {
+        if self.failed {
+            return Err(RoundError::Finished);
+        }
+        if self.is_finished() {
+            return Ok(());
+        }
+        Err(RoundError::MissingMessages {
+            round: self.current_round,
+            parties: self
+                .parties
+                .iter()
+                .copied()
+                .filter(|party| *party != self.party_index && !self.current.contains_key(party))
+                .collect(),
+        })
+    }
+
+    pub fn broadcast(&mut self, body: B) {
+        self.outgoing.push(Msg {
+            sender: self.party_index,
+            receiver: None,
+            body,
+        });
+    }
+
+    pub fn send(&mut self, receiver: u16, body: B) {
+        self.outgoing.push(Msg {
+            sender: self.party_index,
+            receiver: Some(receiver),
+            body,
+        });
+    }
+
+    pub fn take_outgoing(&mut self) -> Vec<Msg<B>> {
+        mem::take(&mut self.outgoing)
+    }
+}
+
+mod test;
@@ -0,0 +1,194 @@
+/*
+    multi-party-ed25519
+
+    Copyright 2018 by Kzen Networks
+
+    This file is part of multi-party-ed25519 library
+    (https://github.com/KZen-networks/multisig-schnorr)
+
+    multi-party-ed25519 is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-ed25519/blob/master/LICENSE>
+*/
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error;
+
+    use protocols::state_machine::{Msg, RoundError, RoundMessage, Rounds};
+
+    // (round, broadcast)
+    #[derive(Clone, Debug, PartialEq)]
+    struct Body(u16, bool);
+
+    impl RoundMessage for Body {
+        fn round(&self) -> u16 {
+            self.0
+        }
+        fn is_broadcast(&self) -> bool {
+            self.1
+        }
+    }
+
+    fn msg(sender: u16, receiver: Option<u16>, round: u16) -> Msg<Body> {
+        Msg {
+            sender,
+            receiver,
+            body: Body(round, receiver.is_none()),
+        }
+    }
+
+    #[test]
+    fn test_rounds_collect_messages() {
+        let mut rounds = Rounds::new(1, vec![0, 1, 2], 2).unwrap();
+        assert!(!rounds.is_complete());
+        rounds.accept(msg(0, None, 1)).unwrap();
+        // the next round is kept until the current one is over
+        rounds.accept(msg(2, Some(1), 2)).unwrap();
+        assert!(!rounds.is_complete());
+        assert_eq!(
+            rounds.check_finished(),
+            Err(RoundError::MissingMessages {
+                round: 1,
+                parties: vec![2]
+            })
+        );
+        rounds.accept(msg(2, None, 1)).unwrap();
+        assert!(rounds.is_complete());
+        let messages = rounds.advance();
+        assert_eq!(messages.keys().copied().collect::<Vec<_>>(), vec![0, 2]);
+        assert_eq!(rounds.current_round(), 2);
+        assert!(!rounds.is_complete());
+        rounds.accept(msg(0, Some(1), 2)).unwrap();
+        assert!(rounds.is_complete());
+        let messages = rounds.advance();
+        assert_eq!(messages[&2], Body(2, false));
+        assert!(rounds.is_finished());
+        assert_eq!(rounds.check_finished(), Ok(()));
+    }
+
+    #[test]
+    fn test_rounds_need_distinct_parties_with_self() {
+        assert!(matches!(
+            Rounds::<Body>::new(1, vec![0, 2], 2),
+            Err(RoundError::Setup(::Error::UnknownParty(1)))
+        ));
+        assert!(matches!(
+            Rounds::<Body>::new(1, vec![0, 1, 1], 2),
+            Err(RoundError::Setup(::Error::DuplicateParty(1)))
+        ));
+    }
+
+    #[test]
+    fn test_rounds_reject_messages() {
+        let mut rounds = Rounds::new(1, vec![0, 1, 2], 3).unwrap();
+        assert_eq!(
+            rounds.accept(msg(1, None, 1)),
+            Err(RoundError::UnknownSender(1))
+        );
+        assert_eq!(
+            rounds.accept(msg(3, None, 1)),
+            Err(RoundError::UnknownSender(3))
+        );
+        assert_eq!(
+            rounds.accept(msg(0, Some(2), 1)),
+            Err(RoundError::WrongReceiver {
+                sender: 0,
+                round: 1
+            })
+        );
+        let mut broadcast_as_p2p = msg(0, Some(1), 1);
+        broadcast_as_p2p.body.1 = true;
+        assert_eq!(
+            rounds.accept(broadcast_as_p2p),
+            Err(RoundError::WrongReceiver {
+                sender: 0,
+                round: 1
+            })
+        );
+        assert_eq!(
+            rounds.accept(msg(0, None, 3)),
+            Err(RoundError::OutOfOrder {
+                sender: 0,
+                round: 3,
+                current_round: 1
+            })
+        );
+        rounds.accept(msg(0, None, 1)).unwrap();
+        assert_eq!(
+            rounds.accept(msg(0, None, 1)),
+            Err(RoundError::Duplicate {
+                sender: 0,
+                round: 1
+            })
+        );
+        rounds.accept(msg(2, None, 1)).unwrap();
+        rounds.advance();
+        assert_eq!(
+            rounds.accept(msg(2, None, 1)),
+            Err(RoundError::Duplicate {
+                sender: 2,
+                round: 1
+            })
+        );
+        assert_eq!(
+            rounds.accept(msg(2, None, 0)),
+            Err(RoundError::OutOfOrder {
+                sender: 2,
+                round: 0,
+                current_round: 2
+            })
+        );
+
+        assert_eq!(
+            rounds.fail(2, ::Error::InvalidDecom(vec![2])),
+            RoundError::Protocol {
+                round: 2,
+                error: ::Error::InvalidDecom(vec![2])
+            }
+        );
+        assert_eq!(rounds.accept(msg(2, None, 2)), Err(RoundError::Finished));
+        assert_eq!(rounds.check_finished(), Err(RoundError::Finished));
+    }
+
+    #[test]
+    fn test_errors_display() {
+        let error = RoundError::Protocol {
+            round: 3,
+            error: ::Error::InvalidSS(vec![1, 4]),
+        };
+        assert_eq!(
+            error.to_string(),
+            "round 3 failed: invalid secret shares from parties 1, 4"
+        );
+        assert_eq!(
+            Error::source(&error).unwrap().to_string(),
+            "invalid secret shares from parties 1, 4"
+        );
+        let error = RoundError::MissingMessages {
+            round: 2,
+            parties: vec![0, 2],
+        };
+        assert_eq!(
+            error.to_string(),
+            "round 2 is waiting for the messages of parties 0, 2"
+        );
+        assert!(Error::source(&error).is_none());
+        let error = ::Error::WrongPartyCount {
+            threshold: 1,
+            share_count: 3,
+            received: 1,
+        };
+        assert_eq!(
+            RoundError::Setup(error).to_string(),
+            "can't start the protocol: expected more than 1 and at most 3 parties, received 1"
+        );
+        assert_eq!(
+            RoundError::Setup(::Error::DuplicateParty(2)).to_string(),
+            "can't start the protocol: party 2 appears more than once"
+        );
+    }
+}
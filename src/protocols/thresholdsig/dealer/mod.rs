@@ -0,0 +1,76 @@
+/*
+    Multisig eddsa
+    Copyright 2018 by Kzen Networks
+    This file is part of multi-party-eddsa library
+    (https://github.com/KZen-networks/multi-party-eddsa)
+    Multisig Schnorr is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-eddsa/blob/master/LICENSE>
+*/
+
+//! Trusted-dealer import of an existing Ed25519 key
+//!
+//! The dealer expands an RFC 8032 seed as in `ExpandedKeyPair::create_from_private_key` and
+//! splits the private scalar with a Feldman VSS, so the threshold public key `y` is the public key
+//! of the original key. The dealer learns the whole key and should erase the seed afterwards.
+
+use Error::{self, InvalidDealtShare, InvalidKey};
+
+use curv::cryptographic_primitives::secret_sharing::feldman_vss::VerifiableSS;
+use curv::elliptic::curves::{Ed25519, Point, Scalar};
+use protocols::thresholdsig::{check_length, check_parties, Parameters, SharedKeys};
+use protocols::ExpandedKeyPair;
+use zeroize::Zeroize;
+
+// splits the key, shared_keys[j] is sent privately to the party at parties[j] and the VSS is broadcast
+pub fn deal(
+    mut secret: [u8; 32],
+    params: &Parameters,
+    parties: &[u16],
+) -> Result<(VerifiableSS<Ed25519>, Vec<SharedKeys>), Error> {
+    check_length(usize::from(params.share_count), parties.len())?;
+    check_parties(parties)?;
+    let keypair = ExpandedKeyPair::create_from_private_key(secret);
+    secret.zeroize();
+    let (vss_scheme, secret_shares) = VerifiableSS::share_at_indices(
+        params.threshold,
+        params.share_count,
+        &keypair.expanded_private_key.private_key,
+        parties,
+    );
+    let shared_keys = secret_shares
+        .iter()
+        .map(|x_i| SharedKeys {
+            y: keypair.public_key.clone(),
+            x_i: x_i.clone(),
+            prefix: Scalar::random(),
+        })
+        .collect();
+    Ok((vss_scheme, shared_keys))
+}
+
+// run by every recipient, `public_key` is the public key of the imported wallet
+pub fn verify_share(
+    shared_keys: &SharedKeys,
+    vss_scheme: &VerifiableSS<Ed25519>,
+    params: &Parameters,
+    public_key: &Point<Ed25519>,
+    index: u16,
+) -> Result<(), Error> {
+    if vss_scheme.parameters.threshold != params.threshold
+        || vss_scheme.parameters.share_count != params.share_count
+        || vss_scheme.commitments.len() != usize::from(params.threshold) + 1
+        || &vss_scheme.commitments[0] != public_key
+        || &shared_keys.y != public_key
+    {
+        return Err(InvalidKey);
+    }
+    if vss_scheme.validate_share(&shared_keys.x_i, index).is_err() {
+        return Err(InvalidDealtShare(index));
+    }
+    Ok(())
+}
+
+mod test;
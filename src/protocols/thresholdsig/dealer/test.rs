@@ -0,0 +1,120 @@
+#![allow(non_snake_case)]
+/*
+    Multisig eddsa
+    Copyright 2018 by Kzen Networks
+    This file is part of multi-party-eddsa library
+    (https://github.com/KZen-networks/multi-party-eddsa)
+    Multisig Schnorr is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-eddsa/blob/master/LICENSE>
+*/
+#[cfg(test)]
+mod tests {
+    use curv::elliptic::curves::{Point, Scalar};
+    use rand::RngCore;
+
+    use protocols::tests::{deterministic_fast_rand, verify_dalek};
+    use protocols::thresholdsig::{dealer, frost, Parameters};
+    use protocols::{ExpandedKeyPair, SignatureMode};
+    use Error;
+
+    #[test]
+    fn test_import_key_and_sign() {
+        let mut rng = deterministic_fast_rand("test_import_key_and_sign", None);
+        let mut seed = [0u8; 32];
+        rng.fill_bytes(&mut seed);
+        let dalek_secret = ed25519_dalek::SecretKey::from_bytes(&seed).unwrap();
+        let dalek_pub = ed25519_dalek::PublicKey::from(&dalek_secret);
+        let public_key = Point::from_bytes(&dalek_pub.to_bytes()).unwrap();
+        assert_eq!(
+            ExpandedKeyPair::create_from_private_key(seed).public_key,
+            public_key
+        );
+
+        let params = Parameters {
+            threshold: 2,
+            share_count: 4,
+        };
+        let parties = [1u16, 2, 3, 4];
+        let (vss_scheme, shared_keys) = dealer::deal(seed, &params, &parties).unwrap();
+        for (keys, &index) in shared_keys.iter().zip(parties.iter()) {
+            assert!(dealer::verify_share(keys, &vss_scheme, &params, &public_key, index).is_ok());
+        }
+
+        // the imported shares sign for the original public key
+        let message: [u8; 4] = [79, 77, 69, 82];
+        let group = [0usize, 2, 3];
+        let (nonces, commitments): (Vec<_>, Vec<_>) = group
+            .iter()
+            .map(|&j| frost::commit(&shared_keys[j], parties[j]))
+            .unzip();
+        let shares: Vec<_> = nonces
+            .into_iter()
+            .zip(group.iter())
+            .map(|(nonces, &j)| {
+                frost::sign(
+                    &message,
+                    &shared_keys[j],
+                    parties[j],
+                    nonces,
+                    &commitments,
+                    &SignatureMode::Pure,
+                )
+                .unwrap()
+            })
+            .collect();
+        let sig = frost::aggregate(
+            &message,
+            &public_key,
+            &commitments,
+            &shares,
+            &SignatureMode::Pure,
+        )
+        .unwrap();
+        assert!(verify_dalek(&public_key, &sig, &message));
+    }
+
+    #[test]
+    fn test_verify_share_rejects_bad_dealing() {
+        let mut rng = deterministic_fast_rand("test_verify_share_rejects_bad_dealing", None);
+        let mut seed = [0u8; 32];
+        rng.fill_bytes(&mut seed);
+        let public_key = ExpandedKeyPair::create_from_private_key(seed).public_key;
+        let params = Parameters {
+            threshold: 1,
+            share_count: 3,
+        };
+        let parties = [1u16, 2, 3];
+        let (vss_scheme, mut shared_keys) = dealer::deal(seed, &params, &parties).unwrap();
+
+        shared_keys[1].x_i = &shared_keys[1].x_i + Scalar::from(1);
+        assert_eq!(
+            dealer::verify_share(&shared_keys[1], &vss_scheme, &params, &public_key, 2),
+            Err(Error::InvalidDealtShare(2))
+        );
+
+        // a dealing of some other key is rejected by every recipient
+        let other_public_key = ExpandedKeyPair::create().public_key;
+        assert_eq!(
+            dealer::verify_share(&shared_keys[0], &vss_scheme, &params, &other_public_key, 1),
+            Err(Error::InvalidKey)
+        );
+    }
+
+    #[test]
+    fn test_deal_rejects_wrong_party_count() {
+        let params = Parameters {
+            threshold: 1,
+            share_count: 3,
+        };
+        assert_eq!(
+            dealer::deal([7u8; 32], &params, &[1, 2]).err(),
+            Some(Error::WrongLength {
+                expected: 3,
+                received: 2
+            })
+        );
+    }
+}
@@ -0,0 +1,417 @@
+#![allow(non_snake_case)]
+/*
+    Multisig eddsa
+    Copyright 2018 by Kzen Networks
+    This file is part of multi-party-eddsa library
+    (https://github.com/KZen-networks/multi-party-eddsa)
+    Multisig Schnorr is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-eddsa/blob/master/LICENSE>
+*/
+
+//! FROST two-round threshold signing
+//!
+//! See https://www.rfc-editor.org/rfc/rfc9591 , implementing the FROST(Ed25519, SHA-512) ciphersuite.
+//! Signing uses the `SharedKeys` produced by the `thresholdsig` key generation, where a party's
+//! identifier is the index its share was evaluated at. Round one (`commit`) does not depend on
+//! the message, so nonces can be preprocessed in batches with `preprocess`.
+
+use Error::{self, InvalidCom, InvalidKey, InvalidPartialSig, InvalidSig, UnknownParty};
+
+use curv::arithmetic::traits::*;
+use curv::cryptographic_primitives::secret_sharing::feldman_vss::VerifiableSS;
+use curv::cryptographic_primitives::secret_sharing::Polynomial;
+use curv::elliptic::curves::{Ed25519, Point, Scalar};
+use curv::BigInt;
+use protocols::scheme::{KeyAggregation, MultiPartySignature};
+use protocols::thresholdsig::state_machine::LocalKey;
+use protocols::thresholdsig::{check_length, check_parties, check_party_count, SharedKeys};
+use protocols::{Signature, SignatureMode, VerificationPolicy, REDACTED};
+use rand::{thread_rng, Rng};
+use sha2::{digest::Digest, Sha512};
+use std::collections::BTreeMap;
+use std::fmt;
+
+const CONTEXT_STRING: &[u8] = b"FROST-ED25519-SHA512-v1";
+
+// the hiding and binding nonces, must be used for a single signature only.
+#[derive(Serialize, Deserialize)]
+pub struct SigningNonces {
+    hiding: Scalar<Ed25519>,
+    binding: Scalar<Ed25519>,
+}
+
+impl fmt::Debug for SigningNonces {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SigningNonces")
+            .field("hiding", &REDACTED)
+            .field("binding", &REDACTED)
+            .finish()
+    }
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct SigningCommitments {
+    pub index: u16,
+    pub hiding: Point<Ed25519>,
+    pub binding: Point<Ed25519>,
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct SignatureShare {
+    pub index: u16,
+    pub z_i: Scalar<Ed25519>,
+}
+
+// round one: generate nonces and the commitments to broadcast (section 5.1)
+pub fn commit(keys: &SharedKeys, index: u16) -> (SigningNonces, SigningCommitments) {
+    commit_rng(keys, index, &mut thread_rng())
+}
+
+fn commit_rng(
+    keys: &SharedKeys,
+    index: u16,
+    rng: &mut impl Rng,
+) -> (SigningNonces, SigningCommitments) {
+    let hiding = nonce_generate(&rng.gen(), &keys.x_i);
+    let binding = nonce_generate(&rng.gen(), &keys.x_i);
+    let commitments = SigningCommitments {
+        index,
+        hiding: Point::generator() * &hiding,
+        binding: Point::generator() * &binding,
+    };
+    (SigningNonces { hiding, binding }, commitments)
+}
+
+// runs round one `count` times ahead of time, each pair must be used for one signature only.
+pub fn preprocess(
+    keys: &SharedKeys,
+    index: u16,
+    count: usize,
+) -> (Vec<SigningNonces>, Vec<SigningCommitments>) {
+    let mut rng = thread_rng();
+    (0..count)
+        .map(|_| commit_rng(keys, index, &mut rng))
+        .unzip()
+}
+
+// round two: compute the signature share (section 5.2).
+// `commitments` are the round one outputs of all participants in this signature, including ours.
+pub fn sign(
+    message: &[u8],
+    keys: &SharedKeys,
+    index: u16,
+    nonces: SigningNonces,
+    commitments: &[SigningCommitments],
+    mode: &SignatureMode,
+) -> Result<SignatureShare, Error> {
+    let commitments = sorted_commitment_list(commitments)?;
+    let binding_factors = compute_binding_factors(&keys.y, &commitments, message);
+    let my_position = commitments
+        .iter()
+        .position(|comm| comm.index == index)
+        .ok_or(InvalidCom)?;
+
+    let R = compute_group_commitment(&commitments, &binding_factors);
+    let lambda_i = derive_interpolating_value(&commitments, my_position);
+    let challenge = Signature::k(&R, &keys.y, message, mode);
+
+    let z_i = nonces.hiding
+        + nonces.binding * &binding_factors[my_position]
+        + lambda_i * &keys.x_i * challenge;
+    Ok(SignatureShare { index, z_i })
+}
+
+// checks a single signature share against the signer's public share `Y_i = x_i * G` (section 5.4)
+pub fn verify_signature_share(
+    share: &SignatureShare,
+    public_share: &Point<Ed25519>,
+    message: &[u8],
+    group_public_key: &Point<Ed25519>,
+    commitments: &[SigningCommitments],
+    mode: &SignatureMode,
+    policy: VerificationPolicy,
+) -> Result<(), Error> {
+    let commitments = sorted_commitment_list(commitments)?;
+    let binding_factors = compute_binding_factors(group_public_key, &commitments, message);
+    let position = commitments
+        .iter()
+        .position(|comm| comm.index == share.index)
+        .ok_or_else(|| InvalidPartialSig(vec![share.index]))?;
+
+    let R = compute_group_commitment(&commitments, &binding_factors);
+    let lambda_i = derive_interpolating_value(&commitments, position);
+    let challenge = Signature::k(&R, group_public_key, message, mode);
+
+    let comm_share =
+        &commitments[position].hiding + &commitments[position].binding * &binding_factors[position];
+    policy
+        .check(
+            &share.z_i,
+            &comm_share,
+            &(challenge * lambda_i),
+            public_share,
+        )
+        .map_err(|_| InvalidPartialSig(vec![share.index]))
+}
+
+// combines the signature shares of all participants into an Ed25519 signature (section 5.3).
+// the signature is verified before it's returned, a bad share fails it with `InvalidSig` and can
+// be found with `verify_signature_share`.
+pub fn aggregate(
+    message: &[u8],
+    group_public_key: &Point<Ed25519>,
+    commitments: &[SigningCommitments],
+    shares: &[SignatureShare],
+    mode: &SignatureMode,
+) -> Result<Signature, Error> {
+    let commitments = sorted_commitment_list(commitments)?;
+    check_length(commitments.len(), shares.len())?;
+    if !commitments
+        .iter()
+        .all(|comm| shares.iter().any(|share| share.index == comm.index))
+    {
+        return Err(InvalidSig);
+    }
+    let binding_factors = compute_binding_factors(group_public_key, &commitments, message);
+    let R = compute_group_commitment(&commitments, &binding_factors);
+    let s = shares.iter().map(|share| &share.z_i).sum();
+    let signature = Signature { R, s };
+    signature.verify_with_mode(
+        message,
+        group_public_key,
+        mode,
+        VerificationPolicy::default(),
+    )?;
+    Ok(signature)
+}
+
+// public share of party `index`, computed from the keygen VSS commitments of all parties
+pub fn public_share(vss_scheme_vec: &[VerifiableSS<Ed25519>], index: u16) -> Point<Ed25519> {
+    vss_scheme_vec
+        .iter()
+        .map(|vss_scheme| vss_scheme.get_point_commitment(index))
+        .sum()
+}
+
+/// FROST signing with a key from the `thresholdsig` key generation, as a `MultiPartySignature`.
+/// Any set of more than `threshold` parties can sign.
+pub struct Frost;
+
+impl KeyAggregation for Frost {
+    type KeyShare = LocalKey;
+
+    fn party_index(key: &LocalKey) -> u16 {
+        key.keys.party_index
+    }
+
+    fn public_key(key: &LocalKey) -> Point<Ed25519> {
+        key.shared_keys.y.clone()
+    }
+
+    fn check_signers(key: &LocalKey, signers: &[u16]) -> Result<(), Error> {
+        let share_count = key.vss_schemes.len() as u16;
+        let threshold = key
+            .vss_schemes
+            .first()
+            .ok_or(InvalidKey)?
+            .parameters
+            .threshold;
+        check_parties(signers)?;
+        if let Some(&signer) = signers.iter().find(|&&signer| signer > share_count) {
+            return Err(UnknownParty(signer));
+        }
+        check_party_count(threshold, share_count, signers.len())
+    }
+}
+
+impl MultiPartySignature for Frost {
+    const COMMITS_TO_NONCES: bool = false;
+
+    type NonceCommitment = ();
+    type SecretNonces = SigningNonces;
+    type PublicNonces = SigningCommitments;
+    type PartialSignature = SignatureShare;
+    type Signature = Signature;
+
+    fn generate_nonces(
+        key: &LocalKey,
+        _message: &[u8],
+        _mode: &SignatureMode,
+    ) -> (SigningNonces, (), SigningCommitments) {
+        let (nonces, commitments) = commit(&key.shared_keys, key.keys.party_index);
+        (nonces, (), commitments)
+    }
+
+    fn check_nonces(_commitment: &(), _nonces: &SigningCommitments) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn partial_sign(
+        key: &LocalKey,
+        secret_nonces: SigningNonces,
+        nonces: &BTreeMap<u16, SigningCommitments>,
+        message: &[u8],
+        mode: &SignatureMode,
+    ) -> Result<SignatureShare, Error> {
+        let commitments = commitment_list(nonces)?;
+        sign(
+            message,
+            &key.shared_keys,
+            key.keys.party_index,
+            secret_nonces,
+            &commitments,
+            mode,
+        )
+    }
+
+    fn verify_partial(
+        key: &LocalKey,
+        nonces: &BTreeMap<u16, SigningCommitments>,
+        message: &[u8],
+        mode: &SignatureMode,
+        signer: u16,
+        partial_sig: &SignatureShare,
+    ) -> Result<(), Error> {
+        if partial_sig.index != signer {
+            return Err(InvalidPartialSig(vec![signer]));
+        }
+        verify_signature_share(
+            partial_sig,
+            &public_share(&key.vss_schemes, signer),
+            message,
+            &key.shared_keys.y,
+            &commitment_list(nonces)?,
+            mode,
+            VerificationPolicy::default(),
+        )
+    }
+
+    fn combine(
+        key: &LocalKey,
+        nonces: &BTreeMap<u16, SigningCommitments>,
+        message: &[u8],
+        mode: &SignatureMode,
+        partial_sigs: &BTreeMap<u16, SignatureShare>,
+    ) -> Result<Signature, Error> {
+        let shares: Vec<_> = partial_sigs.values().cloned().collect();
+        aggregate(
+            message,
+            &key.shared_keys.y,
+            &commitment_list(nonces)?,
+            &shares,
+            mode,
+        )
+    }
+
+    fn verify(
+        public_key: &Point<Ed25519>,
+        message: &[u8],
+        mode: &SignatureMode,
+        signature: &Signature,
+    ) -> Result<(), Error> {
+        signature.verify_with_mode(message, public_key, mode, VerificationPolicy::default())
+    }
+}
+
+// the commitments by signer index, each one must be for the index it is under
+fn commitment_list(
+    nonces: &BTreeMap<u16, SigningCommitments>,
+) -> Result<Vec<SigningCommitments>, Error> {
+    if nonces.iter().any(|(index, comm)| comm.index != *index) {
+        return Err(InvalidCom);
+    }
+    Ok(nonces.values().cloned().collect())
+}
+
+fn nonce_generate(random_bytes: &[u8; 32], secret: &Scalar<Ed25519>) -> Scalar<Ed25519> {
+    hash_to_scalar(b"nonce", &[random_bytes, &secret.to_bytes()])
+}
+
+// the commitment list must be sorted by identifier, with no duplicates and no zero identifier
+fn sorted_commitment_list(
+    commitments: &[SigningCommitments],
+) -> Result<Vec<SigningCommitments>, Error> {
+    let mut commitments = commitments.to_vec();
+    commitments.sort_by_key(|comm| comm.index);
+    let distinct = commitments.windows(2).all(|w| w[0].index != w[1].index);
+    if commitments.is_empty() || commitments[0].index == 0 || !distinct {
+        return Err(InvalidCom);
+    }
+    Ok(commitments)
+}
+
+fn compute_binding_factors(
+    group_public_key: &Point<Ed25519>,
+    commitments: &[SigningCommitments],
+    message: &[u8],
+) -> Vec<Scalar<Ed25519>> {
+    let msg_hash = hash(b"msg", &[message]);
+    let encoded_commitments: Vec<u8> = commitments
+        .iter()
+        .flat_map(|comm| {
+            let mut encoded = Scalar::<Ed25519>::from(comm.index).to_bytes().to_vec();
+            encoded.extend_from_slice(&comm.hiding.to_bytes(true));
+            encoded.extend_from_slice(&comm.binding.to_bytes(true));
+            encoded
+        })
+        .collect();
+    let encoded_commitment_hash = hash(b"com", &[&encoded_commitments]);
+    let group_public_key = group_public_key.to_bytes(true);
+    commitments
+        .iter()
+        .map(|comm| {
+            hash_to_scalar(
+                b"rho",
+                &[
+                    &group_public_key,
+                    &msg_hash,
+                    &encoded_commitment_hash,
+                    &Scalar::<Ed25519>::from(comm.index).to_bytes(),
+                ],
+            )
+        })
+        .collect()
+}
+
+fn compute_group_commitment(
+    commitments: &[SigningCommitments],
+    binding_factors: &[Scalar<Ed25519>],
+) -> Point<Ed25519> {
+    commitments
+        .iter()
+        .zip(binding_factors)
+        .map(|(comm, binding_factor)| &comm.hiding + &comm.binding * binding_factor)
+        .sum()
+}
+
+fn derive_interpolating_value(
+    commitments: &[SigningCommitments],
+    position: usize,
+) -> Scalar<Ed25519> {
+    let xs: Vec<Scalar<Ed25519>> = commitments
+        .iter()
+        .map(|comm| Scalar::from(comm.index))
+        .collect();
+    Polynomial::lagrange_basis(&Scalar::zero(), position as u16, &xs)
+}
+
+fn hash(tag: &[u8], parts: &[&[u8]]) -> Vec<u8> {
+    let mut hasher = Sha512::new().chain(CONTEXT_STRING).chain(tag);
+    for part in parts {
+        hasher.update(part);
+    }
+    hasher.finalize().to_vec()
+}
+
+fn hash_to_scalar(tag: &[u8], parts: &[&[u8]]) -> Scalar<Ed25519> {
+    let mut hash_result = hash(tag, parts);
+    // reverse because BigInt uses big-endian
+    hash_result.reverse();
+    // reduce modulu the group order
+    Scalar::from_bigint(&BigInt::from_bytes(&hash_result))
+}
+
+mod test;
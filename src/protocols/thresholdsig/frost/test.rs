@@ -0,0 +1,388 @@
+#![allow(non_snake_case)]
+/*
+    Multisig eddsa
+    Copyright 2018 by Kzen Networks
+    This file is part of multi-party-eddsa library
+    (https://github.com/KZen-networks/multi-party-eddsa)
+    Multisig Schnorr is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-eddsa/blob/master/LICENSE>
+*/
+#[cfg(test)]
+mod tests {
+    use std::convert::TryInto;
+
+    use curv::elliptic::curves::{Ed25519, Point, Scalar};
+    use hex::decode;
+    use itertools::Itertools;
+    use rand::RngCore;
+
+    use protocols::tests::{assert_serde_roundtrip, deterministic_fast_rand, verify_dalek};
+    use protocols::thresholdsig::frost::{self, SigningCommitments, SigningNonces};
+    use protocols::thresholdsig::test::tests::keygen_t_n_parties;
+    use protocols::thresholdsig::SharedKeys;
+    use protocols::{Context, SignatureMode, VerificationPolicy};
+    use Error;
+
+    fn scalar(hex: &str) -> Scalar<Ed25519> {
+        Scalar::from_bytes(&decode(hex).unwrap()).unwrap()
+    }
+
+    fn point(hex: &str) -> Point<Ed25519> {
+        Point::from_bytes(&decode(hex).unwrap()).unwrap()
+    }
+
+    // RFC 9591 appendix E.1, FROST(Ed25519, SHA-512)
+    #[test]
+    fn test_rfc9591_vector() {
+        let group_secret_key =
+            scalar("7b1c33d3f5291d85de664833beb1ad469f7fb6025a0ec78b3a790c6e13a98304");
+        let y = point("15d21ccd7ee42959562fc8aa63224c8851fb3ec85a3faf66040d380fb9738673");
+        assert_eq!(Point::generator() * &group_secret_key, y);
+        let message = decode("74657374").unwrap();
+
+        let participants: [(u16, &str, &str, &str); 2] = [
+            (
+                1,
+                "929dcc590407aae7d388761cddb0c0db6f5627aea8e217f4a033f2ec83d93509",
+                "0fd2e39e111cdc266f6c0f4d0fd45c947761f1f5d3cb583dfcb9bbaf8d4c9fec",
+                "69cd85f631d5f7f2721ed5e40519b1366f340a87c2f6856363dbdcda348a7501",
+            ),
+            (
+                3,
+                "d3cb090a075eb154e82fdb4b3cb507f110040905468bb9c46da8bdea643a9a02",
+                "86d64a260059e495d0fb4fcc17ea3da7452391baa494d4b00321098ed2a0062f",
+                "13e6b25afb2eba51716a9a7d44130c0dbae0004a9ef8d7b5550c8a0e07c61775",
+            ),
+        ];
+        let expected_nonces = [
+            (
+                "812d6104142944d5a55924de6d49940956206909f2acaeedecda2b726e630407",
+                "b1110165fc2334149750b28dd813a39244f315cff14d4e89e6142f262ed83301",
+                "b5aa8ab305882a6fc69cbee9327e5a45e54c08af61ae77cb8207be3d2ce13de3",
+                "67e98ab55aa310c3120418e5050c9cf76cf387cb20ac9e4b6fdb6f82a469f932",
+            ),
+            (
+                "c256de65476204095ebdc01bd11dc10e57b36bc96284595b8215222374f99c0e",
+                "243d71944d929063bc51205714ae3c2218bd3451d0214dfb5aeec2a90c35180d",
+                "cfbdb165bd8aad6eb79deb8d287bcc0ab6658ae57fdcc98ed12c0669e90aec91",
+                "7487bc41a6e712eea2f2af24681b58b1cf1da278ea11fe4e8b78398965f13552",
+            ),
+        ];
+        let expected_binding_factors = [
+            "f2cb9d7dd9beff688da6fcc83fa89046b3479417f47f55600b106760eb3b5603",
+            "b087686bf35a13f3dc78e780a34b0fe8a77fef1b9938c563f5573d71d8d7890f",
+        ];
+        let expected_sig_shares = [
+            "001719ab5a53ee1a12095cd088fd149702c0720ce5fd2f29dbecf24b7281b603",
+            "bd86125de990acc5e1f13781d8e32c03a9bbd4c53539bbc106058bfd14326007",
+        ];
+        let expected_sig = decode(
+            "36282629c383bb820a88b71cae937d41f2f2adfcc3d02e55507e2fb9e2dd3cbe\
+             bd9d2b0844e49ae0f3fa935161e1419aab7b47d21a37ebeae1f17d4987b3160b",
+        )
+        .unwrap();
+
+        let keys: Vec<_> = participants
+            .iter()
+            .map(|(_, share, _, _)| SharedKeys {
+                y: y.clone(),
+                x_i: scalar(share),
+                prefix: Scalar::zero(),
+            })
+            .collect();
+
+        // round one
+        let (nonces, commitments): (Vec<_>, Vec<_>) = participants
+            .iter()
+            .zip(keys.iter())
+            .zip(expected_nonces.iter())
+            .map(|(((index, _, hiding_rand, binding_rand), key), expected)| {
+                let hiding_rand: [u8; 32] = decode(hiding_rand).unwrap().try_into().unwrap();
+                let binding_rand: [u8; 32] = decode(binding_rand).unwrap().try_into().unwrap();
+                let hiding = frost::nonce_generate(&hiding_rand, &key.x_i);
+                let binding = frost::nonce_generate(&binding_rand, &key.x_i);
+                assert_eq!(hiding, scalar(expected.0));
+                assert_eq!(binding, scalar(expected.1));
+                let commitments = SigningCommitments {
+                    index: *index,
+                    hiding: Point::generator() * &hiding,
+                    binding: Point::generator() * &binding,
+                };
+                assert_eq!(commitments.hiding, point(expected.2));
+                assert_eq!(commitments.binding, point(expected.3));
+                (SigningNonces { hiding, binding }, commitments)
+            })
+            .unzip();
+
+        let binding_factors = frost::compute_binding_factors(&y, &commitments, &message);
+        for (binding_factor, expected) in binding_factors.iter().zip(expected_binding_factors) {
+            assert_eq!(binding_factor, &scalar(expected));
+        }
+
+        // round two
+        let shares: Vec<_> = nonces
+            .into_iter()
+            .zip(keys.iter())
+            .zip(participants.iter())
+            .map(|((nonces, key), (index, _, _, _))| {
+                frost::sign(
+                    &message,
+                    key,
+                    *index,
+                    nonces,
+                    &commitments,
+                    &SignatureMode::Pure,
+                )
+                .unwrap()
+            })
+            .collect();
+        for ((share, key), expected) in shares.iter().zip(keys.iter()).zip(expected_sig_shares) {
+            assert_eq!(share.z_i, scalar(expected));
+            let public_share = Point::generator() * &key.x_i;
+            frost::verify_signature_share(
+                share,
+                &public_share,
+                &message,
+                &y,
+                &commitments,
+                &SignatureMode::Pure,
+                VerificationPolicy::Strict,
+            )
+            .unwrap();
+        }
+
+        let sig =
+            frost::aggregate(&message, &y, &commitments, &shares, &SignatureMode::Pure).unwrap();
+        let mut sig_bytes = sig.R.to_bytes(true).to_vec();
+        sig_bytes.extend_from_slice(&sig.s.to_bytes());
+        assert_eq!(sig_bytes, expected_sig);
+        sig.verify(&message, &y).unwrap();
+    }
+
+    #[test]
+    fn test_frost_sign_verify_dalek() {
+        let mut rng = deterministic_fast_rand("test_frost_sign_verify_dalek", None);
+        let mut msg = [0u8; 33];
+        let n = 4u16;
+        let indices: Vec<_> = (1..=n).collect();
+        for t in 0..n {
+            let (_, shared_keys, y, vss_schemes) = keygen_t_n_parties(t, n, &indices, &mut rng);
+            for group in indices.iter().copied().combinations(usize::from(t + 1)) {
+                let msg_len = rng.next_u32() as usize % msg.len();
+                let msg = &mut msg[..msg_len];
+                rng.fill_bytes(msg);
+
+                let (nonces, commitments): (Vec<_>, Vec<_>) = group
+                    .iter()
+                    .map(|&index| {
+                        frost::commit_rng(&shared_keys[usize::from(index - 1)], index, &mut rng)
+                    })
+                    .unzip();
+                let shares: Vec<_> = nonces
+                    .into_iter()
+                    .zip(group.iter())
+                    .map(|(nonces, &index)| {
+                        frost::sign(
+                            msg,
+                            &shared_keys[usize::from(index - 1)],
+                            index,
+                            nonces,
+                            &commitments,
+                            &SignatureMode::Pure,
+                        )
+                        .unwrap()
+                    })
+                    .collect();
+                for share in &shares {
+                    let public_share = frost::public_share(&vss_schemes, share.index);
+                    frost::verify_signature_share(
+                        share,
+                        &public_share,
+                        msg,
+                        &y,
+                        &commitments,
+                        &SignatureMode::Pure,
+                        VerificationPolicy::Strict,
+                    )
+                    .unwrap();
+                }
+                let sig =
+                    frost::aggregate(msg, &y, &commitments, &shares, &SignatureMode::Pure).unwrap();
+                assert!(sig.verify(msg, &y).is_ok());
+                assert!(verify_dalek(&y, &sig, msg));
+            }
+        }
+    }
+
+    #[test]
+    fn test_frost_preprocessed_nonces_and_bad_share() {
+        let mut rng = deterministic_fast_rand("test_frost_preprocessed_nonces_and_bad_share", None);
+        let (t, n) = (1u16, 3u16);
+        let indices: Vec<_> = (1..=n).collect();
+        let (_, shared_keys, y, vss_schemes) = keygen_t_n_parties(t, n, &indices, &mut rng);
+        let group = [1u16, 3];
+        let message: [u8; 4] = [79, 77, 69, 82];
+
+        // each signer commits to several nonces ahead of time
+        let (mut nonces, preprocessed): (Vec<Vec<_>>, Vec<Vec<_>>) = group
+            .iter()
+            .map(|&index| frost::preprocess(&shared_keys[usize::from(index - 1)], index, 3))
+            .unzip();
+        for round in 0..3 {
+            let commitments: Vec<_> = preprocessed.iter().map(|c| c[round].clone()).collect();
+            let mut shares: Vec<_> = nonces
+                .iter_mut()
+                .zip(group.iter())
+                .map(|(nonces, &index)| {
+                    frost::sign(
+                        &message,
+                        &shared_keys[usize::from(index - 1)],
+                        index,
+                        nonces.remove(0),
+                        &commitments,
+                        &SignatureMode::Pure,
+                    )
+                    .unwrap()
+                })
+                .collect();
+            let sig = frost::aggregate(&message, &y, &commitments, &shares, &SignatureMode::Pure)
+                .unwrap();
+            assert!(sig.verify(&message, &y).is_ok());
+
+            // a corrupted share is caught and attributed before aggregation
+            shares[1].z_i = &shares[1].z_i + Scalar::from(1);
+            let bad: Vec<_> = shares
+                .iter()
+                .filter(|share| {
+                    let public_share = frost::public_share(&vss_schemes, share.index);
+                    frost::verify_signature_share(
+                        share,
+                        &public_share,
+                        &message,
+                        &y,
+                        &commitments,
+                        &SignatureMode::Pure,
+                        VerificationPolicy::Strict,
+                    )
+                    .is_err()
+                })
+                .map(|share| share.index)
+                .collect();
+            assert_eq!(bad, vec![3]);
+            assert_eq!(
+                frost::aggregate(&message, &y, &commitments, &shares, &SignatureMode::Pure).err(),
+                Some(Error::InvalidSig)
+            );
+        }
+
+        // signing without our own commitment in the list is rejected
+        let (my_nonces, _) = frost::commit_rng(&shared_keys[0], 1, &mut rng);
+        let (_, other_commitments) = frost::commit_rng(&shared_keys[2], 3, &mut rng);
+        assert!(frost::sign(
+            &message,
+            &shared_keys[0],
+            1,
+            my_nonces,
+            &[other_commitments],
+            &SignatureMode::Pure
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_frost_sign_with_modes() {
+        let mut rng = deterministic_fast_rand("test_frost_sign_with_modes", None);
+        let indices = [1u16, 2, 3];
+        let (_, shared_keys, y, vss_schemes) = keygen_t_n_parties(1, 3, &indices, &mut rng);
+        let data: [u8; 4] = [79, 77, 69, 82];
+        let prehash = SignatureMode::prehash(&data);
+        let ctx = SignatureMode::Context(Context::new(b"frost").unwrap());
+        let ph = SignatureMode::Prehash(Context::new(b"frost").unwrap());
+        let group = [1u16, 3];
+        for (message, mode) in [(&data[..], &ctx), (&prehash[..], &ph)] {
+            let (nonces, commitments): (Vec<_>, Vec<_>) = group
+                .iter()
+                .map(|&index| {
+                    frost::commit_rng(&shared_keys[usize::from(index - 1)], index, &mut rng)
+                })
+                .unzip();
+            let shares: Vec<_> = nonces
+                .into_iter()
+                .zip(group.iter())
+                .map(|(nonces, &index)| {
+                    let keys = &shared_keys[usize::from(index - 1)];
+                    frost::sign(message, keys, index, nonces, &commitments, mode).unwrap()
+                })
+                .collect();
+            for share in &shares {
+                let public_share = frost::public_share(&vss_schemes, share.index);
+                for (other_mode, valid) in [(mode, true), (&SignatureMode::Pure, false)] {
+                    let result = frost::verify_signature_share(
+                        share,
+                        &public_share,
+                        message,
+                        &y,
+                        &commitments,
+                        other_mode,
+                        VerificationPolicy::Strict,
+                    );
+                    assert_eq!(result.is_ok(), valid);
+                }
+            }
+            assert_eq!(
+                frost::aggregate(message, &y, &commitments, &shares, &SignatureMode::Pure).err(),
+                Some(Error::InvalidSig)
+            );
+            let sig = frost::aggregate(message, &y, &commitments, &shares, mode).unwrap();
+            assert!(sig
+                .verify_with_mode(message, &y, mode, VerificationPolicy::Strict)
+                .is_ok());
+        }
+    }
+
+    #[test]
+    fn test_serde_roundtrip() {
+        let mut rng = deterministic_fast_rand("frost_test_serde_roundtrip", None);
+        let indices = [1u16, 2];
+        let (_, shared_keys, y, _) = keygen_t_n_parties(1, 2, &indices, &mut rng);
+        let message: [u8; 4] = [79, 77, 69, 82];
+
+        let (nonces, commitments) = frost::commit(&shared_keys[0], 1);
+        let nonces = assert_serde_roundtrip(&nonces);
+        assert_eq!(assert_serde_roundtrip(&commitments), commitments);
+        let (other_nonces, other_commitments) = frost::commit(&shared_keys[1], 2);
+        let commitments = [commitments, other_commitments];
+
+        let share = frost::sign(
+            &message,
+            &shared_keys[0],
+            1,
+            nonces,
+            &commitments,
+            &SignatureMode::Pure,
+        )
+        .unwrap();
+        assert_eq!(assert_serde_roundtrip(&share), share);
+        let other_share = frost::sign(
+            &message,
+            &shared_keys[1],
+            2,
+            other_nonces,
+            &commitments,
+            &SignatureMode::Pure,
+        )
+        .unwrap();
+        let sig = frost::aggregate(
+            &message,
+            &y,
+            &commitments,
+            &[share, other_share],
+            &SignatureMode::Pure,
+        )
+        .unwrap();
+        assert!(sig.verify(&message, &y).is_ok());
+    }
+}
@@ -10,7 +10,9 @@
     version 3 of the License, or (at your option) any later version.
     @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-eddsa/blob/master/LICENSE>
 */
-use Error::{self, InvalidKey, InvalidSS};
+use Error::{
+    self, EmptyInput, InvalidDecom, InvalidPartyIndex, InvalidSS, WrongLength, WrongPartyCount,
+};
 
 use curv::arithmetic::traits::*;
 use curv::cryptographic_primitives::commitments::hash_commitment::HashCommitment;
@@ -19,46 +21,80 @@ use curv::cryptographic_primitives::hashing::DigestExt;
 use curv::cryptographic_primitives::secret_sharing::feldman_vss::{SecretShares, VerifiableSS};
 use curv::elliptic::curves::{Ed25519, Point, Scalar};
 use curv::BigInt;
-use protocols::{ExpandedKeyPair, Signature};
+use protocols::{check_distinct, ExpandedKeyPair, Signature, SignatureMode, REDACTED};
 use rand::{thread_rng, Rng};
 use sha2::{digest::Digest, Sha512};
+use std::fmt;
 
 const SECURITY: usize = 256;
 
 // u_i is private key and {u__i, prefix} are extended private key.
+#[derive(Debug, Serialize, Deserialize)]
 pub struct Keys {
     pub keypair: ExpandedKeyPair,
     pub party_index: u16,
 }
 
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
 pub struct KeyGenBroadcastMessage1 {
     com: BigInt,
 }
 
-#[derive(Debug)]
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
 pub struct Parameters {
     pub threshold: u16,   //t
     pub share_count: u16, //n
 }
-#[derive(Clone, Serialize, Deserialize)]
+#[derive(Serialize, Deserialize)]
 pub struct SharedKeys {
     pub y: Point<Ed25519>,
     pub x_i: Scalar<Ed25519>,
     prefix: Scalar<Ed25519>,
 }
 
+impl fmt::Debug for SharedKeys {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SharedKeys")
+            .field("y", &self.y)
+            .field("x_i", &REDACTED)
+            .field("prefix", &REDACTED)
+            .finish()
+    }
+}
+
+#[derive(Serialize, Deserialize)]
 pub struct EphemeralKey {
     pub r_i: Scalar<Ed25519>,
     pub R_i: Point<Ed25519>,
     pub party_index: u16,
 }
 
-#[derive(Clone, Serialize, Deserialize)]
+impl fmt::Debug for EphemeralKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("EphemeralKey")
+            .field("r_i", &REDACTED)
+            .field("R_i", &self.R_i)
+            .field("party_index", &self.party_index)
+            .finish()
+    }
+}
+
+#[derive(Serialize, Deserialize)]
 pub struct EphemeralSharedKeys {
     pub R: Point<Ed25519>,
     pub r_i: Scalar<Ed25519>,
 }
 
+impl fmt::Debug for EphemeralSharedKeys {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("EphemeralSharedKeys")
+            .field("R", &self.R)
+            .field("r_i", &REDACTED)
+            .finish()
+    }
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
 pub struct LocalSig {
     gamma_i: Scalar<Ed25519>,
     k: Scalar<Ed25519>,
@@ -103,23 +139,14 @@ impl Keys {
         parties: &[u16],
     ) -> Result<(VerifiableSS<Ed25519>, SecretShares<Ed25519>), Error> {
         // test length:
-        assert_eq!(blind_vec.len(), usize::from(params.share_count));
-        assert_eq!(bc1_vec.len(), usize::from(params.share_count));
-        assert_eq!(y_vec.len(), usize::from(params.share_count));
+        let share_count = usize::from(params.share_count);
+        check_length(share_count, blind_vec.len())?;
+        check_length(share_count, bc1_vec.len())?;
+        check_length(share_count, y_vec.len())?;
+        check_length(share_count, parties.len())?;
         // test decommitments
-        let correct_key_correct_decom_all = y_vec
-            .iter()
-            .zip(blind_vec.iter())
-            .zip(bc1_vec.iter())
-            .all(|((y, blind), comm)| {
-                HashCommitment::<Sha512>::create_commitment_with_user_defined_randomness(
-                    &y.y_coord().unwrap(),
-                    blind,
-                ) == comm.com
-            });
-        if !correct_key_correct_decom_all {
-            return Err(InvalidKey);
-        }
+        check_parties(parties)?;
+        check_decommitments(y_vec, blind_vec, bc1_vec, parties)?;
         Ok(VerifiableSS::share_at_indices(
             params.threshold,
             params.share_count,
@@ -128,38 +155,43 @@ impl Keys {
         ))
     }
 
+    // the input vectors are ordered as `parties`, the indices of the parties that sent them.
+    // on failure the error lists the indices of the parties with a bad share
     pub fn phase2_verify_vss_construct_keypair(
         &self,
         params: &Parameters,
         y_vec: &[Point<Ed25519>],
         secret_shares_vec: &[Scalar<Ed25519>],
         vss_scheme_vec: &[VerifiableSS<Ed25519>],
+        parties: &[u16],
         index: u16,
     ) -> Result<SharedKeys, Error> {
-        assert_eq!(y_vec.len(), usize::from(params.share_count));
-        assert_eq!(secret_shares_vec.len(), usize::from(params.share_count));
-        assert_eq!(vss_scheme_vec.len(), usize::from(params.share_count));
-
-        let correct_ss_verify = vss_scheme_vec
-            .iter()
-            .zip(secret_shares_vec.iter())
-            .zip(y_vec.iter())
-            .all(|((vss_scheme, secret_share), y)| {
-                vss_scheme.validate_share(secret_share, index).is_ok()
-                    && &vss_scheme.commitments[0] == y
-            });
-        if !correct_ss_verify {
-            return Err(InvalidSS);
+        let share_count = usize::from(params.share_count);
+        check_length(share_count, y_vec.len())?;
+        check_length(share_count, secret_shares_vec.len())?;
+        check_length(share_count, vss_scheme_vec.len())?;
+        check_length(share_count, parties.len())?;
+
+        let bad_parties = invalid_share_parties(
+            params,
+            vss_scheme_vec,
+            secret_shares_vec,
+            y_vec,
+            parties,
+            index,
+        );
+        if !bad_parties.is_empty() {
+            return Err(InvalidSS(bad_parties));
         }
-        let first_y = y_vec[0].clone();
-        let y = y_vec[1..].iter().fold(first_y, |acc, y| acc + y);
+        let y = y_vec.iter().sum();
         let x_i = secret_shares_vec
             .iter()
             .fold(Scalar::zero(), |acc, x| acc + x);
         Ok(SharedKeys {
             y,
             x_i,
-            prefix: self.keypair.expanded_private_key.prefix.clone(),
+            prefix: Scalar::from_bytes(&self.keypair.expanded_private_key.prefix)
+                .expect("prefix is the right length, so can't fail"),
         })
     }
 }
@@ -190,7 +222,7 @@ impl EphemeralKey {
         // here we deviate from the spec, by introducing  non-deterministic element (random number)
         // to the nonce
         let r_i = Sha512::new()
-            .chain_scalar(&keys.keypair.expanded_private_key.prefix)
+            .chain(keys.keypair.expanded_private_key.prefix)
             .chain(message)
             .chain(rng.gen::<[u8; 32]>())
             .result_scalar();
@@ -227,77 +259,57 @@ impl EphemeralKey {
         parties: &[u16],
     ) -> Result<(VerifiableSS<Ed25519>, SecretShares<Ed25519>), Error> {
         // test length:
-        assert!(
-            blind_vec.len() > usize::from(params.threshold)
-                && blind_vec.len() <= usize::from(params.share_count)
-        );
-        assert!(
-            bc1_vec.len() > usize::from(params.threshold)
-                && bc1_vec.len() <= usize::from(params.share_count)
-        );
-        assert!(
-            R_vec.len() > usize::from(params.threshold)
-                && R_vec.len() <= usize::from(params.share_count)
-        );
+        check_party_count(params.threshold, params.share_count, R_vec.len())?;
+        check_length(R_vec.len(), blind_vec.len())?;
+        check_length(R_vec.len(), bc1_vec.len())?;
+        check_length(R_vec.len(), parties.len())?;
         // test decommitments
-        let correct_key_correct_decom_all = R_vec
-            .iter()
-            .zip(blind_vec.iter())
-            .zip(bc1_vec.iter())
-            .all(|((R, blind), comm)| {
-                HashCommitment::<Sha512>::create_commitment_with_user_defined_randomness(
-                    &R.y_coord().unwrap(),
-                    blind,
-                ) == comm.com
-            });
-
-        if !correct_key_correct_decom_all {
-            return Err(InvalidKey);
-        }
+        check_parties(parties)?;
+        check_decommitments(R_vec, blind_vec, bc1_vec, parties)?;
 
+        // the ephemeral key is only shared among the signers
         Ok(VerifiableSS::share_at_indices(
             params.threshold,
-            params.share_count,
+            parties.len() as u16,
             &self.r_i,
             parties,
         ))
     }
 
+    // the input vectors are ordered as `parties`, the indices of the signers that sent them.
+    // on failure the error lists the indices of the signers with a bad share
     pub fn phase2_verify_vss_construct_keypair(
         &self,
         params: &Parameters,
         R_vec: &[Point<Ed25519>],
         secret_shares_vec: &[Scalar<Ed25519>],
         vss_scheme_vec: &[VerifiableSS<Ed25519>],
+        parties: &[u16],
         index: u16,
     ) -> Result<EphemeralSharedKeys, Error> {
-        assert!(
-            R_vec.len() > usize::from(params.threshold)
-                && R_vec.len() <= usize::from(params.share_count)
-        );
-        assert!(
-            secret_shares_vec.len() > usize::from(params.threshold)
-                && secret_shares_vec.len() <= usize::from(params.share_count)
-        );
-        assert!(
-            vss_scheme_vec.len() > usize::from(params.threshold)
-                && vss_scheme_vec.len() <= usize::from(params.share_count)
+        check_party_count(params.threshold, params.share_count, R_vec.len())?;
+        check_length(R_vec.len(), secret_shares_vec.len())?;
+        check_length(R_vec.len(), vss_scheme_vec.len())?;
+        check_length(R_vec.len(), parties.len())?;
+
+        // the ephemeral VSS are only shared among the signers
+        let eph_params = Parameters {
+            threshold: params.threshold,
+            share_count: R_vec.len() as u16,
+        };
+        let bad_parties = invalid_share_parties(
+            &eph_params,
+            vss_scheme_vec,
+            secret_shares_vec,
+            R_vec,
+            parties,
+            index,
         );
-
-        let correct_ss_verify = vss_scheme_vec
-            .iter()
-            .zip(secret_shares_vec.iter())
-            .zip(R_vec.iter())
-            .all(|((vss_scheme, secret_share), R)| {
-                vss_scheme.validate_share(secret_share, index).is_ok()
-                    && &vss_scheme.commitments[0] == R
-            });
-        if !correct_ss_verify {
-            return Err(InvalidSS);
+        if !bad_parties.is_empty() {
+            return Err(InvalidSS(bad_parties));
         }
 
-        let R_first = R_vec[0].clone();
-        let R = R_vec[1..].iter().fold(R_first, |acc, x| acc + x);
+        let R = R_vec.iter().sum();
         let r_i = secret_shares_vec
             .iter()
             .fold(Scalar::zero(), |acc, x| acc + x);
@@ -310,11 +322,12 @@ impl LocalSig {
         message: &[u8],
         local_ephemaral_key: &EphemeralSharedKeys,
         local_private_key: &SharedKeys,
+        mode: &SignatureMode,
     ) -> LocalSig {
         let r_i = local_ephemaral_key.r_i.clone();
         let s_i = local_private_key.x_i.clone();
 
-        let k = Signature::k(&local_ephemaral_key.R, &local_private_key.y, message);
+        let k = Signature::k(&local_ephemaral_key.R, &local_private_key.y, message, mode);
         let gamma_i = r_i + &k * s_i;
 
         LocalSig { gamma_i, k }
@@ -329,15 +342,47 @@ impl LocalSig {
         vss_ephemeral_keys: &[VerifiableSS<Ed25519>],
     ) -> Result<VerifiableSS<Ed25519>, Error> {
         //parties_index_vec is a vector with indices of the parties that are participating and provided gamma_i for this step
+        //on failure the error lists the entries of parties_index_vec whose gamma_i did not verify,
+        //or whose ephemeral VSS is malformed
+        let params = &vss_private_keys.first().ok_or(EmptyInput)?.parameters;
+        let commitment_count = usize::from(params.threshold) + 1;
+        // the key generation VSS schemes are part of the local key, not sent by a signer
+        if let Some(vss) = vss_private_keys
+            .iter()
+            .find(|vss| vss.commitments.len() != commitment_count)
+        {
+            return Err(WrongLength {
+                expected: commitment_count,
+                received: vss.commitments.len(),
+            });
+        }
         // test that enough parties are in this round
-        assert!(parties_index_vec.len() > usize::from(vss_private_keys[0].parameters.threshold));
+        check_party_count(
+            params.threshold,
+            params.share_count,
+            parties_index_vec.len(),
+        )?;
+        check_length(parties_index_vec.len(), gamma_vec.len())?;
+        check_length(parties_index_vec.len(), vss_ephemeral_keys.len())?;
+        let bad_parties: Vec<u16> = vss_ephemeral_keys
+            .iter()
+            .zip(parties_index_vec.iter())
+            .filter(|(vss, _)| {
+                vss.commitments.len() != commitment_count
+                    || vss.parameters.threshold != params.threshold
+            })
+            .map(|(_, &party_index)| party_index)
+            .collect();
+        if !bad_parties.is_empty() {
+            return Err(InvalidSS(bad_parties));
+        }
 
         // Vec of joint commitments:
         // n' = num of signers, n - num of parties in keygen
         // [com0_eph_0,... ,com0_eph_n', e*com0_kg_0, ..., e*com0_kg_n ;
         // ...  ;
         // comt_eph_0,... ,comt_eph_n', e*comt_kg_0, ..., e*comt_kg_n ]
-        let comm_vec: Vec<_> = (0..usize::from(vss_private_keys[0].parameters.threshold) + 1)
+        let comm_vec: Vec<_> = (0..commitment_count)
             .map(|i| {
                 let mut key_gen_comm_i_vec: Vec<_> = (0..vss_private_keys.len())
                     .map(|j| &vss_private_keys[j].commitments[i] * &gamma_vec[i].k)
@@ -352,43 +397,145 @@ impl LocalSig {
             .collect();
 
         let vss_sum = VerifiableSS {
-            parameters: vss_ephemeral_keys[0].parameters.clone(),
+            parameters: params.clone(),
             commitments: comm_vec,
         };
 
         let g = Point::generator();
 
-        let correct_ss_verify =
-            gamma_vec
-                .iter()
-                .zip(parties_index_vec.iter())
-                .all(|(gamma, &party_index)| {
-                    let gamma_i_g = &gamma.gamma_i * g;
-                    vss_sum
-                        .validate_share_public(&gamma_i_g, party_index + 1)
-                        .is_ok()
-                });
-
-        match correct_ss_verify {
-            true => Ok(vss_sum),
-            false => Err(InvalidSS),
+        let bad_parties: Vec<u16> = gamma_vec
+            .iter()
+            .zip(parties_index_vec.iter())
+            .filter(|(gamma, &party_index)| {
+                let gamma_i_g = &gamma.gamma_i * g;
+                match party_index.checked_add(1) {
+                    Some(point) => vss_sum.validate_share_public(&gamma_i_g, point).is_err(),
+                    None => true,
+                }
+            })
+            .map(|(_, &party_index)| party_index)
+            .collect();
+
+        if bad_parties.is_empty() {
+            Ok(vss_sum)
+        } else {
+            Err(InvalidSS(bad_parties))
         }
     }
 }
 
+// Returns the indices of the parties whose VSS is not for `params`, whose share does not match
+// their VSS commitments, or whose VSS does not commit to the public value they broadcast.
+fn invalid_share_parties(
+    params: &Parameters,
+    vss_scheme_vec: &[VerifiableSS<Ed25519>],
+    secret_shares_vec: &[Scalar<Ed25519>],
+    public_vec: &[Point<Ed25519>],
+    parties: &[u16],
+    index: u16,
+) -> Vec<u16> {
+    vss_scheme_vec
+        .iter()
+        .zip(secret_shares_vec.iter())
+        .zip(public_vec.iter())
+        .zip(parties.iter())
+        .filter(|(((vss_scheme, secret_share), public), _)| {
+            vss_scheme.parameters.threshold != params.threshold
+                || vss_scheme.parameters.share_count != params.share_count
+                || vss_scheme.commitments.len() != usize::from(params.threshold) + 1
+                || vss_scheme.validate_share(secret_share, index).is_err()
+                || &vss_scheme.commitments[0] != *public
+        })
+        .map(|(_, &party)| party)
+        .collect()
+}
+
 pub fn generate(
     vss_sum_local_sigs: &VerifiableSS<Ed25519>,
     local_sig_vec: &[LocalSig],
     parties_index_vec: &[u16],
     R: Point<Ed25519>,
-) -> Signature {
-    let reconstruct_limit = usize::from(vss_sum_local_sigs.parameters.threshold) + 1;
+) -> Result<Signature, Error> {
+    let params = &vss_sum_local_sigs.parameters;
+    check_party_count(
+        params.threshold,
+        params.share_count,
+        parties_index_vec.len(),
+    )?;
+    check_length(parties_index_vec.len(), local_sig_vec.len())?;
+    // the indices here are counted from 0, the last one has no share
+    check_distinct(parties_index_vec)?;
+    if parties_index_vec.contains(&u16::MAX) {
+        return Err(InvalidPartyIndex(u16::MAX));
+    }
+    let reconstruct_limit = usize::from(params.threshold) + 1;
     let gamma_vec: Vec<_> = local_sig_vec[..reconstruct_limit]
         .iter()
         .map(|sig| sig.gamma_i.clone())
         .collect();
     let s = vss_sum_local_sigs.reconstruct(&parties_index_vec[0..reconstruct_limit], &gamma_vec);
-    Signature { s, R }
+    Ok(Signature { s, R })
+}
+
+fn check_length(expected: usize, received: usize) -> Result<(), Error> {
+    if received != expected {
+        return Err(WrongLength { expected, received });
+    }
+    Ok(())
+}
+
+// shares are dealt at the party indices, which start from 1 as index 0 would be the secret itself,
+// and each party may only appear once
+fn check_parties(parties: &[u16]) -> Result<(), Error> {
+    if parties.contains(&0) {
+        return Err(InvalidPartyIndex(0));
+    }
+    check_distinct(parties)
+}
+
+// more than `threshold` and at most `share_count` parties must take part
+fn check_party_count(threshold: u16, share_count: u16, received: usize) -> Result<(), Error> {
+    if received <= usize::from(threshold) || received > usize::from(share_count) {
+        return Err(WrongPartyCount {
+            threshold,
+            share_count,
+            received,
+        });
+    }
+    Ok(())
+}
+
+// the input vectors are ordered as `parties`, on failure the error lists the indices of the
+// parties whose public value doesn't open their commitment
+fn check_decommitments(
+    public_vec: &[Point<Ed25519>],
+    blind_vec: &[BigInt],
+    bc1_vec: &[KeyGenBroadcastMessage1],
+    parties: &[u16],
+) -> Result<(), Error> {
+    let bad_parties: Vec<u16> = public_vec
+        .iter()
+        .zip(blind_vec.iter())
+        .zip(bc1_vec.iter())
+        .zip(parties.iter())
+        .filter(|(((public, blind), comm), _)| {
+            HashCommitment::<Sha512>::create_commitment_with_user_defined_randomness(
+                &public.y_coord().unwrap(),
+                blind,
+            ) != comm.com
+        })
+        .map(|(_, &party)| party)
+        .collect();
+    if !bad_parties.is_empty() {
+        return Err(InvalidDecom(bad_parties));
+    }
+    Ok(())
 }
 
+pub mod dealer;
+pub mod frost;
+pub mod recovery;
+pub mod refresh;
+pub mod reshare;
+pub mod state_machine;
 mod test;
@@ -0,0 +1,184 @@
+/*
+    Multisig eddsa
+    Copyright 2018 by Kzen Networks
+    This file is part of multi-party-eddsa library
+    (https://github.com/KZen-networks/multi-party-eddsa)
+    Multisig Schnorr is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-eddsa/blob/master/LICENSE>
+*/
+
+//! Lost-share recovery
+//!
+//! See "Repairable Threshold Secret Sharing Schemes" (Laing, Stinson 2017), section 3.
+//! t+1 helpers each compute `delta_i = lambda_i(r) * x_i`, the Lagrange term of the lost share at
+//! index r, and split it into random additive parts, one per helper. Each helper only ever sees
+//! masked sums, and the lost party adds up the sums it receives to get `x_r`. Every part is
+//! committed to, so bad parts are attributed and the result is checked against the keygen VSS.
+
+use Error::{self, DuplicateParty, InvalidKey, InvalidPartyIndex, InvalidSS, UnknownParty};
+
+use curv::cryptographic_primitives::secret_sharing::feldman_vss::VerifiableSS;
+use curv::cryptographic_primitives::secret_sharing::Polynomial;
+use curv::elliptic::curves::{Ed25519, Point, Scalar};
+use protocols::thresholdsig::refresh::combine_vss_schemes;
+use protocols::thresholdsig::{check_length, check_parties, SharedKeys};
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct RecoveryBroadcastMessage1 {
+    // commitments to the parts of delta_i, ordered as the helpers
+    pub delta_commitments: Vec<Point<Ed25519>>,
+}
+
+// helper round 1: split our term of the lost share, send deltas[j] to helpers[j] and broadcast the commitments
+pub fn phase1_split(
+    keys: &SharedKeys,
+    index: u16,
+    helpers: &[u16],
+    lost_index: u16,
+) -> Result<(RecoveryBroadcastMessage1, Vec<Scalar<Ed25519>>), Error> {
+    check_helpers(helpers, lost_index)?;
+    let lambda_i = lagrange_coefficient(helpers, index, lost_index).ok_or(UnknownParty(index))?;
+    let delta_i = lambda_i * &keys.x_i;
+
+    let mut deltas: Vec<Scalar<Ed25519>> = (1..helpers.len()).map(|_| Scalar::random()).collect();
+    let last = deltas.iter().fold(delta_i, |acc, delta| acc - delta);
+    deltas.push(last);
+
+    let delta_commitments = deltas.iter().map(|d| Point::generator() * d).collect();
+    Ok((RecoveryBroadcastMessage1 { delta_commitments }, deltas))
+}
+
+// helper round 2: verify the parts received from all helpers and send their sum to the lost party.
+// `deltas_vec` and `bc1_vec` are ordered as `helpers`.
+// on failure the error lists the indices of the helpers with a bad part
+pub fn phase2_verify_combine(
+    helpers: &[u16],
+    lost_index: u16,
+    deltas_vec: &[Scalar<Ed25519>],
+    bc1_vec: &[RecoveryBroadcastMessage1],
+    vss_scheme_vec: &[VerifiableSS<Ed25519>],
+    index: u16,
+) -> Result<Scalar<Ed25519>, Error> {
+    check_length(helpers.len(), deltas_vec.len())?;
+    check_length(helpers.len(), bc1_vec.len())?;
+    check_helpers(helpers, lost_index)?;
+    let position = helpers
+        .iter()
+        .position(|&i| i == index)
+        .ok_or(UnknownParty(index))?;
+
+    let vss_scheme = combine_vss_schemes(vss_scheme_vec)?;
+    let bad_parties: Vec<u16> = deltas_vec
+        .iter()
+        .zip(bc1_vec.iter())
+        .zip(helpers.iter())
+        .filter(|((delta, bc1), &helper)| {
+            !valid_commitments(bc1, helpers, helper, lost_index, &vss_scheme)
+                || Point::generator() * *delta != bc1.delta_commitments[position]
+        })
+        .map(|(_, &helper)| helper)
+        .collect();
+    if !bad_parties.is_empty() {
+        return Err(InvalidSS(bad_parties));
+    }
+    Ok(deltas_vec.iter().sum())
+}
+
+// lost party: combine the sums sent by the helpers into the lost share and check it against the VSS.
+// `sigma_vec` and `bc1_vec` are ordered as `helpers`.
+// on failure the error lists the indices of the helpers that misbehaved
+pub fn phase3_construct_keypair(
+    helpers: &[u16],
+    sigma_vec: &[Scalar<Ed25519>],
+    bc1_vec: &[RecoveryBroadcastMessage1],
+    vss_scheme_vec: &[VerifiableSS<Ed25519>],
+    lost_index: u16,
+) -> Result<SharedKeys, Error> {
+    check_length(helpers.len(), sigma_vec.len())?;
+    check_length(helpers.len(), bc1_vec.len())?;
+
+    let vss_scheme = combine_vss_schemes(vss_scheme_vec)?;
+    let bad_commitments = bc1_vec
+        .iter()
+        .zip(helpers.iter())
+        .filter(|(bc1, &helper)| !valid_commitments(bc1, helpers, helper, lost_index, &vss_scheme))
+        .map(|(_, &helper)| helper);
+    let bad_sums = sigma_vec
+        .iter()
+        .zip(helpers.iter())
+        .enumerate()
+        .filter(|(j, (sigma, _))| {
+            let expected: Point<Ed25519> = bc1_vec
+                .iter()
+                .filter_map(|bc1| bc1.delta_commitments.get(*j))
+                .sum();
+            Point::generator() * *sigma != expected
+        })
+        .map(|(_, (_, &helper))| helper);
+    let mut bad_parties: Vec<u16> = bad_commitments.chain(bad_sums).collect();
+    bad_parties.sort_unstable();
+    bad_parties.dedup();
+    if !bad_parties.is_empty() {
+        return Err(InvalidSS(bad_parties));
+    }
+
+    let x_i: Scalar<Ed25519> = sigma_vec.iter().sum();
+    let y = vss_scheme.commitments.first().ok_or(InvalidKey)?;
+    if vss_scheme.validate_share(&x_i, lost_index).is_err() {
+        return Err(InvalidKey);
+    }
+    Ok(SharedKeys {
+        y: y.clone(),
+        x_i,
+        prefix: Scalar::random(),
+    })
+}
+
+// the parts of helper `index` must add up to lambda_i(r) times its public share
+fn valid_commitments(
+    bc1: &RecoveryBroadcastMessage1,
+    helpers: &[u16],
+    index: u16,
+    lost_index: u16,
+    vss_scheme: &VerifiableSS<Ed25519>,
+) -> bool {
+    let lambda_i = match lagrange_coefficient(helpers, index, lost_index) {
+        Some(lambda_i) => lambda_i,
+        None => return false,
+    };
+    let sum: Point<Ed25519> = bc1.delta_commitments.iter().sum();
+    bc1.delta_commitments.len() == helpers.len()
+        && sum == vss_scheme.get_point_commitment(index) * lambda_i
+}
+
+// the helpers are distinct parties, other than the lost one
+fn check_helpers(helpers: &[u16], lost_index: u16) -> Result<(), Error> {
+    check_parties(helpers)?;
+    if lost_index == 0 {
+        return Err(InvalidPartyIndex(0));
+    }
+    if helpers.contains(&lost_index) {
+        return Err(DuplicateParty(lost_index));
+    }
+    Ok(())
+}
+
+// Lagrange coefficient of `index` in `helpers`, evaluated at the lost party's index
+fn lagrange_coefficient(helpers: &[u16], index: u16, lost_index: u16) -> Option<Scalar<Ed25519>> {
+    check_parties(helpers).ok()?;
+    if lost_index == 0 || helpers.contains(&lost_index) {
+        return None;
+    }
+    let position = helpers.iter().position(|&i| i == index)?;
+    let xs: Vec<Scalar<Ed25519>> = helpers.iter().map(|&i| Scalar::from(i)).collect();
+    Some(Polynomial::lagrange_basis(
+        &Scalar::from(lost_index),
+        position as u16,
+        &xs,
+    ))
+}
+
+mod test;
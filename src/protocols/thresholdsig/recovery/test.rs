@@ -0,0 +1,158 @@
+#![allow(non_snake_case)]
+/*
+    Multisig eddsa
+    Copyright 2018 by Kzen Networks
+    This file is part of multi-party-eddsa library
+    (https://github.com/KZen-networks/multi-party-eddsa)
+    Multisig Schnorr is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-eddsa/blob/master/LICENSE>
+*/
+#[cfg(test)]
+mod tests {
+    use curv::elliptic::curves::Scalar;
+
+    use protocols::tests::deterministic_fast_rand;
+    use protocols::thresholdsig::recovery;
+    use protocols::thresholdsig::test::tests::keygen_t_n_parties;
+    use Error;
+
+    #[test]
+    fn test_recover_lost_share() {
+        let mut rng = deterministic_fast_rand("test_recover_lost_share", None);
+        let (t, n) = (2u16, 5u16);
+        let parties: Vec<_> = (1..=n).collect();
+        let (_, shared_keys, y, vss_schemes) = keygen_t_n_parties(t, n, &parties, &mut rng);
+
+        // party 2 lost its keys, parties 1, 4 and 5 help
+        let lost_index = 2u16;
+        let helpers = [1u16, 4, 5];
+        let (bc1_vec, deltas): (Vec<_>, Vec<_>) = helpers
+            .iter()
+            .map(|&index| {
+                recovery::phase1_split(
+                    &shared_keys[usize::from(index - 1)],
+                    index,
+                    &helpers,
+                    lost_index,
+                )
+                .unwrap()
+            })
+            .unzip();
+
+        let sigma_vec: Vec<_> = helpers
+            .iter()
+            .enumerate()
+            .map(|(j, &index)| {
+                let received: Vec<_> = deltas.iter().map(|d| d[j].clone()).collect();
+                recovery::phase2_verify_combine(
+                    &helpers,
+                    lost_index,
+                    &received,
+                    &bc1_vec,
+                    &vss_schemes,
+                    index,
+                )
+                .unwrap()
+            })
+            .collect();
+        // no helper's sum is the lost share itself
+        let lost = &shared_keys[usize::from(lost_index - 1)];
+        assert!(sigma_vec.iter().all(|sigma| sigma != &lost.x_i));
+
+        let recovered = recovery::phase3_construct_keypair(
+            &helpers,
+            &sigma_vec,
+            &bc1_vec,
+            &vss_schemes,
+            lost_index,
+        )
+        .unwrap();
+        assert_eq!(recovered.x_i, lost.x_i);
+        assert_eq!(recovered.y, y);
+
+        // the helpers are distinct parties other than the lost one, and include the caller
+        let keys = &shared_keys[0];
+        for (helpers, index, error) in [
+            (vec![1u16, 2, 4], 1, Error::DuplicateParty(2)),
+            (vec![1, 4, 4], 1, Error::DuplicateParty(4)),
+            (vec![0, 1, 4], 1, Error::InvalidPartyIndex(0)),
+            (vec![3, 4, 5], 1, Error::UnknownParty(1)),
+        ] {
+            assert_eq!(
+                recovery::phase1_split(keys, index, &helpers, lost_index).err(),
+                Some(error)
+            );
+        }
+    }
+
+    #[test]
+    fn test_recover_lost_share_identifies_bad_helper() {
+        let mut rng =
+            deterministic_fast_rand("test_recover_lost_share_identifies_bad_helper", None);
+        let (t, n) = (1u16, 3u16);
+        let parties: Vec<_> = (1..=n).collect();
+        let (_, shared_keys, _, vss_schemes) = keygen_t_n_parties(t, n, &parties, &mut rng);
+
+        let lost_index = 3u16;
+        let helpers = [1u16, 2];
+        let (bc1_vec, deltas): (Vec<_>, Vec<_>) = helpers
+            .iter()
+            .map(|&index| {
+                recovery::phase1_split(
+                    &shared_keys[usize::from(index - 1)],
+                    index,
+                    &helpers,
+                    lost_index,
+                )
+                .unwrap()
+            })
+            .unzip();
+
+        // helper 1 sends a part that does not match its commitment to helper 2
+        let mut received: Vec<_> = deltas.iter().map(|d| d[1].clone()).collect();
+        received[0] = &received[0] + Scalar::from(1);
+        let err = recovery::phase2_verify_combine(
+            &helpers,
+            lost_index,
+            &received,
+            &bc1_vec,
+            &vss_schemes,
+            helpers[1],
+        )
+        .err()
+        .unwrap();
+        assert_eq!(err, Error::InvalidSS(vec![1]));
+
+        // helper 2 sends a wrong sum to the lost party
+        let mut sigma_vec: Vec<_> = helpers
+            .iter()
+            .enumerate()
+            .map(|(j, &index)| {
+                let received: Vec<_> = deltas.iter().map(|d| d[j].clone()).collect();
+                recovery::phase2_verify_combine(
+                    &helpers,
+                    lost_index,
+                    &received,
+                    &bc1_vec,
+                    &vss_schemes,
+                    index,
+                )
+                .unwrap()
+            })
+            .collect();
+        sigma_vec[1] = Scalar::random();
+        let err = recovery::phase3_construct_keypair(
+            &helpers,
+            &sigma_vec,
+            &bc1_vec,
+            &vss_schemes,
+            lost_index,
+        )
+        .err()
+        .unwrap();
+        assert_eq!(err, Error::InvalidSS(vec![2]));
+    }
+}
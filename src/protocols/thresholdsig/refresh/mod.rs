@@ -0,0 +1,106 @@
+/*
+    Multisig eddsa
+    Copyright 2018 by Kzen Networks
+    This file is part of multi-party-eddsa library
+    (https://github.com/KZen-networks/multi-party-eddsa)
+    Multisig Schnorr is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-eddsa/blob/master/LICENSE>
+*/
+
+//! Proactive share refresh
+//!
+//! See "Proactive Secret Sharing Or: How to Cope With Perpetual Leakage" (Herzberg et al. 1995).
+//! Every party deals a verifiable sharing of zero to all parties, and adds the shares it received
+//! to its own `x_i`. The aggregate public key `y` stays the same, while shares of different epochs
+//! no longer lie on the same polynomial and cannot be combined.
+
+use Error::{self, EmptyInput, InvalidSS};
+
+use curv::cryptographic_primitives::secret_sharing::feldman_vss::{SecretShares, VerifiableSS};
+use curv::elliptic::curves::{Ed25519, Point, Scalar};
+use protocols::thresholdsig::{check_length, check_parties, Parameters, SharedKeys};
+
+// every party runs this and sends secret_shares[j] to the party at parties[j], and broadcasts the VSS
+pub fn phase1_distribute(
+    params: &Parameters,
+    parties: &[u16],
+) -> Result<(VerifiableSS<Ed25519>, SecretShares<Ed25519>), Error> {
+    check_length(usize::from(params.share_count), parties.len())?;
+    check_parties(parties)?;
+    Ok(VerifiableSS::share_at_indices(
+        params.threshold,
+        params.share_count,
+        &Scalar::zero(),
+        parties,
+    ))
+}
+
+// verifies the zero sharings received from all parties and returns the refreshed keys, together
+// with the VSS of the new shares to be used in place of the previous epoch's `key_gen_vss_vec`.
+// the input vectors are ordered as `parties`, on failure the error lists the indices of the
+// parties with a bad share
+pub fn phase2_verify_vss_refresh_keys(
+    keys: &SharedKeys,
+    params: &Parameters,
+    secret_shares_vec: &[Scalar<Ed25519>],
+    vss_scheme_vec: &[VerifiableSS<Ed25519>],
+    key_gen_vss_vec: &[VerifiableSS<Ed25519>],
+    parties: &[u16],
+    index: u16,
+) -> Result<(SharedKeys, VerifiableSS<Ed25519>), Error> {
+    check_length(usize::from(params.share_count), secret_shares_vec.len())?;
+    check_length(usize::from(params.share_count), vss_scheme_vec.len())?;
+    check_length(usize::from(params.share_count), parties.len())?;
+
+    let bad_parties: Vec<u16> = vss_scheme_vec
+        .iter()
+        .zip(secret_shares_vec.iter())
+        .zip(parties.iter())
+        .filter(|((vss_scheme, secret_share), _)| {
+            vss_scheme.parameters.threshold != params.threshold
+                || vss_scheme.commitments.len() != usize::from(params.threshold) + 1
+                || !vss_scheme.commitments[0].is_zero()
+                || vss_scheme.validate_share(secret_share, index).is_err()
+        })
+        .map(|(_, &party)| party)
+        .collect();
+    if !bad_parties.is_empty() {
+        return Err(InvalidSS(bad_parties));
+    }
+
+    let x_i = secret_shares_vec
+        .iter()
+        .fold(keys.x_i.clone(), |acc, x| acc + x);
+    let vss_scheme = combine_vss_schemes(key_gen_vss_vec.iter().chain(vss_scheme_vec.iter()))?;
+    Ok((
+        SharedKeys {
+            y: keys.y.clone(),
+            x_i,
+            prefix: keys.prefix.clone(),
+        },
+        vss_scheme,
+    ))
+}
+
+// adds up the commitments of several VSS schemes of the same threshold, the result commits to the
+// polynomial that is the sum of their polynomials.
+pub(crate) fn combine_vss_schemes<'a>(
+    vss_schemes: impl IntoIterator<Item = &'a VerifiableSS<Ed25519>>,
+) -> Result<VerifiableSS<Ed25519>, Error> {
+    let mut vss_schemes = vss_schemes.into_iter();
+    let first = vss_schemes.next().ok_or(EmptyInput)?.clone();
+    Ok(vss_schemes.fold(first, |mut acc, vss_scheme| {
+        acc.commitments = acc
+            .commitments
+            .iter()
+            .zip(vss_scheme.commitments.iter())
+            .map(|(a, b)| a + b)
+            .collect::<Vec<Point<Ed25519>>>();
+        acc
+    }))
+}
+
+mod test;
@@ -0,0 +1,185 @@
+#![allow(non_snake_case)]
+/*
+    Multisig eddsa
+    Copyright 2018 by Kzen Networks
+    This file is part of multi-party-eddsa library
+    (https://github.com/KZen-networks/multi-party-eddsa)
+    Multisig Schnorr is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-eddsa/blob/master/LICENSE>
+*/
+#[cfg(test)]
+mod tests {
+    use curv::cryptographic_primitives::secret_sharing::feldman_vss::VerifiableSS;
+    use curv::elliptic::curves::{Ed25519, Point, Scalar};
+
+    use protocols::tests::{deterministic_fast_rand, verify_dalek};
+    use protocols::thresholdsig::test::tests::{eph_keygen_t_n_parties, keygen_t_n_parties};
+    use protocols::thresholdsig::{self, refresh, LocalSig, Parameters, SharedKeys};
+    use protocols::SignatureMode;
+    use Error;
+
+    fn reconstruct(indices: &[u16], shares: &[&SharedKeys]) -> Point<Ed25519> {
+        let points: Vec<_> = indices.iter().map(|&i| Scalar::from(i)).collect();
+        let values: Vec<_> = shares.iter().map(|keys| keys.x_i.clone()).collect();
+        Point::generator()
+            * VerifiableSS::<Ed25519>::lagrange_interpolation_at_zero(&points, &values)
+    }
+
+    fn refresh_all(
+        params: &Parameters,
+        parties: &[u16],
+        shared_keys: &[SharedKeys],
+        key_gen_vss_vec: &[VerifiableSS<Ed25519>],
+    ) -> (Vec<SharedKeys>, Vec<VerifiableSS<Ed25519>>) {
+        let (vss_schemes, secret_shares): (Vec<_>, Vec<_>) = parties
+            .iter()
+            .map(|_| refresh::phase1_distribute(params, parties).unwrap())
+            .unzip();
+        shared_keys
+            .iter()
+            .zip(parties.iter())
+            .enumerate()
+            .map(|(i, (keys, &index))| {
+                let received: Vec<_> = secret_shares.iter().map(|s| s[i].clone()).collect();
+                refresh::phase2_verify_vss_refresh_keys(
+                    keys,
+                    params,
+                    &received,
+                    &vss_schemes,
+                    key_gen_vss_vec,
+                    parties,
+                    index,
+                )
+                .unwrap()
+            })
+            .unzip()
+    }
+
+    #[test]
+    fn test_refresh_keeps_public_key_and_changes_shares() {
+        let mut rng =
+            deterministic_fast_rand("test_refresh_keeps_public_key_and_changes_shares", None);
+        let (t, n) = (2u16, 4u16);
+        let params = Parameters {
+            threshold: t,
+            share_count: n,
+        };
+        let parties: Vec<_> = (1..=n).collect();
+        let (keys_vec, old_shared_keys, y, key_gen_vss_vec) =
+            keygen_t_n_parties(t, n, &parties, &mut rng);
+
+        let (new_shared_keys, new_vss_vec) =
+            refresh_all(&params, &parties, &old_shared_keys, &key_gen_vss_vec);
+
+        // all parties agree on the new VSS, and every share changed while y did not
+        assert!(new_vss_vec.iter().all(|vss| vss == &new_vss_vec[0]));
+        assert_eq!(new_vss_vec[0].commitments[0], y);
+        for ((old, new), &index) in old_shared_keys
+            .iter()
+            .zip(new_shared_keys.iter())
+            .zip(parties.iter())
+        {
+            assert_eq!(old.y, new.y);
+            assert_ne!(old.x_i, new.x_i);
+            assert!(new_vss_vec[0].validate_share(&new.x_i, index).is_ok());
+        }
+
+        // t+1 shares from the same epoch reconstruct the key, mixing epochs does not
+        let group = [1u16, 2, 4];
+        let old: Vec<_> = group
+            .iter()
+            .map(|&i| &old_shared_keys[usize::from(i - 1)])
+            .collect();
+        let new: Vec<_> = group
+            .iter()
+            .map(|&i| &new_shared_keys[usize::from(i - 1)])
+            .collect();
+        assert_eq!(reconstruct(&group, &old), y);
+        assert_eq!(reconstruct(&group, &new), y);
+        let mixed = vec![old[0], new[1], new[2]];
+        assert_ne!(reconstruct(&group, &mixed), y);
+
+        // the refreshed shares can sign with the existing protocol
+        let message: [u8; 4] = [79, 77, 69, 82];
+        let parties_index_vec: Vec<_> = group.iter().map(|i| i - 1).collect();
+        let (eph_shared_keys_vec, R, eph_vss_vec) =
+            eph_keygen_t_n_parties(t, t + 1, &group, &keys_vec, &message, &mut rng);
+        let local_sig_vec: Vec<_> = eph_shared_keys_vec
+            .iter()
+            .zip(new.iter())
+            .map(|(eph_keys, keys)| {
+                LocalSig::compute(&message, eph_keys, keys, &SignatureMode::Pure)
+            })
+            .collect();
+        let vss_sum_local_sigs = LocalSig::verify_local_sigs(
+            &local_sig_vec,
+            &parties_index_vec,
+            &new_vss_vec[..1],
+            &eph_vss_vec,
+        )
+        .unwrap();
+        let sig =
+            thresholdsig::generate(&vss_sum_local_sigs, &local_sig_vec, &parties_index_vec, R)
+                .unwrap();
+        assert!(verify_dalek(&y, &sig, &message));
+
+        // a signer still holding its old share is caught by the new commitments
+        let (eph_shared_keys_vec, _, eph_vss_vec) =
+            eph_keygen_t_n_parties(t, t + 1, &group, &keys_vec, &message, &mut rng);
+        let local_sig_vec: Vec<_> = eph_shared_keys_vec
+            .iter()
+            .zip(mixed.iter())
+            .map(|(eph_keys, keys)| {
+                LocalSig::compute(&message, eph_keys, keys, &SignatureMode::Pure)
+            })
+            .collect();
+        let err = LocalSig::verify_local_sigs(
+            &local_sig_vec,
+            &parties_index_vec,
+            &new_vss_vec[..1],
+            &eph_vss_vec,
+        )
+        .err()
+        .unwrap();
+        assert_eq!(err, Error::InvalidSS(vec![0]));
+    }
+
+    #[test]
+    fn test_refresh_rejects_non_zero_sharing() {
+        let mut rng = deterministic_fast_rand("test_refresh_rejects_non_zero_sharing", None);
+        let (t, n) = (1u16, 3u16);
+        let params = Parameters {
+            threshold: t,
+            share_count: n,
+        };
+        let parties: Vec<_> = (1..=n).collect();
+        let (_, shared_keys, _, key_gen_vss_vec) = keygen_t_n_parties(t, n, &parties, &mut rng);
+
+        let (mut vss_schemes, mut secret_shares): (Vec<_>, Vec<_>) = parties
+            .iter()
+            .map(|_| refresh::phase1_distribute(&params, &parties).unwrap())
+            .unzip();
+        // party 2 deals a sharing of a non-zero value, trying to shift the group key
+        let (bad_vss, bad_shares) =
+            VerifiableSS::share_at_indices(t, n, &Scalar::from(1), &parties);
+        vss_schemes[1] = bad_vss;
+        secret_shares[1] = bad_shares;
+
+        let received: Vec<_> = secret_shares.iter().map(|s| s[0].clone()).collect();
+        let err = refresh::phase2_verify_vss_refresh_keys(
+            &shared_keys[0],
+            &params,
+            &received,
+            &vss_schemes,
+            &key_gen_vss_vec,
+            &parties,
+            parties[0],
+        )
+        .err()
+        .unwrap();
+        assert_eq!(err, Error::InvalidSS(vec![2]));
+    }
+}
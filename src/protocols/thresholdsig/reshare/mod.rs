@@ -0,0 +1,113 @@
+/*
+    Multisig eddsa
+    Copyright 2018 by Kzen Networks
+    This file is part of multi-party-eddsa library
+    (https://github.com/KZen-networks/multi-party-eddsa)
+    Multisig Schnorr is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-eddsa/blob/master/LICENSE>
+*/
+
+//! Resharing to a new committee
+//!
+//! See "Verifiable Secret Redistribution for Archive Systems" (Wong, Wang, Wing 2002).
+//! At least t+1 old parties each turn their share into an additive share `lambda_i * x_i` of the
+//! secret, and deal it with a fresh VSS under the new parameters to the new parties. Every new
+//! party checks its sub-shares against the old VSS commitments and adds them up, so the aggregate
+//! public key `y` is unchanged.
+
+use Error::{self, InvalidKey, InvalidSS, UnknownParty};
+
+use curv::cryptographic_primitives::secret_sharing::feldman_vss::{SecretShares, VerifiableSS};
+use curv::cryptographic_primitives::secret_sharing::Polynomial;
+use curv::elliptic::curves::{Ed25519, Point, Scalar};
+use protocols::thresholdsig::refresh::combine_vss_schemes;
+use protocols::thresholdsig::{check_length, check_parties, Parameters, SharedKeys};
+
+// run by every old party in `old_parties`, sends secret_shares[j] to the new party at new_parties[j]
+// and broadcasts the VSS.
+pub fn phase1_distribute(
+    keys: &SharedKeys,
+    index: u16,
+    old_parties: &[u16],
+    new_params: &Parameters,
+    new_parties: &[u16],
+) -> Result<(VerifiableSS<Ed25519>, SecretShares<Ed25519>), Error> {
+    check_length(usize::from(new_params.share_count), new_parties.len())?;
+    check_parties(new_parties)?;
+    check_parties(old_parties)?;
+    let lambda_i = lagrange_coefficient(old_parties, index).ok_or(UnknownParty(index))?;
+    Ok(VerifiableSS::share_at_indices(
+        new_params.threshold,
+        new_params.share_count,
+        &(lambda_i * &keys.x_i),
+        new_parties,
+    ))
+}
+
+// run by every new party, `secret_shares_vec` and `vss_scheme_vec` are ordered as `old_parties`,
+// and `old_vss_vec` are the VSS schemes the old shares were verified against.
+// returns the new keys and the VSS to verify the new shares with.
+// on failure the error lists the indices of the old parties with a bad share
+pub fn phase2_verify_vss_construct_keypair(
+    y: &Point<Ed25519>,
+    new_params: &Parameters,
+    old_parties: &[u16],
+    secret_shares_vec: &[Scalar<Ed25519>],
+    vss_scheme_vec: &[VerifiableSS<Ed25519>],
+    old_vss_vec: &[VerifiableSS<Ed25519>],
+    index: u16,
+) -> Result<(SharedKeys, VerifiableSS<Ed25519>), Error> {
+    check_length(old_parties.len(), secret_shares_vec.len())?;
+    check_length(old_parties.len(), vss_scheme_vec.len())?;
+
+    let old_vss = combine_vss_schemes(old_vss_vec)?;
+    let bad_parties: Vec<u16> = vss_scheme_vec
+        .iter()
+        .zip(secret_shares_vec.iter())
+        .zip(old_parties.iter())
+        .filter(|((vss_scheme, secret_share), &old_index)| {
+            // the dealt secret must be lambda_i times the old public share of the dealer
+            let expected = lagrange_coefficient(old_parties, old_index)
+                .map(|lambda_i| old_vss.get_point_commitment(old_index) * lambda_i);
+            vss_scheme.parameters.threshold != new_params.threshold
+                || vss_scheme.commitments.len() != usize::from(new_params.threshold) + 1
+                || Some(&vss_scheme.commitments[0]) != expected.as_ref()
+                || vss_scheme.validate_share(secret_share, index).is_err()
+        })
+        .map(|(_, &old_index)| old_index)
+        .collect();
+    if !bad_parties.is_empty() {
+        return Err(InvalidSS(bad_parties));
+    }
+
+    let vss_scheme = combine_vss_schemes(vss_scheme_vec)?;
+    // fails if fewer than t+1 old parties took part
+    if vss_scheme.commitments.first() != Some(y) {
+        return Err(InvalidKey);
+    }
+    let x_i = secret_shares_vec.iter().sum();
+    Ok((
+        SharedKeys {
+            y: y.clone(),
+            x_i,
+            prefix: Scalar::random(),
+        },
+        vss_scheme,
+    ))
+}
+
+fn lagrange_coefficient(parties: &[u16], index: u16) -> Option<Scalar<Ed25519>> {
+    check_parties(parties).ok()?;
+    let position = parties.iter().position(|&i| i == index)?;
+    let xs: Vec<Scalar<Ed25519>> = parties.iter().map(|&i| Scalar::from(i)).collect();
+    Some(Polynomial::lagrange_basis(
+        &Scalar::zero(),
+        position as u16,
+        &xs,
+    ))
+}
+
+mod test;
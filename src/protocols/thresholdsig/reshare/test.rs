@@ -0,0 +1,200 @@
+#![allow(non_snake_case)]
+/*
+    Multisig eddsa
+    Copyright 2018 by Kzen Networks
+    This file is part of multi-party-eddsa library
+    (https://github.com/KZen-networks/multi-party-eddsa)
+    Multisig Schnorr is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-eddsa/blob/master/LICENSE>
+*/
+#[cfg(test)]
+mod tests {
+    use curv::cryptographic_primitives::secret_sharing::feldman_vss::VerifiableSS;
+    use curv::elliptic::curves::Scalar;
+
+    use protocols::tests::{deterministic_fast_rand, verify_dalek};
+    use protocols::thresholdsig::test::tests::keygen_t_n_parties;
+    use protocols::thresholdsig::{frost, reshare, Parameters};
+    use protocols::{SignatureMode, VerificationPolicy};
+    use Error;
+
+    #[test]
+    fn test_reshare_to_new_committee() {
+        let mut rng = deterministic_fast_rand("test_reshare_to_new_committee", None);
+        let (t, n) = (1u16, 3u16);
+        let old_indices: Vec<_> = (1..=n).collect();
+        let (_, old_shared_keys, y, key_gen_vss_vec) =
+            keygen_t_n_parties(t, n, &old_indices, &mut rng);
+
+        // parties 1 and 3 of the old 1-of-3 committee reshare to a new 2-of-5 committee
+        let new_params = Parameters {
+            threshold: 2,
+            share_count: 5,
+        };
+        let old_parties = [1u16, 3];
+        let new_parties: Vec<_> = (10..15).collect();
+        let (vss_schemes, secret_shares): (Vec<_>, Vec<_>) = old_parties
+            .iter()
+            .map(|&index| {
+                reshare::phase1_distribute(
+                    &old_shared_keys[usize::from(index - 1)],
+                    index,
+                    &old_parties,
+                    &new_params,
+                    &new_parties,
+                )
+                .unwrap()
+            })
+            .unzip();
+
+        let (new_shared_keys, new_vss_vec): (Vec<_>, Vec<_>) = new_parties
+            .iter()
+            .enumerate()
+            .map(|(j, &index)| {
+                let received: Vec<_> = secret_shares.iter().map(|s| s[j].clone()).collect();
+                reshare::phase2_verify_vss_construct_keypair(
+                    &y,
+                    &new_params,
+                    &old_parties,
+                    &received,
+                    &vss_schemes,
+                    &key_gen_vss_vec,
+                    index,
+                )
+                .unwrap()
+            })
+            .unzip();
+        assert!(new_vss_vec.iter().all(|vss| vss == &new_vss_vec[0]));
+        assert!(new_shared_keys.iter().all(|keys| keys.y == y));
+
+        // any 3 of the new parties can sign under the same public key
+        let message: [u8; 4] = [79, 77, 69, 82];
+        let group = [1usize, 2, 4];
+        let (nonces, commitments): (Vec<_>, Vec<_>) = group
+            .iter()
+            .map(|&j| frost::commit(&new_shared_keys[j], new_parties[j]))
+            .unzip();
+        let shares: Vec<_> = nonces
+            .into_iter()
+            .zip(group.iter())
+            .map(|(nonces, &j)| {
+                frost::sign(
+                    &message,
+                    &new_shared_keys[j],
+                    new_parties[j],
+                    nonces,
+                    &commitments,
+                    &SignatureMode::Pure,
+                )
+                .unwrap()
+            })
+            .collect();
+        for share in &shares {
+            let public_share = frost::public_share(&new_vss_vec[..1], share.index);
+            assert!(frost::verify_signature_share(
+                share,
+                &public_share,
+                &message,
+                &y,
+                &commitments,
+                &SignatureMode::Pure,
+                VerificationPolicy::Strict
+            )
+            .is_ok());
+        }
+        let sig =
+            frost::aggregate(&message, &y, &commitments, &shares, &SignatureMode::Pure).unwrap();
+        assert!(verify_dalek(&y, &sig, &message));
+    }
+
+    #[test]
+    fn test_reshare_rejects_bad_dealer_and_too_few_dealers() {
+        let mut rng =
+            deterministic_fast_rand("test_reshare_rejects_bad_dealer_and_too_few_dealers", None);
+        let (t, n) = (2u16, 4u16);
+        let old_indices: Vec<_> = (1..=n).collect();
+        let (_, old_shared_keys, y, key_gen_vss_vec) =
+            keygen_t_n_parties(t, n, &old_indices, &mut rng);
+        let new_params = Parameters {
+            threshold: 1,
+            share_count: 2,
+        };
+        let new_parties = [1u16, 2];
+
+        let deal = |old_parties: &[u16]| -> (Vec<_>, Vec<_>) {
+            old_parties
+                .iter()
+                .map(|&index| {
+                    let (mut vss_scheme, mut secret_shares) = reshare::phase1_distribute(
+                        &old_shared_keys[usize::from(index - 1)],
+                        index,
+                        old_parties,
+                        &new_params,
+                        &new_parties,
+                    )
+                    .unwrap();
+                    // old party 4 deals a wrong value
+                    if index == 4 {
+                        let (bad_vss, bad_shares) = VerifiableSS::share_at_indices(
+                            new_params.threshold,
+                            new_params.share_count,
+                            &Scalar::random(),
+                            &new_parties,
+                        );
+                        vss_scheme = bad_vss;
+                        secret_shares = bad_shares;
+                    }
+                    (vss_scheme, secret_shares)
+                })
+                .unzip()
+        };
+
+        let old_parties = [1u16, 2, 4];
+        let (vss_schemes, secret_shares) = deal(&old_parties);
+        let received: Vec<_> = secret_shares.iter().map(|s| s[0].clone()).collect();
+        let err = reshare::phase2_verify_vss_construct_keypair(
+            &y,
+            &new_params,
+            &old_parties,
+            &received,
+            &vss_schemes,
+            &key_gen_vss_vec,
+            new_parties[0],
+        )
+        .err()
+        .unwrap();
+        assert_eq!(err, Error::InvalidSS(vec![4]));
+
+        // only t old parties cannot reshare
+        let old_parties = [1u16, 2];
+        let (vss_schemes, secret_shares) = deal(&old_parties);
+        let received: Vec<_> = secret_shares.iter().map(|s| s[0].clone()).collect();
+        let err = reshare::phase2_verify_vss_construct_keypair(
+            &y,
+            &new_params,
+            &old_parties,
+            &received,
+            &vss_schemes,
+            &key_gen_vss_vec,
+            new_parties[0],
+        )
+        .err()
+        .unwrap();
+        assert_eq!(err, Error::InvalidKey);
+
+        // an old party listed twice would make the Lagrange coefficients undefined
+        let err = reshare::phase1_distribute(
+            &old_shared_keys[0],
+            1,
+            &[1, 2, 2],
+            &new_params,
+            &new_parties,
+        )
+        .err()
+        .unwrap();
+        assert_eq!(err, Error::DuplicateParty(2));
+    }
+}
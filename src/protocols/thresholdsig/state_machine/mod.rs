@@ -0,0 +1,560 @@
+#![allow(non_snake_case)]
+/*
+    multi-party-ed25519
+
+    Copyright 2018 by Kzen Networks
+
+    This file is part of multi-party-ed25519 library
+    (https://github.com/KZen-networks/multisig-schnorr)
+
+    multi-party-ed25519 is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-ed25519/blob/master/LICENSE>
+*/
+
+//! Threshold key generation and signing as state machines. Parties are indexed like in the rest
+//! of the module, from 1 to n.
+//!
+//! Key generation broadcasts a commitment to each party's public key in round 1 and opens it in
+//! round 2, then each party sends its VSS and a share to every other party in round 3. Signing
+//! does the same with the ephemeral keys, and broadcasts the local signatures in round 4.
+
+use curv::cryptographic_primitives::secret_sharing::feldman_vss::VerifiableSS;
+use curv::elliptic::curves::{Ed25519, Point, Scalar};
+use curv::BigInt;
+use std::collections::BTreeMap;
+use std::fmt;
+
+use protocols::state_machine::{Msg, RoundError, RoundMessage, Rounds, StateMachine};
+use protocols::thresholdsig::{self, EphemeralKey, KeyGenBroadcastMessage1, Keys, LocalSig};
+use protocols::thresholdsig::{
+    check_length, check_parties, check_party_count, Parameters, SharedKeys,
+};
+use protocols::{Signature, SignatureMode, REDACTED};
+use Error::{self, InvalidKey, InvalidSS};
+
+/// Messages of both key generation and signing.
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub enum ThresholdMessage {
+    Commitment(KeyGenBroadcastMessage1),
+    Decommitment {
+        public: Point<Ed25519>,
+        blind_factor: BigInt,
+    },
+    /// Sent point-to-point, the share has to stay private.
+    Share {
+        vss: VerifiableSS<Ed25519>,
+        share: Scalar<Ed25519>,
+    },
+    LocalSignature(LocalSig),
+}
+
+impl fmt::Debug for ThresholdMessage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ThresholdMessage::Commitment(commitment) => {
+                f.debug_tuple("Commitment").field(commitment).finish()
+            }
+            ThresholdMessage::Decommitment {
+                public,
+                blind_factor,
+            } => f
+                .debug_struct("Decommitment")
+                .field("public", public)
+                .field("blind_factor", blind_factor)
+                .finish(),
+            ThresholdMessage::Share { vss, .. } => f
+                .debug_struct("Share")
+                .field("vss", vss)
+                .field("share", &REDACTED)
+                .finish(),
+            ThresholdMessage::LocalSignature(local_sig) => {
+                f.debug_tuple("LocalSignature").field(local_sig).finish()
+            }
+        }
+    }
+}
+
+impl RoundMessage for ThresholdMessage {
+    fn round(&self) -> u16 {
+        match self {
+            ThresholdMessage::Commitment(_) => 1,
+            ThresholdMessage::Decommitment { .. } => 2,
+            ThresholdMessage::Share { .. } => 3,
+            ThresholdMessage::LocalSignature(_) => 4,
+        }
+    }
+
+    fn is_broadcast(&self) -> bool {
+        !matches!(self, ThresholdMessage::Share { .. })
+    }
+}
+
+/// What a party keeps from key generation, to sign with later.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LocalKey {
+    pub keys: Keys,
+    pub shared_keys: SharedKeys,
+    /// The VSS of every party, in the order of their indices.
+    pub vss_schemes: Vec<VerifiableSS<Ed25519>>,
+}
+
+// The commit-reveal-share rounds that key generation and signing have in common.
+#[derive(Serialize, Deserialize)]
+struct Dealing {
+    params: Parameters,
+    commitments: BTreeMap<u16, KeyGenBroadcastMessage1>,
+    decommitments: BTreeMap<u16, (Point<Ed25519>, BigInt)>,
+    shares: BTreeMap<u16, (VerifiableSS<Ed25519>, Scalar<Ed25519>)>,
+}
+
+impl Dealing {
+    fn new(
+        rounds: &mut Rounds<ThresholdMessage>,
+        params: Parameters,
+        commitment: KeyGenBroadcastMessage1,
+        public: Point<Ed25519>,
+        blind_factor: BigInt,
+    ) -> Dealing {
+        let me = rounds.party_index();
+        rounds.broadcast(ThresholdMessage::Commitment(commitment.clone()));
+        let mut dealing = Dealing {
+            params,
+            commitments: BTreeMap::new(),
+            decommitments: BTreeMap::new(),
+            shares: BTreeMap::new(),
+        };
+        dealing.commitments.insert(me, commitment);
+        dealing.decommitments.insert(me, (public, blind_factor));
+        dealing
+    }
+
+    // ends round 1, returns the decommitment to broadcast
+    fn collect_commitments(
+        &mut self,
+        me: u16,
+        messages: BTreeMap<u16, ThresholdMessage>,
+    ) -> ThresholdMessage {
+        for (sender, message) in messages {
+            if let ThresholdMessage::Commitment(commitment) = message {
+                self.commitments.insert(sender, commitment);
+            }
+        }
+        let (public, blind_factor) = self.decommitments[&me].clone();
+        ThresholdMessage::Decommitment {
+            public,
+            blind_factor,
+        }
+    }
+
+    // ends round 2, returns the vectors of blind factors, public values and commitments in the
+    // order of the parties
+    fn collect_decommitments(
+        &mut self,
+        messages: BTreeMap<u16, ThresholdMessage>,
+    ) -> (
+        Vec<BigInt>,
+        Vec<Point<Ed25519>>,
+        Vec<KeyGenBroadcastMessage1>,
+    ) {
+        for (sender, message) in messages {
+            if let ThresholdMessage::Decommitment {
+                public,
+                blind_factor,
+            } = message
+            {
+                self.decommitments.insert(sender, (public, blind_factor));
+            }
+        }
+        let blind_vec = self
+            .decommitments
+            .values()
+            .map(|(_, b)| b.clone())
+            .collect();
+        let public_vec = self.public_vec();
+        let bc1_vec = self.commitments.values().cloned().collect();
+        (blind_vec, public_vec, bc1_vec)
+    }
+
+    fn public_vec(&self) -> Vec<Point<Ed25519>> {
+        self.decommitments
+            .values()
+            .map(|(p, _)| p.clone())
+            .collect()
+    }
+
+    // sends the shares of the other parties and keeps our own
+    fn distribute(
+        &mut self,
+        rounds: &mut Rounds<ThresholdMessage>,
+        parties: &[u16],
+        vss: VerifiableSS<Ed25519>,
+        shares: &[Scalar<Ed25519>],
+    ) {
+        let me = rounds.party_index();
+        for (&party, share) in parties.iter().zip(shares) {
+            if party == me {
+                self.shares.insert(me, (vss.clone(), share.clone()));
+            } else {
+                rounds.send(
+                    party,
+                    ThresholdMessage::Share {
+                        vss: vss.clone(),
+                        share: share.clone(),
+                    },
+                );
+            }
+        }
+    }
+
+    // ends round 3, returns the shares and VSS schemes in the order of the parties
+    fn collect_shares(
+        &mut self,
+        messages: BTreeMap<u16, ThresholdMessage>,
+    ) -> (Vec<Scalar<Ed25519>>, Vec<VerifiableSS<Ed25519>>) {
+        for (sender, message) in messages {
+            if let ThresholdMessage::Share { vss, share } = message {
+                self.shares.insert(sender, (vss, share));
+            }
+        }
+        self.shares
+            .values()
+            .map(|(vss, share)| (share.clone(), vss.clone()))
+            .unzip()
+    }
+}
+
+/// Can be serialized between rounds, to run each round in a separate process. The state holds the
+/// party's secrets, so it has to be kept as safe as the key itself.
+#[derive(Serialize, Deserialize)]
+pub struct KeyGen {
+    rounds: Rounds<ThresholdMessage>,
+    keys: Option<Keys>,
+    dealing: Dealing,
+    output: Option<LocalKey>,
+}
+
+impl KeyGen {
+    /// Starts key generation with `keys`, which comes from `Keys::phase1_create` with the party's
+    /// index. `parties` are the indices of all the parties, including this one.
+    pub fn new(keys: Keys, params: Parameters, parties: Vec<u16>) -> Result<KeyGen, RoundError> {
+        if params.threshold >= params.share_count {
+            return Err(RoundError::Setup(InvalidKey));
+        }
+        check_length(usize::from(params.share_count), parties.len())?;
+        check_parties(&parties)?;
+        let mut rounds = Rounds::new(keys.party_index, parties, 3)?;
+        let (commitment, blind_factor) = keys.phase1_broadcast();
+        let dealing = Dealing::new(
+            &mut rounds,
+            params,
+            commitment,
+            keys.keypair.public_key.clone(),
+            blind_factor,
+        );
+        let mut keygen = KeyGen {
+            rounds,
+            keys: Some(keys),
+            dealing,
+            output: None,
+        };
+        keygen.proceed()?;
+        Ok(keygen)
+    }
+
+    fn proceed(&mut self) -> Result<(), RoundError> {
+        while self.rounds.is_complete() {
+            let round = self.rounds.current_round();
+            let messages = self.rounds.advance();
+            if let Err(error) = self.finish_round(round, messages) {
+                return Err(self.rounds.fail(round, error));
+            }
+        }
+        Ok(())
+    }
+
+    fn finish_round(
+        &mut self,
+        round: u16,
+        messages: BTreeMap<u16, ThresholdMessage>,
+    ) -> Result<(), Error> {
+        let me = self.rounds.party_index();
+        let parties = self.rounds.parties().to_vec();
+        let keys = self.keys.as_ref().expect("taken in the last round");
+        match round {
+            1 => {
+                let decommitment = self.dealing.collect_commitments(me, messages);
+                self.rounds.broadcast(decommitment);
+            }
+            2 => {
+                let (blind_vec, y_vec, bc1_vec) = self.dealing.collect_decommitments(messages);
+                let (vss, shares) = keys.phase1_verify_com_phase2_distribute(
+                    &self.dealing.params,
+                    &blind_vec,
+                    &y_vec,
+                    &bc1_vec,
+                    &parties,
+                )?;
+                self.dealing
+                    .distribute(&mut self.rounds, &parties, vss, &shares);
+            }
+            _ => {
+                let (secret_shares_vec, vss_schemes) = self.dealing.collect_shares(messages);
+                let shared_keys = keys.phase2_verify_vss_construct_keypair(
+                    &self.dealing.params,
+                    &self.dealing.public_vec(),
+                    &secret_shares_vec,
+                    &vss_schemes,
+                    &parties,
+                    me,
+                )?;
+                self.output = Some(LocalKey {
+                    keys: self.keys.take().expect("taken in the last round"),
+                    shared_keys,
+                    vss_schemes,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Can be serialized between rounds, like `KeyGen`.
+#[derive(Serialize, Deserialize)]
+pub struct Signing {
+    rounds: Rounds<ThresholdMessage>,
+    shared_keys: SharedKeys,
+    keygen_vss_schemes: Vec<VerifiableSS<Ed25519>>,
+    message: Vec<u8>,
+    mode: SignatureMode,
+    ephemeral_key: EphemeralKey,
+    dealing: Dealing,
+    R: Option<Point<Ed25519>>,
+    eph_vss_schemes: Vec<VerifiableSS<Ed25519>>,
+    local_sigs: BTreeMap<u16, LocalSig>,
+    output: Option<Signature>,
+}
+
+impl Signing {
+    /// Starts signing `message` with the key from key generation, `signers` are the indices of
+    /// the signing parties, including this one, and there must be more than the threshold of
+    /// them.
+    pub fn new(
+        local_key: &LocalKey,
+        signers: Vec<u16>,
+        message: &[u8],
+        mode: SignatureMode,
+    ) -> Result<Signing, RoundError> {
+        let me = local_key.keys.party_index;
+        let threshold = match local_key.vss_schemes.first() {
+            Some(vss_scheme) => vss_scheme.parameters.threshold,
+            None => return Err(RoundError::Setup(InvalidKey)),
+        };
+        check_party_count(threshold, local_key.vss_schemes.len() as u16, signers.len())?;
+        check_parties(&signers)?;
+        let params = Parameters {
+            threshold,
+            share_count: signers.len() as u16,
+        };
+        let mut rounds = Rounds::new(me, signers, 4)?;
+        let ephemeral_key = EphemeralKey::ephermeral_key_create_from_deterministic_secret(
+            &local_key.keys,
+            message,
+            me,
+        );
+        let (commitment, blind_factor) = ephemeral_key.phase1_broadcast();
+        let dealing = Dealing::new(
+            &mut rounds,
+            params,
+            commitment,
+            ephemeral_key.R_i.clone(),
+            blind_factor,
+        );
+        let shared_keys = &local_key.shared_keys;
+        let mut signing = Signing {
+            rounds,
+            shared_keys: SharedKeys {
+                y: shared_keys.y.clone(),
+                x_i: shared_keys.x_i.clone(),
+                prefix: shared_keys.prefix.clone(),
+            },
+            keygen_vss_schemes: local_key.vss_schemes.clone(),
+            message: message.to_vec(),
+            mode,
+            ephemeral_key,
+            dealing,
+            R: None,
+            eph_vss_schemes: Vec::new(),
+            local_sigs: BTreeMap::new(),
+            output: None,
+        };
+        signing.proceed()?;
+        Ok(signing)
+    }
+
+    fn proceed(&mut self) -> Result<(), RoundError> {
+        while self.rounds.is_complete() {
+            let round = self.rounds.current_round();
+            let messages = self.rounds.advance();
+            if let Err(error) = self.finish_round(round, messages) {
+                return Err(self.rounds.fail(round, error));
+            }
+        }
+        Ok(())
+    }
+
+    fn finish_round(
+        &mut self,
+        round: u16,
+        messages: BTreeMap<u16, ThresholdMessage>,
+    ) -> Result<(), Error> {
+        let me = self.rounds.party_index();
+        let signers = self.rounds.parties().to_vec();
+        match round {
+            1 => {
+                let decommitment = self.dealing.collect_commitments(me, messages);
+                self.rounds.broadcast(decommitment);
+            }
+            2 => {
+                let (blind_vec, R_vec, bc1_vec) = self.dealing.collect_decommitments(messages);
+                let (vss, shares) = self.ephemeral_key.phase1_verify_com_phase2_distribute(
+                    &self.dealing.params,
+                    &blind_vec,
+                    &R_vec,
+                    &bc1_vec,
+                    &signers,
+                )?;
+                self.dealing
+                    .distribute(&mut self.rounds, &signers, vss, &shares);
+            }
+            3 => {
+                let (secret_shares_vec, vss_schemes) = self.dealing.collect_shares(messages);
+                let eph_shared_keys = self.ephemeral_key.phase2_verify_vss_construct_keypair(
+                    &self.dealing.params,
+                    &self.dealing.public_vec(),
+                    &secret_shares_vec,
+                    &vss_schemes,
+                    &signers,
+                    me,
+                )?;
+                let local_sig = LocalSig::compute(
+                    &self.message,
+                    &eph_shared_keys,
+                    &self.shared_keys,
+                    &self.mode,
+                );
+                self.local_sigs.insert(me, local_sig.clone());
+                self.R = Some(eph_shared_keys.R);
+                self.eph_vss_schemes = vss_schemes;
+                self.rounds
+                    .broadcast(ThresholdMessage::LocalSignature(local_sig));
+            }
+            _ => {
+                for (sender, message) in messages {
+                    if let ThresholdMessage::LocalSignature(local_sig) = message {
+                        self.local_sigs.insert(sender, local_sig);
+                    }
+                }
+                let local_sig_vec: Vec<_> = self.local_sigs.values().cloned().collect();
+                // the local signatures are checked against shares of the secret key, which are
+                // indexed from 0
+                let parties_index_vec: Vec<_> = signers.iter().map(|index| index - 1).collect();
+                let vss_sum_local_sigs = LocalSig::verify_local_sigs(
+                    &local_sig_vec,
+                    &parties_index_vec,
+                    &self.keygen_vss_schemes,
+                    &self.eph_vss_schemes,
+                )
+                .map_err(|error| match error {
+                    // back to the signers' indices, which are counted from 1
+                    InvalidSS(bad_signers) => {
+                        InvalidSS(bad_signers.into_iter().map(|index| index + 1).collect())
+                    }
+                    error => error,
+                })?;
+                self.output = Some(thresholdsig::generate(
+                    &vss_sum_local_sigs,
+                    &local_sig_vec,
+                    &parties_index_vec,
+                    self.R.take().expect("set in round 3"),
+                )?);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl StateMachine for KeyGen {
+    type MessageBody = ThresholdMessage;
+    type Output = LocalKey;
+
+    fn handle_incoming(&mut self, msg: Msg<ThresholdMessage>) -> Result<(), RoundError> {
+        self.rounds.accept(msg)?;
+        self.proceed()
+    }
+
+    fn outgoing(&mut self) -> Vec<Msg<ThresholdMessage>> {
+        self.rounds.take_outgoing()
+    }
+
+    fn pick_output(&mut self) -> Result<LocalKey, RoundError> {
+        self.rounds.check_finished()?;
+        self.output.take().ok_or(RoundError::Finished)
+    }
+
+    fn current_round(&self) -> u16 {
+        self.rounds.current_round()
+    }
+
+    fn total_rounds(&self) -> u16 {
+        self.rounds.total_rounds()
+    }
+
+    fn party_index(&self) -> u16 {
+        self.rounds.party_index()
+    }
+
+    fn parties(&self) -> &[u16] {
+        self.rounds.parties()
+    }
+}
+
+impl StateMachine for Signing {
+    type MessageBody = ThresholdMessage;
+    type Output = Signature;
+
+    fn handle_incoming(&mut self, msg: Msg<ThresholdMessage>) -> Result<(), RoundError> {
+        self.rounds.accept(msg)?;
+        self.proceed()
+    }
+
+    fn outgoing(&mut self) -> Vec<Msg<ThresholdMessage>> {
+        self.rounds.take_outgoing()
+    }
+
+    fn pick_output(&mut self) -> Result<Signature, RoundError> {
+        self.rounds.check_finished()?;
+        self.output.take().ok_or(RoundError::Finished)
+    }
+
+    fn current_round(&self) -> u16 {
+        self.rounds.current_round()
+    }
+
+    fn total_rounds(&self) -> u16 {
+        self.rounds.total_rounds()
+    }
+
+    fn party_index(&self) -> u16 {
+        self.rounds.party_index()
+    }
+
+    fn parties(&self) -> &[u16] {
+        self.rounds.parties()
+    }
+}
+
+mod test;
@@ -0,0 +1,206 @@
+/*
+    multi-party-ed25519
+
+    Copyright 2018 by Kzen Networks
+
+    This file is part of multi-party-ed25519 library
+    (https://github.com/KZen-networks/multisig-schnorr)
+
+    multi-party-ed25519 is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-ed25519/blob/master/LICENSE>
+*/
+
+#[cfg(test)]
+mod tests {
+    use curv::elliptic::curves::Scalar;
+
+    use protocols::simulation::{Fate, Simulation};
+    use protocols::state_machine::{RoundError, StateMachine};
+    use protocols::tests::verify_dalek;
+    use protocols::thresholdsig::state_machine::{KeyGen, LocalKey, Signing, ThresholdMessage};
+    use protocols::thresholdsig::{Keys, Parameters};
+    use protocols::SignatureMode;
+    use Error;
+
+    fn keygen_parties(t: u16, n: u16) -> Vec<KeyGen> {
+        let params = Parameters {
+            threshold: t,
+            share_count: n,
+        };
+        let parties: Vec<_> = (1..=n).collect();
+        parties
+            .iter()
+            .map(|&i| KeyGen::new(Keys::phase1_create(i), params.clone(), parties.clone()).unwrap())
+            .collect()
+    }
+
+    fn keygen(t: u16, n: u16) -> Vec<LocalKey> {
+        Simulation::new(keygen_parties(t, n))
+            .run()
+            .into_iter()
+            .map(Result::unwrap)
+            .collect()
+    }
+
+    fn sign(local_keys: &[LocalKey], signers: &[u16], message: &[u8]) {
+        let parties = signers
+            .iter()
+            .map(|&i| {
+                Signing::new(
+                    &local_keys[usize::from(i - 1)],
+                    signers.to_vec(),
+                    message,
+                    SignatureMode::Pure,
+                )
+                .unwrap()
+            })
+            .collect();
+        let y = &local_keys[0].shared_keys.y;
+        for signature in Simulation::new(parties).run() {
+            let signature = signature.unwrap();
+            assert!(verify_dalek(y, &signature, message));
+        }
+    }
+
+    #[test]
+    fn test_keygen_and_signing_state_machines() {
+        let message = [79, 77, 69, 82];
+        for (t, n) in [(0, 1), (1, 2), (1, 3), (2, 4)] {
+            let local_keys = keygen(t, n);
+            assert!(local_keys
+                .iter()
+                .all(|key| key.shared_keys.y == local_keys[0].shared_keys.y));
+            let all: Vec<_> = (1..=n).collect();
+            sign(&local_keys, &all, &message);
+            let threshold_signers: Vec<_> = (1..=n).rev().take(usize::from(t) + 1).collect();
+            sign(&local_keys, &threshold_signers, &message);
+        }
+    }
+
+    #[test]
+    fn test_signing_state_machine_needs_enough_signers() {
+        let local_keys = keygen(1, 3);
+        assert!(matches!(
+            Signing::new(&local_keys[0], vec![1], &[], SignatureMode::Pure),
+            Err(RoundError::Setup(Error::WrongPartyCount {
+                threshold: 1,
+                share_count: 3,
+                received: 1
+            }))
+        ));
+        assert!(matches!(
+            Signing::new(&local_keys[0], vec![2, 3], &[], SignatureMode::Pure),
+            Err(RoundError::Setup(Error::UnknownParty(1)))
+        ));
+    }
+
+    #[test]
+    fn test_state_machines_reject_bad_party_indices() {
+        let params = Parameters {
+            threshold: 1,
+            share_count: 3,
+        };
+        for (parties, error) in [
+            (vec![0, 1, 2], Error::InvalidPartyIndex(0)),
+            (vec![1, 2, 2], Error::DuplicateParty(2)),
+        ] {
+            assert_eq!(
+                KeyGen::new(Keys::phase1_create(1), params.clone(), parties).err(),
+                Some(RoundError::Setup(error))
+            );
+        }
+        let local_keys = keygen(1, 3);
+        for (signers, error) in [
+            (vec![0, 1, 2], Error::InvalidPartyIndex(0)),
+            (vec![1, 2, 2], Error::DuplicateParty(2)),
+            (vec![1, 1], Error::DuplicateParty(1)),
+        ] {
+            assert_eq!(
+                Signing::new(&local_keys[0], signers, &[], SignatureMode::Pure).err(),
+                Some(RoundError::Setup(error))
+            );
+        }
+    }
+
+    #[test]
+    fn test_signing_state_machine_blames_bad_signer() {
+        let local_keys = keygen(1, 3);
+        let parties = [1u16, 3]
+            .iter()
+            .map(|&i| {
+                Signing::new(
+                    &local_keys[usize::from(i - 1)],
+                    vec![1, 3],
+                    &[79, 77, 69, 82],
+                    SignatureMode::Pure,
+                )
+                .unwrap()
+            })
+            .collect();
+        // party 3 sends party 1 a bad local signature
+        let results = Simulation::new(parties)
+            .with_interceptor(|receiver, msg| {
+                if let ThresholdMessage::LocalSignature(local_sig) = &mut msg.body {
+                    if receiver == 1 {
+                        local_sig.gamma_i = &local_sig.gamma_i + Scalar::from(1);
+                    }
+                }
+                Fate::Deliver
+            })
+            .run();
+        assert_eq!(
+            results[0],
+            Err(RoundError::Protocol {
+                round: 4,
+                error: Error::InvalidSS(vec![3])
+            })
+        );
+        assert!(results[1].is_ok());
+    }
+
+    #[test]
+    fn test_keygen_state_machine_blames_bad_dealer() {
+        let mut parties = keygen_parties(1, 3);
+        for _ in 0..2 {
+            let messages: Vec<_> = parties.iter_mut().flat_map(|p| p.outgoing()).collect();
+            for msg in messages {
+                for party in parties.iter_mut().filter(|p| p.party_index() != msg.sender) {
+                    party.handle_incoming(msg.clone()).unwrap();
+                }
+            }
+        }
+        let shares: Vec<_> = parties.iter_mut().flat_map(|p| p.outgoing()).collect();
+        assert!(shares.iter().all(|msg| msg.receiver.is_some()));
+        let mut to_party_1: Vec<_> = shares
+            .into_iter()
+            .filter(|msg| msg.receiver == Some(1))
+            .collect();
+        assert_eq!(to_party_1.len(), 2);
+        // party 3 sends party 1 a share that doesn't match its VSS
+        if let ThresholdMessage::Share { share, .. } = &mut to_party_1[1].body {
+            *share = &*share + Scalar::from(1);
+        }
+        assert!(format!("{:?}", to_party_1[1]).contains("share: \"<redacted>\""));
+        let mut broadcast = to_party_1[0].clone();
+        broadcast.receiver = None;
+        assert_eq!(
+            parties[0].handle_incoming(broadcast),
+            Err(RoundError::WrongReceiver {
+                sender: 2,
+                round: 3
+            })
+        );
+        parties[0].handle_incoming(to_party_1[0].clone()).unwrap();
+        assert_eq!(
+            parties[0].handle_incoming(to_party_1[1].clone()),
+            Err(RoundError::Protocol {
+                round: 3,
+                error: Error::InvalidSS(vec![3])
+            })
+        );
+    }
+}
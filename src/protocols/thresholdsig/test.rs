@@ -11,15 +11,19 @@
     @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-eddsa/blob/master/LICENSE>
 */
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     use curv::cryptographic_primitives::secret_sharing::feldman_vss::VerifiableSS;
-    use curv::elliptic::curves::{Ed25519, Point};
+    use curv::elliptic::curves::{Ed25519, Point, Scalar};
     use itertools::{izip, Itertools};
-    use protocols::tests::{deterministic_fast_rand, verify_dalek};
+    use protocols::tests::{
+        assert_serde_roundtrip, deterministic_fast_rand, verify_dalek, verify_dalek_prehashed,
+    };
     use protocols::thresholdsig::{
         self, EphemeralKey, EphemeralSharedKeys, Keys, LocalSig, Parameters, SharedKeys,
     };
+    use protocols::{Context, SignatureMode, VerificationPolicy};
     use rand::{Rng, RngCore};
+    use Error;
 
     #[test]
     fn test_sign_threshold_verify_dalek_n1() {
@@ -85,6 +89,7 @@ mod tests {
                                 msg,
                                 nonce_share,
                                 &combined_shares[usize::from(index)],
+                                &SignatureMode::Pure,
                             )
                         })
                         .collect();
@@ -102,13 +107,61 @@ mod tests {
                         &partial_sigs,
                         &group_indexs,
                         agg_nonce,
-                    );
+                    )
+                    .unwrap();
                     assert!(verify_dalek(&agg_pubkey, &sig, msg));
                 }
             }
         }
     }
 
+    #[test]
+    fn test_sign_threshold_with_modes() {
+        let mut rng = deterministic_fast_rand("test_sign_threshold_with_modes", None);
+        let (t, n) = (1u16, 3u16);
+        let parties: Vec<_> = (1..=n).collect();
+        let (keypairs, shared_keys, y, vss_schemes) = keygen_t_n_parties(t, n, &parties, &mut rng);
+        let group_indexs = [0u16, 2];
+        let group: Vec<_> = group_indexs.iter().map(|i| i + 1).collect();
+
+        let data = [79u8, 77, 69, 82];
+        let prehash = SignatureMode::prehash(&data);
+        let ctx = SignatureMode::Context(Context::new(b"thresholdsig").unwrap());
+        let ph = SignatureMode::Prehash(Context::new(b"thresholdsig").unwrap());
+        for (message, mode) in [(&data[..], &ctx), (&prehash[..], &ph)] {
+            let (nonce_shares, R, nonce_vss_schemes) =
+                eph_keygen_t_n_parties(t, t + 1, &group, &keypairs, message, &mut rng);
+            let local_sigs: Vec<_> = nonce_shares
+                .iter()
+                .zip(group_indexs.iter())
+                .map(|(nonce_share, &index)| {
+                    LocalSig::compute(message, nonce_share, &shared_keys[usize::from(index)], mode)
+                })
+                .collect();
+            let vss_sum = LocalSig::verify_local_sigs(
+                &local_sigs,
+                &group_indexs,
+                &vss_schemes,
+                &nonce_vss_schemes,
+            )
+            .unwrap();
+            let signature =
+                thresholdsig::generate(&vss_sum, &local_sigs, &group_indexs, R).unwrap();
+            assert!(signature
+                .verify_with_mode(message, &y, mode, VerificationPolicy::Strict)
+                .is_ok());
+            assert!(signature.verify(message, &y).is_err());
+            if let SignatureMode::Prehash(_) = mode {
+                assert!(verify_dalek_prehashed(
+                    &y,
+                    &signature,
+                    &data,
+                    b"thresholdsig"
+                ));
+            }
+        }
+    }
+
     #[test]
     fn test_t2_n4() {
         let mut rng = deterministic_fast_rand("test_t2_n4", None);
@@ -134,7 +187,14 @@ mod tests {
         let (eph_shared_keys_vec, R, eph_vss_vec) =
             eph_keygen_t_n_parties(t, n, &parties_points_vec, &priv_keys_vec, &message, rng);
         let local_sig_vec = (0..usize::from(n))
-            .map(|i| LocalSig::compute(&message, &eph_shared_keys_vec[i], &priv_shared_keys_vec[i]))
+            .map(|i| {
+                LocalSig::compute(
+                    &message,
+                    &eph_shared_keys_vec[i],
+                    &priv_shared_keys_vec[i],
+                    &SignatureMode::Pure,
+                )
+            })
             .collect::<Vec<LocalSig>>();
         let verify_local_sig = LocalSig::verify_local_sigs(
             &local_sig_vec,
@@ -146,7 +206,8 @@ mod tests {
         assert!(verify_local_sig.is_ok());
         let vss_sum_local_sigs = verify_local_sig.unwrap();
         let signature =
-            thresholdsig::generate(&vss_sum_local_sigs, &local_sig_vec, &parties_index_vec, R);
+            thresholdsig::generate(&vss_sum_local_sigs, &local_sig_vec, &parties_index_vec, R)
+                .unwrap();
         let verify_sig = signature.verify(&message, &Y);
         assert!(verify_sig.is_ok());
     }
@@ -192,6 +253,7 @@ mod tests {
                     &message,
                     &eph_shared_keys_vec[i],
                     &priv_shared_keys_vec[usize::from(parties_index_vec[i])],
+                    &SignatureMode::Pure,
                 )
             })
             .collect::<Vec<LocalSig>>();
@@ -208,11 +270,381 @@ mod tests {
 
         /// each party / dealer can generate the signature
         let signature =
-            thresholdsig::generate(&vss_sum_local_sigs, &local_sig_vec, &parties_index_vec, R);
+            thresholdsig::generate(&vss_sum_local_sigs, &local_sig_vec, &parties_index_vec, R)
+                .unwrap();
         let verify_sig = signature.verify(&message, &Y);
         assert!(verify_sig.is_ok());
     }
 
+    #[test]
+    fn test_t1_n4_sign_with_3() {
+        let mut rng = deterministic_fast_rand("test_t1_n4_sign_with_3", None);
+        // the signers share their ephemeral keys with the keygen parameters, t < 3 < n
+        let (t, n) = (1u16, 4u16);
+        let key_gen_parties_points_vec: Vec<_> = (1..=n).collect();
+        let (priv_keys_vec, priv_shared_keys_vec, Y, key_gen_vss_vec) =
+            keygen_t_n_parties(t, n, &key_gen_parties_points_vec, &mut rng);
+        let parties_index_vec: [u16; 3] = [0, 2, 3];
+        let parties_points_vec: Vec<_> = parties_index_vec.iter().map(|i| i + 1).collect();
+        let message: [u8; 4] = [79, 77, 69, 82];
+
+        let (eph_shared_keys_vec, R, eph_vss_vec) = eph_keygen_t_n_parties(
+            t,
+            n,
+            &parties_points_vec,
+            &priv_keys_vec,
+            &message,
+            &mut rng,
+        );
+        let local_sig_vec: Vec<_> = eph_shared_keys_vec
+            .iter()
+            .zip(parties_index_vec.iter())
+            .map(|(eph_shared_keys, &i)| {
+                LocalSig::compute(
+                    &message,
+                    eph_shared_keys,
+                    &priv_shared_keys_vec[usize::from(i)],
+                    &SignatureMode::Pure,
+                )
+            })
+            .collect();
+        let vss_sum_local_sigs = LocalSig::verify_local_sigs(
+            &local_sig_vec,
+            &parties_index_vec,
+            &key_gen_vss_vec,
+            &eph_vss_vec,
+        )
+        .unwrap();
+        assert_eq!(
+            thresholdsig::generate(&vss_sum_local_sigs, &local_sig_vec, &[0, 0, 3], R.clone())
+                .err(),
+            Some(Error::DuplicateParty(0))
+        );
+        let signature =
+            thresholdsig::generate(&vss_sum_local_sigs, &local_sig_vec, &parties_index_vec, R)
+                .unwrap();
+        assert!(signature.verify(&message, &Y).is_ok());
+    }
+
+    #[test]
+    fn test_verify_local_sigs_identifies_bad_signers() {
+        let mut rng =
+            deterministic_fast_rand("test_verify_local_sigs_identifies_bad_signers", None);
+        let (t, n) = (2u16, 5u16);
+        let key_gen_parties_points_vec: Vec<_> = (1..=n).collect();
+        let (priv_keys_vec, priv_shared_keys_vec, _Y, key_gen_vss_vec) =
+            keygen_t_n_parties(t, n, &key_gen_parties_points_vec, &mut rng);
+        let parties_index_vec: [u16; 4] = [0, 1, 3, 4];
+        let parties_points_vec: Vec<_> = parties_index_vec.iter().map(|i| i + 1).collect();
+        let message: [u8; 4] = [79, 77, 69, 82];
+
+        let (eph_shared_keys_vec, _R, eph_vss_vec) = eph_keygen_t_n_parties(
+            t,
+            4,
+            &parties_points_vec,
+            &priv_keys_vec,
+            &message,
+            &mut rng,
+        );
+        let mut local_sig_vec: Vec<_> = eph_shared_keys_vec
+            .iter()
+            .zip(parties_index_vec.iter())
+            .map(|(eph_shared_keys, &i)| {
+                LocalSig::compute(
+                    &message,
+                    eph_shared_keys,
+                    &priv_shared_keys_vec[usize::from(i)],
+                    &SignatureMode::Pure,
+                )
+            })
+            .collect();
+
+        // parties 1 and 4 send corrupted local signatures
+        local_sig_vec[1].gamma_i = &local_sig_vec[1].gamma_i + Scalar::from(1);
+        local_sig_vec[3].gamma_i = Scalar::random();
+
+        let err = LocalSig::verify_local_sigs(
+            &local_sig_vec,
+            &parties_index_vec,
+            &key_gen_vss_vec,
+            &eph_vss_vec,
+        )
+        .err()
+        .unwrap();
+        assert_eq!(err, Error::InvalidSS(vec![1, 4]));
+
+        // an index that has no share point
+        let mut overflowing_index_vec = parties_index_vec;
+        overflowing_index_vec[0] = u16::MAX;
+        assert_eq!(
+            LocalSig::verify_local_sigs(
+                &local_sig_vec,
+                &overflowing_index_vec,
+                &key_gen_vss_vec,
+                &eph_vss_vec,
+            )
+            .err(),
+            Some(Error::InvalidSS(vec![u16::MAX, 1, 4]))
+        );
+
+        // too few signers, or a local signature missing
+        assert_eq!(
+            LocalSig::verify_local_sigs(
+                &local_sig_vec[..2],
+                &parties_index_vec[..2],
+                &key_gen_vss_vec,
+                &eph_vss_vec[..2],
+            )
+            .err(),
+            Some(Error::WrongPartyCount {
+                threshold: 2,
+                share_count: 5,
+                received: 2
+            })
+        );
+        assert_eq!(
+            LocalSig::verify_local_sigs(
+                &local_sig_vec[..3],
+                &parties_index_vec,
+                &key_gen_vss_vec,
+                &eph_vss_vec,
+            )
+            .err(),
+            Some(Error::WrongLength {
+                expected: 4,
+                received: 3
+            })
+        );
+        // a signer whose ephemeral VSS is short of commitments
+        let mut eph_vss_vec = eph_vss_vec;
+        eph_vss_vec[2].commitments.pop();
+        assert_eq!(
+            LocalSig::verify_local_sigs(
+                &local_sig_vec,
+                &parties_index_vec,
+                &key_gen_vss_vec,
+                &eph_vss_vec,
+            )
+            .err(),
+            Some(Error::InvalidSS(vec![3]))
+        );
+    }
+
+    #[test]
+    fn test_phase2_verify_vss_identifies_bad_dealers() {
+        let mut rng =
+            deterministic_fast_rand("test_phase2_verify_vss_identifies_bad_dealers", None);
+        let (t, n) = (1u16, 3u16);
+        let params = Parameters {
+            threshold: t,
+            share_count: n,
+        };
+        let parties: Vec<_> = (1..=n).collect();
+        let keypairs: Vec<_> = parties.iter().copied().map(Keys::phase1_create).collect();
+        let (first_msgs, first_msg_blinds): (Vec<_>, Vec<_>) = keypairs
+            .iter()
+            .map(|keypair| keypair.phase1_broadcast_rng(&mut rng))
+            .unzip();
+        let pubkeys_list: Vec<_> = keypairs
+            .iter()
+            .map(|k| k.keypair.public_key.clone())
+            .collect();
+        let (vss_schemes, secret_shares): (Vec<_>, Vec<_>) = keypairs
+            .iter()
+            .map(|keypair| {
+                keypair
+                    .phase1_verify_com_phase2_distribute(
+                        &params,
+                        &first_msg_blinds,
+                        &pubkeys_list,
+                        &first_msgs,
+                        &parties,
+                    )
+                    .unwrap()
+            })
+            .unzip();
+
+        // party 1 receives a bad share from party 3
+        let mut party0_shares: Vec<_> = secret_shares.iter().map(|s| s[0].clone()).collect();
+        party0_shares[2] = &party0_shares[2] + Scalar::from(1);
+        let err = keypairs[0]
+            .phase2_verify_vss_construct_keypair(
+                &params,
+                &pubkeys_list,
+                &party0_shares,
+                &vss_schemes,
+                &parties,
+                parties[0],
+            )
+            .err()
+            .unwrap();
+        assert_eq!(err, Error::InvalidSS(vec![3]));
+
+        // a missing share
+        let err = keypairs[0]
+            .phase2_verify_vss_construct_keypair(
+                &params,
+                &pubkeys_list,
+                &party0_shares[..2],
+                &vss_schemes,
+                &parties,
+                parties[0],
+            )
+            .err()
+            .unwrap();
+        assert_eq!(
+            err,
+            Error::WrongLength {
+                expected: 3,
+                received: 2
+            }
+        );
+    }
+
+    #[test]
+    fn test_phase1_verify_com_identifies_bad_decommitments() {
+        let mut rng =
+            deterministic_fast_rand("test_phase1_verify_com_identifies_bad_decommitments", None);
+        let params = Parameters {
+            threshold: 1,
+            share_count: 3,
+        };
+        let parties: Vec<_> = (1..=3).collect();
+        let keypairs: Vec<_> = parties.iter().copied().map(Keys::phase1_create).collect();
+        let (first_msgs, mut blinds): (Vec<_>, Vec<_>) = keypairs
+            .iter()
+            .map(|keypair| keypair.phase1_broadcast_rng(&mut rng))
+            .unzip();
+        let pubkeys_list: Vec<_> = keypairs
+            .iter()
+            .map(|k| k.keypair.public_key.clone())
+            .collect();
+
+        let distribute = |blinds: &[_], pubkeys_list: &[_], parties: &[u16]| {
+            keypairs[0]
+                .phase1_verify_com_phase2_distribute(
+                    &params,
+                    blinds,
+                    pubkeys_list,
+                    &first_msgs,
+                    parties,
+                )
+                .err()
+        };
+        assert_eq!(
+            distribute(&blinds, &pubkeys_list[..2], &parties),
+            Some(Error::WrongLength {
+                expected: 3,
+                received: 2
+            })
+        );
+        assert_eq!(
+            distribute(&blinds, &pubkeys_list, &parties[1..]),
+            Some(Error::WrongLength {
+                expected: 3,
+                received: 2
+            })
+        );
+        // parties 2 and 3 open their commitments to each other's blind factor
+        blinds.swap(1, 2);
+        assert_eq!(
+            distribute(&blinds, &pubkeys_list, &parties),
+            Some(Error::InvalidDecom(vec![2, 3]))
+        );
+
+        // signing needs more than t parties
+        let message: [u8; 4] = [79, 77, 69, 82];
+        let eph_key = EphemeralKey::ephermeral_key_create_from_deterministic_secret_rng(
+            &keypairs[0],
+            &message,
+            1,
+            &mut rng,
+        );
+        let (bc1, blind) = eph_key.phase1_broadcast_rng(&mut rng);
+        assert_eq!(
+            eph_key
+                .phase1_verify_com_phase2_distribute(
+                    &params,
+                    &[blind],
+                    std::slice::from_ref(&eph_key.R_i),
+                    &[bc1],
+                    &parties[..1],
+                )
+                .err(),
+            Some(Error::WrongPartyCount {
+                threshold: 1,
+                share_count: 3,
+                received: 1
+            })
+        );
+    }
+
+    #[test]
+    fn test_serde_roundtrip() {
+        let mut rng = deterministic_fast_rand("thresholdsig_test_serde_roundtrip", None);
+        let (t, n) = (1u16, 3u16);
+        let params = Parameters {
+            threshold: t,
+            share_count: n,
+        };
+        assert_eq!(assert_serde_roundtrip(&params), params);
+
+        let parties: Vec<_> = (1..=n).collect();
+        let (keys_vec, shared_keys_vec, _, vss_vec) = keygen_t_n_parties(t, n, &parties, &mut rng);
+        let keys = assert_serde_roundtrip(&keys_vec[0]);
+        assert_eq!(keys.party_index, keys_vec[0].party_index);
+        assert_eq!(keys.keypair.public_key, keys_vec[0].keypair.public_key);
+        let (bc1, blind) = keys.phase1_broadcast_rng(&mut rng);
+        assert_eq!(assert_serde_roundtrip(&bc1), bc1);
+        assert_eq!(assert_serde_roundtrip(&blind), blind);
+        assert_eq!(assert_serde_roundtrip(&vss_vec[0]), vss_vec[0]);
+        let shared_keys = assert_serde_roundtrip(&shared_keys_vec[0]);
+        assert_eq!(shared_keys.x_i, shared_keys_vec[0].x_i);
+
+        let message: [u8; 4] = [79, 77, 69, 82];
+        let eph_key = EphemeralKey::ephermeral_key_create_from_deterministic_secret_rng(
+            &keys, &message, 1, &mut rng,
+        );
+        let decoded = assert_serde_roundtrip(&eph_key);
+        assert_eq!(decoded.R_i, eph_key.R_i);
+        let (eph_shared_keys_vec, _, _) =
+            eph_keygen_t_n_parties(t, t + 1, &parties[..2], &keys_vec, &message, &mut rng);
+        let eph_shared_keys = assert_serde_roundtrip(&eph_shared_keys_vec[0]);
+        assert_eq!(eph_shared_keys.r_i, eph_shared_keys_vec[0].r_i);
+
+        let local_sig = LocalSig::compute(
+            &message,
+            &eph_shared_keys,
+            &shared_keys,
+            &SignatureMode::Pure,
+        );
+        assert_eq!(assert_serde_roundtrip(&local_sig), local_sig);
+    }
+
+    #[test]
+    fn test_debug_redacts_secrets() {
+        let mut rng = deterministic_fast_rand("thresholdsig_test_debug_redacts_secrets", None);
+        let (t, n) = (1u16, 3u16);
+        let parties: Vec<_> = (1..=n).collect();
+        let (keys_vec, shared_keys_vec, _, _) = keygen_t_n_parties(t, n, &parties, &mut rng);
+        let shared_keys = format!("{:?}", shared_keys_vec[0]);
+        assert!(!shared_keys.contains(&format!("{:?}", shared_keys_vec[0].x_i)));
+        assert!(shared_keys.contains(&format!("{:?}", shared_keys_vec[0].y)));
+
+        let message: [u8; 4] = [79, 77, 69, 82];
+        let eph_key = EphemeralKey::ephermeral_key_create_from_deterministic_secret_rng(
+            &keys_vec[0],
+            &message,
+            1,
+            &mut rng,
+        );
+        assert!(!format!("{:?}", eph_key).contains(&format!("{:?}", eph_key.r_i)));
+        let (eph_shared_keys_vec, _, _) =
+            eph_keygen_t_n_parties(t, t + 1, &parties[..2], &keys_vec, &message, &mut rng);
+        let eph_shared_keys = &eph_shared_keys_vec[0];
+        assert!(!format!("{:?}", eph_shared_keys).contains(&format!("{:?}", eph_shared_keys.r_i)));
+    }
+
     pub fn keygen_t_n_parties(
         t: u16,
         n: u16,
@@ -277,6 +709,7 @@ mod tests {
                         &pubkeys_list,
                         secret_shares,
                         &vss_schemes,
+                        parties,
                         index,
                     )
                     .unwrap()
@@ -288,7 +721,7 @@ mod tests {
 
     pub fn eph_keygen_t_n_parties(
         t: u16, // system threshold
-        n: u16, // number of signers
+        n: u16, // system share count
         parties: &[u16],
         keypairs: &[Keys],
         message: &[u8],
@@ -345,9 +778,9 @@ mod tests {
             })
             .unzip();
 
-        let nonce_parties_shares: Vec<Vec<_>> = (0..usize::from(n))
+        let nonce_parties_shares: Vec<Vec<_>> = (0..parties.len())
             .map(|i| {
-                (0..usize::from(n))
+                (0..parties.len())
                     .map(|j| nonce_secret_shares[j][i].clone())
                     .collect()
             })
@@ -365,6 +798,7 @@ mod tests {
                     &Rs,
                     nonce_secret_share,
                     &nonce_vss_schemes,
+                    parties,
                     index,
                 )
                 .unwrap()
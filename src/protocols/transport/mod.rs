@@ -0,0 +1,287 @@
+/*
+    multi-party-ed25519
+
+    Copyright 2018 by Kzen Networks
+
+    This file is part of multi-party-ed25519 library
+    (https://github.com/KZen-networks/multisig-schnorr)
+
+    multi-party-ed25519 is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-ed25519/blob/master/LICENSE>
+*/
+
+//! Drives a state machine over an asynchronous transport, for parties that run in separate
+//! processes. Needs the `async` feature.
+//!
+//! A transport is a `Sink` of the messages a party sends and a `Stream` of the messages addressed
+//! to it, routed by party index as `Transport` describes. `Run` is a future that sends what the
+//! party has to send, feeds it what it receives and resolves to its output, so it can be spawned
+//! on a tokio runtime like any other task.
+
+use futures::{ready, Sink, Stream};
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt;
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+
+use protocols::state_machine::{Msg, RoundError, StateMachine};
+use protocols::thresholdsig::state_machine::{KeyGen, LocalKey, Signing, ThresholdMessage};
+use protocols::thresholdsig::{Keys, Parameters};
+use protocols::SignatureMode;
+
+/// The messages of one party, to and from the other parties.
+///
+/// Any `Sink` and `Stream` of `Msg`s is a transport, as long as it routes them by party index:
+///
+/// * a message sent with `receiver: Some(party)` is delivered to `party` only, and should be kept
+///   from everyone else as it can hold a secret share,
+/// * a message sent with `receiver: None` is a broadcast, delivered to every other party,
+/// * the stream yields the messages sent to this party, with the `sender` they were sent with.
+///
+/// Messages don't have to arrive in order, the state machines keep early ones until they get to
+/// their round. `InMemory` is a transport that does this routing over channels.
+pub trait Transport<B>:
+    Stream<Item = io::Result<Msg<B>>> + Sink<Msg<B>, Error = io::Error> + Unpin
+{
+}
+
+impl<B, T> Transport<B> for T where
+    T: Stream<Item = io::Result<Msg<B>>> + Sink<Msg<B>, Error = io::Error> + Unpin
+{
+}
+
+#[derive(Debug)]
+pub enum RunError {
+    Round(RoundError),
+    Transport(io::Error),
+}
+
+impl fmt::Display for RunError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RunError::Round(error) => write!(f, "protocol failed: {}", error),
+            RunError::Transport(error) => write!(f, "transport failed: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for RunError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            RunError::Round(error) => Some(error),
+            RunError::Transport(error) => Some(error),
+        }
+    }
+}
+
+impl From<RoundError> for RunError {
+    fn from(error: RoundError) -> Self {
+        RunError::Round(error)
+    }
+}
+
+impl From<io::Error> for RunError {
+    fn from(error: io::Error) -> Self {
+        RunError::Transport(error)
+    }
+}
+
+/// Runs a party to the end over a transport.
+///
+/// Messages the party rejects without failing, like duplicates or messages from unknown parties,
+/// are dropped and the protocol goes on. If the transport ends before the party is done, the
+/// future fails with `RoundError::MissingMessages`.
+pub struct Run<M: StateMachine, T> {
+    party: M,
+    transport: T,
+    outgoing: VecDeque<Msg<M::MessageBody>>,
+    flushed: bool,
+}
+
+impl<M, T> Run<M, T>
+where
+    M: StateMachine + Unpin,
+    T: Transport<M::MessageBody>,
+{
+    pub fn new(party: M, transport: T) -> Run<M, T> {
+        Run {
+            party,
+            transport,
+            outgoing: VecDeque::new(),
+            flushed: true,
+        }
+    }
+}
+
+// nothing in a `Run` is ever pinned, the transport is only polled through `Pin::new`
+impl<M: StateMachine + Unpin, T: Unpin> Unpin for Run<M, T> {}
+
+impl<M, T> Future for Run<M, T>
+where
+    M: StateMachine + Unpin,
+    T: Transport<M::MessageBody>,
+{
+    type Output = Result<M::Output, RunError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        loop {
+            this.outgoing.extend(this.party.outgoing());
+            while !this.outgoing.is_empty() {
+                ready!(Pin::new(&mut this.transport).poll_ready(cx))?;
+                let msg = this.outgoing.pop_front().expect("not empty");
+                Pin::new(&mut this.transport).start_send(msg)?;
+                this.flushed = false;
+            }
+            if !this.flushed {
+                ready!(Pin::new(&mut this.transport).poll_flush(cx))?;
+                this.flushed = true;
+            }
+            if this.party.is_finished() {
+                return Poll::Ready(this.party.pick_output().map_err(RunError::Round));
+            }
+
+            match ready!(Pin::new(&mut this.transport).poll_next(cx)) {
+                Some(msg) => {
+                    if let Err(error @ RoundError::Protocol { .. }) =
+                        this.party.handle_incoming(msg?)
+                    {
+                        return Poll::Ready(Err(RunError::Round(error)));
+                    }
+                }
+                None => return Poll::Ready(this.party.pick_output().map_err(RunError::Round)),
+            }
+        }
+    }
+}
+
+/// Runs threshold key generation as the party `keys.party_index`, see `KeyGen::new`.
+pub fn keygen<T>(
+    keys: Keys,
+    params: Parameters,
+    parties: Vec<u16>,
+    transport: T,
+) -> Result<Run<KeyGen, T>, RoundError>
+where
+    T: Transport<ThresholdMessage>,
+{
+    Ok(Run::new(KeyGen::new(keys, params, parties)?, transport))
+}
+
+/// Runs threshold signing with a key from `keygen`, see `Signing::new`.
+pub fn sign<T>(
+    local_key: &LocalKey,
+    signers: Vec<u16>,
+    message: &[u8],
+    mode: SignatureMode,
+    transport: T,
+) -> Result<Run<Signing, T>, RoundError>
+where
+    T: Transport<ThresholdMessage>,
+{
+    Ok(Run::new(
+        Signing::new(local_key, signers, message, mode)?,
+        transport,
+    ))
+}
+
+/// A transport over in-process channels, for tests and for running several parties in one
+/// process.
+pub struct InMemory<B> {
+    party_index: u16,
+    incoming: UnboundedReceiver<Msg<B>>,
+    others: BTreeMap<u16, UnboundedSender<Msg<B>>>,
+}
+
+impl<B> InMemory<B> {
+    /// Connects `parties` to each other, returns their transports in the same order.
+    pub fn network(parties: &[u16]) -> Vec<InMemory<B>> {
+        let (senders, receivers): (Vec<_>, Vec<_>) =
+            parties.iter().map(|_| mpsc::unbounded_channel()).unzip();
+        parties
+            .iter()
+            .zip(receivers)
+            .map(|(&party_index, incoming)| InMemory {
+                party_index,
+                incoming,
+                others: parties
+                    .iter()
+                    .cloned()
+                    .zip(senders.iter().cloned())
+                    .filter(|(index, _)| *index != party_index)
+                    .collect(),
+            })
+            .collect()
+    }
+
+    pub fn party_index(&self) -> u16 {
+        self.party_index
+    }
+
+    /// Sends `msg` to the party `receiver` only.
+    pub fn send_to(&self, receiver: u16, msg: Msg<B>) -> io::Result<()> {
+        let sender = self.others.get(&receiver).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("no party with index {}", receiver),
+            )
+        })?;
+        sender.send(msg).map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::NotConnected,
+                format!("party {} is gone", receiver),
+            )
+        })
+    }
+
+    /// Sends `msg` to every other party.
+    pub fn broadcast(&self, msg: Msg<B>) -> io::Result<()>
+    where
+        B: Clone,
+    {
+        for receiver in self.others.keys() {
+            self.send_to(*receiver, msg.clone())?;
+        }
+        Ok(())
+    }
+}
+
+impl<B: Clone> Sink<Msg<B>> for InMemory<B> {
+    type Error = io::Error;
+
+    fn poll_ready(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn start_send(self: Pin<&mut Self>, msg: Msg<B>) -> io::Result<()> {
+        match msg.receiver {
+            Some(receiver) => self.send_to(receiver, msg),
+            None => self.broadcast(msg),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<B> Stream for InMemory<B> {
+    type Item = io::Result<Msg<B>>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        self.incoming.poll_recv(cx).map(|msg| msg.map(Ok))
+    }
+}
+
+mod test;
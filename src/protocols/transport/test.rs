@@ -0,0 +1,88 @@
+/*
+    multi-party-ed25519
+
+    Copyright 2018 by Kzen Networks
+
+    This file is part of multi-party-ed25519 library
+    (https://github.com/KZen-networks/multisig-schnorr)
+
+    multi-party-ed25519 is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-ed25519/blob/master/LICENSE>
+*/
+
+#[cfg(test)]
+mod tests {
+    use futures::future;
+    use tokio::runtime::Runtime;
+
+    use protocols::state_machine::Msg;
+    use protocols::tests::verify_dalek;
+    use protocols::thresholdsig::{Keys, Parameters};
+    use protocols::transport::{self, InMemory};
+    use protocols::SignatureMode;
+
+    #[test]
+    fn test_keygen_and_signing_over_in_memory_transport() {
+        let runtime = Runtime::new().unwrap();
+        let message = [79, 77, 69, 82];
+        let params = Parameters {
+            threshold: 1,
+            share_count: 3,
+        };
+        let parties = vec![1, 2, 3];
+
+        let keygen = InMemory::network(&parties).into_iter().map(|channel| {
+            let keys = Keys::phase1_create(channel.party_index());
+            let party = transport::keygen(keys, params.clone(), parties.clone(), channel).unwrap();
+            runtime.spawn(party)
+        });
+        let local_keys: Vec<_> = runtime
+            .block_on(future::join_all(keygen))
+            .into_iter()
+            .map(|result| result.unwrap().unwrap())
+            .collect();
+        let y = &local_keys[0].shared_keys.y;
+        assert!(local_keys.iter().all(|key| &key.shared_keys.y == y));
+
+        let signers = vec![1, 3];
+        let signing = InMemory::network(&signers).into_iter().map(|channel| {
+            let local_key = &local_keys[usize::from(channel.party_index() - 1)];
+            let party = transport::sign(
+                local_key,
+                signers.clone(),
+                &message,
+                SignatureMode::Pure,
+                channel,
+            )
+            .unwrap();
+            runtime.spawn(party)
+        });
+        for result in runtime.block_on(future::join_all(signing)) {
+            let signature = result.unwrap().unwrap();
+            assert!(verify_dalek(y, &signature, &message));
+        }
+    }
+
+    #[test]
+    fn test_in_memory_routes_by_party_index() {
+        let mut network = InMemory::network(&[1, 2, 3]);
+        let msg = |receiver, body| Msg {
+            sender: 1,
+            receiver,
+            body,
+        };
+        network[0].send_to(3, msg(Some(3), 7u8)).unwrap();
+        network[0].broadcast(msg(None, 8)).unwrap();
+        assert!(network[0].send_to(1, msg(Some(1), 9)).is_err());
+
+        assert!(network[0].incoming.try_recv().is_err());
+        assert_eq!(network[1].incoming.try_recv().unwrap(), msg(None, 8));
+        assert!(network[1].incoming.try_recv().is_err());
+        assert_eq!(network[2].incoming.try_recv().unwrap(), msg(Some(3), 7));
+        assert_eq!(network[2].incoming.try_recv().unwrap(), msg(None, 8));
+    }
+}